@@ -0,0 +1,82 @@
+use rust_args_parser as ap;
+use splicer::ipc::{Request, Response};
+use splicer::server::acl::{Grantee, Role};
+
+pub fn grant_command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("grant"),
+        Some(|args, ctx: &mut super::Context| {
+            let grantee: Grantee = args[1].parse()?;
+            let role: Role = args[2].parse()?;
+            super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let session = super::resolve_session(&mut client, args[0]).await?;
+                super::request(&mut client, Request::Grant { session, grantee, role }).await.map(drop)
+            })?;
+            Ok(())
+        }),
+    )
+    .desc("Let another user, or one connection, into a session")
+    .pos([
+        ap::PosSpec::new("SESSION").one().desc("Session name or ID"),
+        ap::PosSpec::new("WHO").one().desc("uid:N (or just N) for a user, peer:N for one connection"),
+        ap::PosSpec::new("ROLE").one().desc("view, input (view and type) or admin (everything)"),
+    ])
+}
+
+pub fn revoke_command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("revoke"),
+        Some(|args, ctx: &mut super::Context| {
+            let grantee: Grantee = args[1].parse()?;
+            super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let session = super::resolve_session(&mut client, args[0]).await?;
+                super::request(&mut client, Request::Revoke { session, grantee }).await.map(drop)
+            })?;
+            Ok(())
+        }),
+    )
+    .desc("Take a grant back; peers left without access are detached")
+    .pos([
+        ap::PosSpec::new("SESSION").one().desc("Session name or ID"),
+        ap::PosSpec::new("WHO").one().desc("uid:N (or just N) for a user, peer:N for one connection"),
+    ])
+}
+
+pub fn show_command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("access"),
+        Some(|args, ctx: &mut super::Context| {
+            let grants = super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let session = super::resolve_session(&mut client, args[0]).await?;
+                match super::request(&mut client, Request::ShowAccess { session }).await? {
+                    Response::Access { grants } => Ok(grants),
+                    _ => Err(splicer::Error::Ipc("unexpected response".into())),
+                }
+            })?;
+            if ctx.json {
+                let grants: Vec<_> = grants
+                    .iter()
+                    .map(|(who, role)| serde_json::json!({"who": who.to_string(), "role": role.to_string()}))
+                    .collect();
+                println!("{}", serde_json::Value::Array(grants));
+            } else {
+                for (who, role) in grants {
+                    println!("{who} {role}");
+                }
+            }
+            Ok(())
+        }),
+    )
+    .desc("Show who besides the server's owner may use a session")
+    .opts([ap::OptSpec::new("json", |_, ctx: &mut super::Context| {
+        ctx.json = true;
+        Ok(())
+    })
+    .short('j')
+    .flag()
+    .help("JSON output")])
+    .pos([ap::PosSpec::new("SESSION").one().desc("Session name or ID")])
+}
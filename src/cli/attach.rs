@@ -4,28 +4,123 @@ use rust_args_parser as ap;
 pub struct AttachContext {
     window: Option<String>,
     pane: Option<String>,
+    prefix: Option<String>,
+    config: Option<std::path::PathBuf>,
+    backpressure: Option<splicer::pty::Backpressure>,
+    fps: Option<u16>,
+    diffs: bool,
+    read_only: bool,
 }
 
 pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
-    ap::CmdSpec::new(Some("attach"), None)
-        .desc("Attach to a session")
-        .opts([
-            ap::OptSpec::new("window", |s, ctx: &mut super::Context| {
-                ctx.attach.window = s.map(|s| s.to_string());
-                Ok(())
-            })
-            .short('w')
-            .required()
-            .metavar("WINDOW")
-            .help("Window name or ID"),
-            ap::OptSpec::new("pane", |s, ctx: &mut super::Context| {
-                ctx.attach.pane = s.map(|s| s.to_string());
-                Ok(())
-            })
-            .short('p')
-            .required()
-            .metavar("PANE")
-            .help("Pane name or ID"),
-        ])
-        .pos([ap::PosSpec::new("SESSION").one().desc("Session name or ID")])
+    ap::CmdSpec::new(
+        Some("attach"),
+        Some(|args, ctx: &mut super::Context| {
+            let session = args.first().copied().unwrap_or_default();
+            super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let session = super::resolve_session(&mut client, session).await?;
+                drop(client);
+                let window = ctx.attach.window.as_deref().map(|w| super::parse_id("window", w)).transpose()?;
+                let pane = ctx.attach.pane.as_deref().map(|p| super::parse_id("pane", p)).transpose()?;
+                let prefix = ctx.attach.prefix.as_deref().map(str::parse).transpose()?;
+                let config = ctx.attach.config.clone().or_else(default_config).filter(|p| p.exists());
+                splicer::client::attach(splicer::client::AttachOptions {
+                    socket: super::socket_path(ctx),
+                    compress: super::compress(ctx),
+                    session,
+                    window,
+                    pane,
+                    prefix,
+                    config,
+                    backpressure: ctx.attach.backpressure,
+                    fps: ctx.attach.fps,
+                    diffs: ctx.attach.diffs,
+                    read_only: ctx.attach.read_only,
+                })
+                .await
+            })?;
+            Ok(())
+        }),
+    )
+    .desc("Attach to a session")
+    .opts([
+        ap::OptSpec::new("window", |s, ctx: &mut super::Context| {
+            ctx.attach.window = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('w')
+        .required()
+        .metavar("WINDOW")
+        .help("Window name or ID"),
+        ap::OptSpec::new("pane", |s, ctx: &mut super::Context| {
+            ctx.attach.pane = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('p')
+        .required()
+        .metavar("PANE")
+        .help("Pane name or ID"),
+        ap::OptSpec::new("prefix", |s, ctx: &mut super::Context| {
+            ctx.attach.prefix = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .required()
+        .metavar("KEY")
+        .help("Prefix key, e.g. C-a (default C-b)")
+        .env("SPLICER_PREFIX"),
+        ap::OptSpec::new("compress", |_, ctx: &mut super::Context| {
+            ctx.compress = true;
+            Ok(())
+        })
+        .flag()
+        .help("Compress large frames, for a socket forwarded over a slow link (or set SPLICER_COMPRESS)"),
+        ap::OptSpec::new("config", |s, ctx: &mut super::Context| {
+            ctx.attach.config = s.map(std::path::PathBuf::from);
+            Ok(())
+        })
+        .required()
+        .metavar("PATH")
+        .help("Lua config file"),
+        ap::OptSpec::new("backpressure", |s, ctx: &mut super::Context| {
+            let s = s.unwrap_or_default();
+            let policy =
+                s.parse().map_err(|_| splicer::Error::UserInput(format!("backpressure must be drop or block: {s}")))?;
+            ctx.attach.backpressure = Some(policy);
+            Ok(())
+        })
+        .required()
+        .metavar("POLICY")
+        .help("When this client falls behind: drop output and redraw (default), or block the pane"),
+        ap::OptSpec::new("fps", |s, ctx: &mut super::Context| {
+            let s = s.unwrap_or_default();
+            let fps = s.parse().map_err(|_| splicer::Error::UserInput(format!("fps must be a number: {s}")))?;
+            ctx.attach.fps = Some(fps);
+            Ok(())
+        })
+        .required()
+        .metavar("N")
+        .help("Most output frames per second (default 60, 0 for unpaced)"),
+        ap::OptSpec::new("diffs", |_, ctx: &mut super::Context| {
+            ctx.attach.diffs = true;
+            Ok(())
+        })
+        .flag()
+        .help("Receive screen updates instead of the raw output, e.g. over a slow link"),
+        ap::OptSpec::new("read-only", |_, ctx: &mut super::Context| {
+            ctx.attach.read_only = true;
+            Ok(())
+        })
+        .short('r')
+        .flag()
+        .help("Watch without typing into or resizing the pane"),
+    ])
+    .pos([ap::PosSpec::new("SESSION").one().desc("Session name or ID")])
+}
+
+fn default_config() -> Option<std::path::PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| std::path::PathBuf::from(h).join(".config")))?;
+    Some(base.join("splicer").join("init.lua"))
 }
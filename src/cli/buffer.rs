@@ -0,0 +1,183 @@
+use rust_args_parser as ap;
+use splicer::ipc::{Request, Response};
+use std::io::{Read, Write};
+
+#[derive(Default)]
+pub struct BufferContext {
+    name: Option<String>,
+    pane: Option<String>,
+    delete: bool,
+}
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(Some("buffer"), None).desc("Manage paste buffers").subs([
+        ap::CmdSpec::new(
+            Some("list"),
+            Some(|_, ctx: &mut super::Context| {
+                let items = super::block_on(async {
+                    let mut client = super::connect(ctx).await?;
+                    match super::request(&mut client, Request::ListBuffers).await? {
+                        Response::Buffers { items } => Ok(items),
+                        _ => Err(splicer::Error::Ipc("unexpected response".into())),
+                    }
+                })?;
+                if ctx.json {
+                    let items: Vec<_> = items
+                        .iter()
+                        .map(|b| serde_json::json!({ "name": b.name, "size": b.size, "automatic": b.automatic }))
+                        .collect();
+                    println!("{}", serde_json::Value::from(items));
+                } else {
+                    for b in items {
+                        println!("{}: {} bytes", b.name, b.size);
+                    }
+                }
+                Ok(())
+            }),
+        )
+        .aliases(["ls"])
+        .desc("List buffers, newest first")
+        .opts([json_opt()]),
+        ap::CmdSpec::new(
+            Some("set"),
+            Some(|args, ctx: &mut super::Context| {
+                let data = args.join(" ").into_bytes();
+                set(ctx, data)
+            }),
+        )
+        .desc("Set a buffer from the command line")
+        .opts([name_opt()])
+        .pos([ap::PosSpec::new("DATA").range(1, usize::MAX).desc("Buffer contents")]),
+        ap::CmdSpec::new(
+            Some("show"),
+            Some(|_, ctx: &mut super::Context| {
+                let data = show(ctx)?;
+                std::io::stdout().write_all(&data)?;
+                Ok(())
+            }),
+        )
+        .desc("Print a buffer")
+        .opts([name_opt()]),
+        ap::CmdSpec::new(
+            Some("delete"),
+            Some(|_, ctx: &mut super::Context| {
+                let name = ctx.buffer.name.clone();
+                super::block_on(async {
+                    let mut client = super::connect(ctx).await?;
+                    super::request(&mut client, Request::DeleteBuffer { name }).await.map(drop)
+                })?;
+                Ok(())
+            }),
+        )
+        .desc("Delete a buffer")
+        .opts([name_opt()]),
+        ap::CmdSpec::new(
+            Some("paste"),
+            Some(|_, ctx: &mut super::Context| {
+                let pane = super::target_pane(ctx.buffer.pane.as_deref())?;
+                let (name, delete) = (ctx.buffer.name.clone(), ctx.buffer.delete);
+                super::block_on(async {
+                    let mut client = super::connect(ctx).await?;
+                    super::request(&mut client, Request::PasteBuffer { pane, name, delete }).await.map(drop)
+                })?;
+                Ok(())
+            }),
+        )
+        .desc("Paste a buffer into a pane")
+        .opts([
+            name_opt(),
+            ap::OptSpec::new("pane", |s, ctx: &mut super::Context| {
+                ctx.buffer.pane = s.map(|s| s.to_string());
+                Ok(())
+            })
+            .short('p')
+            .required()
+            .metavar("PANE")
+            .help("Target pane ID (default $SPLICER_PANE)"),
+            ap::OptSpec::new("delete", |_, ctx: &mut super::Context| {
+                ctx.buffer.delete = true;
+                Ok(())
+            })
+            .short('d')
+            .flag()
+            .help("Delete the buffer after pasting"),
+        ]),
+        ap::CmdSpec::new(
+            Some("load"),
+            Some(|args, ctx: &mut super::Context| {
+                let mut data = Vec::new();
+                match args.first().copied().unwrap_or("-") {
+                    "-" => {
+                        std::io::stdin().read_to_end(&mut data)?;
+                    }
+                    path => data = std::fs::read(path)?,
+                }
+                set(ctx, data)
+            }),
+        )
+        .desc("Load a buffer from a file")
+        .opts([name_opt()])
+        .pos([ap::PosSpec::new("FILE").one().desc("Path, or - for stdin")]),
+        ap::CmdSpec::new(
+            Some("save"),
+            Some(|args, ctx: &mut super::Context| {
+                let data = show(ctx)?;
+                match args.first().copied().unwrap_or("-") {
+                    "-" => std::io::stdout().write_all(&data)?,
+                    path => std::fs::write(path, data)?,
+                }
+                Ok(())
+            }),
+        )
+        .desc("Save a buffer to a file")
+        .opts([name_opt()])
+        .pos([ap::PosSpec::new("FILE").one().desc("Path, or - for stdout")]),
+    ])
+}
+
+fn name_opt<'a>() -> ap::OptSpec<'a, super::Context> {
+    ap::OptSpec::new("buffer", |s, ctx: &mut super::Context| {
+        ctx.buffer.name = s.map(|s| s.to_string());
+        Ok(())
+    })
+    .short('b')
+    .required()
+    .metavar("NAME")
+    .help("Buffer name (default: newest, or a new automatic buffer)")
+}
+
+fn json_opt<'a>() -> ap::OptSpec<'a, super::Context> {
+    ap::OptSpec::new("json", |_, ctx: &mut super::Context| {
+        ctx.json = true;
+        Ok(())
+    })
+    .short('j')
+    .flag()
+    .help("JSON output")
+}
+
+fn set(ctx: &super::Context, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+    let name = ctx.buffer.name.clone();
+    let name = super::block_on(async {
+        let mut client = super::connect(ctx).await?;
+        match super::request(&mut client, Request::SetBuffer { name, data }).await? {
+            Response::BufferSet { name } => Ok(name),
+            _ => Err(splicer::Error::Ipc("unexpected response".into())),
+        }
+    })?;
+    if !ctx.quiet {
+        println!("{name}");
+    }
+    Ok(())
+}
+
+fn show(ctx: &super::Context) -> splicer::Result<Vec<u8>> {
+    let name = ctx.buffer.name.clone();
+    super::block_on(async {
+        let mut client = super::connect(ctx).await?;
+        match super::request(&mut client, Request::ShowBuffer { name }).await? {
+            Response::Buffer { data, .. } => Ok(data),
+            _ => Err(splicer::Error::Ipc("unexpected response".into())),
+        }
+    })
+}
@@ -0,0 +1,75 @@
+use rust_args_parser as ap;
+use splicer::ipc::{Request, Response, StateScope};
+use splicer::server::session::ClipboardPolicy;
+
+#[derive(Default)]
+pub struct ClipboardContext {
+    read: Option<bool>,
+    write: Option<bool>,
+}
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("clipboard"),
+        Some(|args, ctx: &mut super::Context| {
+            let session = args.first().copied().unwrap_or_default();
+            let policy = super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let session = super::resolve_session(&mut client, session).await?;
+                let Response::State { json } =
+                    super::request(&mut client, Request::GetState { scope: StateScope::Sessions }).await?
+                else {
+                    return Err(splicer::Error::Ipc("unexpected response".into()));
+                };
+                let current = json
+                    .as_array()
+                    .and_then(|a| a.iter().find(|s| s["id"] == session.to_string()))
+                    .map(|s| &s["clipboard"]);
+                let get = |k: &str| current.and_then(|c| c[k].as_bool());
+                let default = ClipboardPolicy::default();
+                let policy = ClipboardPolicy {
+                    read: ctx.clipboard.read.or(get("read")).unwrap_or(default.read),
+                    write: ctx.clipboard.write.or(get("write")).unwrap_or(default.write),
+                };
+                if ctx.clipboard.read.is_some() || ctx.clipboard.write.is_some() {
+                    super::request(&mut client, Request::SetClipboardPolicy { session, policy }).await?;
+                }
+                Ok(policy)
+            })?;
+            if !ctx.quiet {
+                println!("read={} write={}", on_off(policy.read), on_off(policy.write));
+            }
+            Ok(())
+        }),
+    )
+    .desc("Show or set a session's OSC 52 clipboard policy")
+    .opts([
+        ap::OptSpec::new("read", |s, ctx: &mut super::Context| {
+            ctx.clipboard.read = Some(parse_switch(s.unwrap_or_default())?);
+            Ok(())
+        })
+        .required()
+        .metavar("on|off")
+        .help("Let programs query the clipboard"),
+        ap::OptSpec::new("write", |s, ctx: &mut super::Context| {
+            ctx.clipboard.write = Some(parse_switch(s.unwrap_or_default())?);
+            Ok(())
+        })
+        .required()
+        .metavar("on|off")
+        .help("Let programs set the clipboard"),
+    ])
+    .pos([ap::PosSpec::new("SESSION").one().desc("Session name or ID")])
+}
+
+fn parse_switch(s: &str) -> splicer::Result<bool> {
+    match s {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err(splicer::Error::UserInput(format!("expected on or off, got {s:?}"))),
+    }
+}
+
+fn on_off(b: bool) -> &'static str {
+    if b { "on" } else { "off" }
+}
@@ -0,0 +1,59 @@
+use rust_args_parser as ap;
+use splicer::ipc::{Request, Response};
+
+pub fn set_command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("set-environment"),
+        Some(|args, ctx: &mut super::Context| {
+            let name = args[1].to_string();
+            let value = args.get(2).map(|s| s.to_string());
+            super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let session = super::resolve_session(&mut client, args[0]).await?;
+                super::request(&mut client, Request::SetEnvironment { session, name, value }).await.map(drop)
+            })?;
+            Ok(())
+        }),
+    )
+    .aliases(["setenv"])
+    .desc("Set a variable new panes of a session inherit; omit VALUE to unset")
+    .pos([
+        ap::PosSpec::new("SESSION").one().desc("Session name or ID"),
+        ap::PosSpec::new("NAME").one().desc("Variable name"),
+        ap::PosSpec::new("VALUE").range(0, 1).desc("Value"),
+    ])
+}
+
+pub fn show_command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("show-environment"),
+        Some(|args, ctx: &mut super::Context| {
+            let vars = super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let session = super::resolve_session(&mut client, args[0]).await?;
+                match super::request(&mut client, Request::ShowEnvironment { session }).await? {
+                    Response::Environment { vars } => Ok(vars),
+                    _ => Err(splicer::Error::Ipc("unexpected response".into())),
+                }
+            })?;
+            if ctx.json {
+                println!("{}", serde_json::Value::Object(vars.into_iter().map(|(k, v)| (k, v.into())).collect()));
+            } else {
+                for (k, v) in vars {
+                    println!("{k}={v}");
+                }
+            }
+            Ok(())
+        }),
+    )
+    .aliases(["showenv"])
+    .desc("Show a session's environment")
+    .opts([ap::OptSpec::new("json", |_, ctx: &mut super::Context| {
+        ctx.json = true;
+        Ok(())
+    })
+    .short('j')
+    .flag()
+    .help("JSON output")])
+    .pos([ap::PosSpec::new("SESSION").one().desc("Session name or ID")])
+}
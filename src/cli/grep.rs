@@ -0,0 +1,111 @@
+use rust_args_parser as ap;
+use splicer::ipc::{GrepScope, Request, Response};
+
+#[derive(Default)]
+pub struct GrepContext {
+    session: Option<String>,
+    window: Option<String>,
+    context: usize,
+    ignore_case: bool,
+}
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("grep"),
+        Some(|args, ctx: &mut super::Context| {
+            let pattern = args.first().copied().unwrap_or_default().to_string();
+            let items = super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let scope = match (&ctx.grep.session, &ctx.grep.window) {
+                    (_, Some(w)) => GrepScope::Window(super::parse_id("window", w)?),
+                    (Some(s), None) => GrepScope::Session(super::resolve_session(&mut client, s).await?),
+                    (None, None) => GrepScope::All,
+                };
+                let req =
+                    Request::Grep { pattern, scope, context: ctx.grep.context, ignore_case: ctx.grep.ignore_case };
+                match super::request(&mut client, req).await? {
+                    Response::Matches { items } => Ok(items),
+                    _ => Err(splicer::Error::Ipc("unexpected response".into())),
+                }
+            })?;
+
+            if ctx.json {
+                let items: Vec<_> = items
+                    .iter()
+                    .map(|m| {
+                        serde_json::json!({
+                            "session": m.session.to_string(),
+                            "window": m.window.to_string(),
+                            "pane": m.pane.to_string(),
+                            "line": m.line,
+                            "text": m.text,
+                            "before": m.before,
+                            "after": m.after,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::Value::from(items));
+                return Ok(());
+            }
+            // grep-style: `:` marks the matching line, `-` context, `--` separates groups
+            for (i, m) in items.iter().enumerate() {
+                let at = format!("{}/{}/{}", m.session, m.window, m.pane);
+                if ctx.grep.context > 0 && i > 0 {
+                    println!("--");
+                }
+                let first = m.line - m.before.len();
+                for (n, l) in m.before.iter().enumerate() {
+                    println!("{at}-{}-{l}", first + n);
+                }
+                println!("{at}:{}:{}", m.line, m.text);
+                for (n, l) in m.after.iter().enumerate() {
+                    println!("{at}-{}-{l}", m.line + 1 + n);
+                }
+            }
+            Ok(())
+        }),
+    )
+    .desc("Search pane screens and scrollback")
+    .opts([
+        ap::OptSpec::new("session", |s, ctx: &mut super::Context| {
+            ctx.grep.session = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('s')
+        .required()
+        .metavar("SESSION")
+        .help("Only panes of this session (name or ID)"),
+        ap::OptSpec::new("window", |s, ctx: &mut super::Context| {
+            ctx.grep.window = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('w')
+        .required()
+        .metavar("WINDOW")
+        .help("Only panes of this window"),
+        ap::OptSpec::new("context", |s, ctx: &mut super::Context| {
+            let s = s.unwrap_or_default();
+            ctx.grep.context = s.parse().map_err(|_| splicer::Error::UserInput(format!("invalid line count: {s}")))?;
+            Ok(())
+        })
+        .short('C')
+        .required()
+        .metavar("N")
+        .help("Lines of context around each match"),
+        ap::OptSpec::new("ignore-case", |_, ctx: &mut super::Context| {
+            ctx.grep.ignore_case = true;
+            Ok(())
+        })
+        .short('i')
+        .flag()
+        .help("Case-insensitive match"),
+        ap::OptSpec::new("json", |_, ctx: &mut super::Context| {
+            ctx.json = true;
+            Ok(())
+        })
+        .short('j')
+        .flag()
+        .help("JSON output"),
+    ])
+    .pos([ap::PosSpec::new("PATTERN").one().desc("Regular expression")])
+}
@@ -0,0 +1,23 @@
+use rust_args_parser as ap;
+use splicer::ipc::Request;
+use splicer::server::pane::InputMode;
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("input-mode"),
+        Some(|args, ctx: &mut super::Context| {
+            let (pane, mode) = super::target_pane_and(args)?;
+            let mode: InputMode = mode.parse()?;
+            super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                super::request(&mut client, Request::SetInputMode { pane, mode }).await.map(drop)
+            })?;
+            Ok(())
+        }),
+    )
+    .desc("Set who may type into a pane")
+    .pos([
+        ap::PosSpec::new("PANE").range(0, 1).desc("Pane ID (default $SPLICER_PANE)"),
+        ap::PosSpec::new("MODE").one().desc("exclusive (one owner at a time) or shared (every attached client)"),
+    ])
+}
@@ -1,68 +1,94 @@
 use rust_args_parser as ap;
-use splicer::server::{pane::TermSize, state::ServerState};
+use splicer::ipc::{KillTarget, Request, Response};
 
 #[derive(Default)]
 pub struct KillContext {
     force: bool,
-    session: Option<String>,
-    window: Option<String>,
-    pane: Option<String>,
+    grace: Option<std::time::Duration>,
 }
 
-pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
-    ap::CmdSpec::new(
-        Some("kill"),
-        Some(|_, _ctx: &mut super::Context| {
-            use splicer::server::window::WindowId;
-            let mut alloc = splicer::server::IdAllocator::default();
-            for _ in 2000..2005 {
-                let win_id = alloc.allocate(WindowId::new);
-                println!("window id: {}", win_id);
-                let win = splicer::server::window::Window::new(win_id, "test");
-                println!("window: {}", win);
-            }
-
-            let mut st = ServerState::new();
-            let sid = st.new_session("work");
-            let wid = st.new_window(sid, "main")?;
-            let pid = st.new_pane(sid, wid, "shell", TermSize::new(120, 34))?;
-            println!("state: {}; sid={}; wid={}; pid={}", st, sid, wid, pid);
+enum Target<'a> {
+    Session(&'a str),
+    Window(&'a str),
+    Pane(Option<&'a str>),
+}
 
-            Ok(())
-        }),
-    )
-    .desc("Destroy a session/window/pane")
-    .opts([ap::OptSpec::new("force", |_, ctx: &mut super::Context| {
-        ctx.kill.force = true;
-        Ok(())
-    })
-    .short('f')
-    .flag()
-    .help("Force kill")])
-    .subs([
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(Some("kill"), None).desc("Destroy a session/window/pane").subs([
         ap::CmdSpec::new(
             Some("session"),
-            Some(|args, ctx: &mut super::Context| {
-                ctx.kill.session = args.first().map(|s| s.to_string());
-                Ok(())
-            }),
+            Some(|args, ctx: &mut super::Context| run(ctx, Target::Session(args.first().copied().unwrap_or_default()))),
         )
+        .opts(opts())
         .pos([ap::PosSpec::new("SESSION").one().desc("Session name or ID")]),
         ap::CmdSpec::new(
             Some("window"),
-            Some(|args, ctx: &mut super::Context| {
-                ctx.kill.window = args.first().map(|s| s.to_string());
-                Ok(())
-            }),
+            Some(|args, ctx: &mut super::Context| run(ctx, Target::Window(args.first().copied().unwrap_or_default()))),
         )
-        .pos([ap::PosSpec::new("WINDOW").one().desc("Window name or ID")]),
+        .opts(opts())
+        .pos([ap::PosSpec::new("WINDOW").one().desc("Window ID")]),
         ap::CmdSpec::new(
             Some("pane"),
-            Some(|args, ctx: &mut super::Context| {
-                ctx.kill.pane = args.first().map(|s| s.to_string());
-                Ok(())
-            }),
+            Some(|args, ctx: &mut super::Context| run(ctx, Target::Pane(args.first().copied()))),
         )
-        .pos([ap::PosSpec::new("PANE").one().desc("Pane name or ID")]),
+        .opts(opts())
+        .pos([ap::PosSpec::new("PANE").range(0, 1).desc("Pane ID (default $SPLICER_PANE)")]),
     ])
 }
+
+fn opts<'a>() -> [ap::OptSpec<'a, super::Context>; 3] {
+    [
+        ap::OptSpec::new("force", |_, ctx: &mut super::Context| {
+            ctx.kill.force = true;
+            Ok(())
+        })
+        .short('f')
+        .flag()
+        .help("SIGKILL right away"),
+        ap::OptSpec::new("grace", |s, ctx: &mut super::Context| {
+            let s = s.unwrap_or_default();
+            let secs: f64 = s.parse().map_err(|_| splicer::Error::UserInput(format!("invalid grace period: {s}")))?;
+            ctx.kill.grace = Some(std::time::Duration::try_from_secs_f64(secs)?);
+            Ok(())
+        })
+        .short('g')
+        .required()
+        .metavar("SECS")
+        .help("Time between SIGTERM and SIGKILL (default 5)"),
+        ap::OptSpec::new("json", |_, ctx: &mut super::Context| {
+            ctx.json = true;
+            Ok(())
+        })
+        .short('j')
+        .flag()
+        .help("JSON output"),
+    ]
+}
+
+fn run(ctx: &super::Context, target: Target<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let panes = super::block_on(async {
+        let mut client = super::connect(ctx).await?;
+        let target = match target {
+            Target::Session(s) => KillTarget::Session(super::resolve_session(&mut client, s).await?),
+            Target::Window(w) => KillTarget::Window(super::parse_id("window", w)?),
+            Target::Pane(p) => KillTarget::Pane(super::target_pane(p)?),
+        };
+        let req = Request::Kill { target, force: ctx.kill.force, grace: ctx.kill.grace };
+        match super::request(&mut client, req).await? {
+            Response::Killed { panes } => Ok(panes),
+            _ => Err(splicer::Error::Ipc("unexpected response".into())),
+        }
+    })?;
+    if ctx.json {
+        let items: Vec<_> = panes
+            .iter()
+            .map(|(id, stage)| serde_json::json!({ "pane": id.to_string(), "stage": stage.to_string() }))
+            .collect();
+        println!("{}", serde_json::Value::from(items));
+    } else if !ctx.quiet {
+        for (id, stage) in panes {
+            println!("pane={id} stage={stage}");
+        }
+    }
+    Ok(())
+}
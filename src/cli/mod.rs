@@ -1,23 +1,46 @@
 use rust_args_parser as ap;
 
+mod access;
 mod attach;
+mod buffer;
+mod clipboard;
 mod detach;
+mod environment;
+mod grep;
+mod input;
 mod kill;
 mod list;
 mod new;
+mod pipe;
+mod record;
+mod remain;
+mod rename;
+mod replay;
+mod respawn;
 mod server;
+mod signal;
+mod split;
 
 #[derive(Default)]
 pub struct Context {
     json: bool,
     quiet: bool,
     socket: Option<std::path::PathBuf>,
+    compress: bool,
     server: server::ServerContext,
     new: new::NewContext,
     attach: attach::AttachContext,
     detach: detach::DetachContext,
     kill: kill::KillContext,
+    buffer: buffer::BufferContext,
+    clipboard: clipboard::ClipboardContext,
+    grep: grep::GrepContext,
+    signal: signal::SignalContext,
     list: list::ListContext,
+    respawn: respawn::RespawnContext,
+    pipe: pipe::PipeContext,
+    replay: replay::ReplayContext,
+    split: split::SplitContext,
 }
 
 /// CLI entry point
@@ -38,6 +61,24 @@ pub fn run() -> splicer::Result {
             list::command(),
             server::command(),
             kill::command(),
+            buffer::command(),
+            clipboard::command(),
+            grep::command(),
+            signal::command(),
+            rename::command(),
+            respawn::command(),
+            remain::command(),
+            input::command(),
+            pipe::command(),
+            record::command(),
+            replay::command(),
+            replay::play_command(),
+            environment::set_command(),
+            environment::show_command(),
+            split::command(),
+            access::grant_command(),
+            access::revoke_command(),
+            access::show_command(),
         ])
         .opts([
             ap::OptSpec::new("json", |_, ctx: &mut Context| {
@@ -75,10 +116,90 @@ pub fn run() -> splicer::Result {
     }
 }
 
+/// Socket for client commands: the parsed `--socket`, then `SPLICER_SOCKET`, then the server
+/// this process runs under (`SPLICER`), then the default.
+fn socket_path(ctx: &Context) -> std::path::PathBuf {
+    ctx.socket
+        .clone()
+        .or_else(|| std::env::var_os("SPLICER_SOCKET").map(std::path::PathBuf::from))
+        .or_else(|| std::env::var("SPLICER").ok()?.rsplitn(3, ',').nth(2).map(std::path::PathBuf::from))
+        .unwrap_or_else(default_socket_path)
+}
+
+/// Whether to offer compressed frames: `attach --compress`, or `SPLICER_COMPRESS` for any command.
+/// Read here rather than as an option, since options only reach the subcommand that declares them.
+fn compress(ctx: &Context) -> bool {
+    ctx.compress || std::env::var("SPLICER_COMPRESS").is_ok_and(|v| !matches!(v.as_str(), "" | "0"))
+}
+
+/// The given pane, or the one this process runs in (`SPLICER_PANE`).
+fn target_pane(arg: Option<&str>) -> splicer::Result<splicer::server::pane::PaneId> {
+    match arg.map(str::to_string).or_else(|| std::env::var("SPLICER_PANE").ok()) {
+        Some(p) => parse_id("pane", &p),
+        None => Err(splicer::Error::UserInput("no pane given and not running inside splicer".into())),
+    }
+}
+
+/// `[PANE] ARG` positionals: given just `ARG`, the pane is the one this process runs in.
+fn target_pane_and<'a>(args: &[&'a str]) -> splicer::Result<(splicer::server::pane::PaneId, &'a str)> {
+    match *args {
+        [pane, arg] => Ok((target_pane(Some(pane))?, arg)),
+        [arg] => Ok((target_pane(None)?, arg)),
+        _ => Err(splicer::Error::UserInput("expected [PANE] and one more argument".into())),
+    }
+}
+
+/// Run one async CLI action on a fresh runtime.
+fn block_on<F: std::future::Future<Output = splicer::Result<T>>, T>(f: F) -> splicer::Result<T> {
+    tokio::runtime::Runtime::new()?.block_on(f)
+}
+
+async fn connect(ctx: &Context) -> splicer::Result<splicer::ipc::client::IpcClient> {
+    let features = if compress(ctx) { splicer::ipc::proto::FEATURE_LZ4 } else { 0 };
+    splicer::ipc::client::IpcClient::connect_with(&socket_path(ctx).to_string_lossy(), features).await
+}
+
+/// Send one request and turn `Response::Err` into an error.
+async fn request(
+    client: &mut splicer::ipc::client::IpcClient,
+    req: splicer::ipc::Request,
+) -> splicer::Result<splicer::ipc::Response> {
+    match client.request(req).await? {
+        splicer::ipc::Response::Err { code, msg } => Err(splicer::Error::Ipc(format!("{code:?}: {msg}"))),
+        resp => Ok(resp),
+    }
+}
+
+/// Accept a session ID or look the name up on the server.
+async fn resolve_session(
+    client: &mut splicer::ipc::client::IpcClient,
+    s: &str,
+) -> splicer::Result<splicer::server::session::SessionId> {
+    if let splicer::ipc::Response::Sessions { items } = request(client, splicer::ipc::Request::ListSessions).await? {
+        if let Some(hit) = items.iter().find(|it| it.name.as_deref() == Some(s)) {
+            return Ok(hit.id);
+        }
+    }
+    s.parse().map_err(|_| splicer::Error::UserInput(format!("no such session: {s}")))
+}
+
+/// `KEY=VAL` from the command line.
+fn parse_var(s: &str) -> splicer::Result<(String, String)> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.is_empty() => Ok((k.to_string(), v.to_string())),
+        _ => Err(splicer::Error::UserInput(format!("expected KEY=VAL: {s}"))),
+    }
+}
+
+fn parse_id<T: std::str::FromStr>(what: &str, s: &str) -> splicer::Result<T> {
+    s.parse().map_err(|_| splicer::Error::UserInput(format!("invalid {what} ID: {s}")))
+}
+
+/// `splicer.sock` in a per-user directory, which the server creates with mode 0700.
 fn default_socket_path() -> std::path::PathBuf {
     let path = std::env::var("XDG_RUNTIME_DIR")
         .map(std::path::PathBuf::from)
         .unwrap_or_else(|_| std::path::PathBuf::from("/tmp"));
 
-    path.join("splicer.sock")
+    path.join(format!("splicer-{}", rustix::process::getuid().as_raw())).join("splicer.sock")
 }
@@ -1,39 +1,98 @@
 use rust_args_parser as ap;
+use splicer::ipc::{Request, Response};
 
 #[derive(Default)]
 pub struct NewContext {
     session: Option<String>,
     cwd: Option<std::path::PathBuf>,
     title: Option<String>,
+    env: Vec<(String, String)>,
+    term: Option<String>,
+    shell: Option<String>,
 }
 
 pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
-    ap::CmdSpec::new(Some("new"), Some(|_, _ctx: &mut super::Context| unimplemented!()))
-        .desc("Create a new session")
-        .opts([
-            ap::OptSpec::new("session", |s, ctx: &mut super::Context| {
-                ctx.new.session = s.map(|s| s.to_string());
-                Ok(())
-            })
-            .short('s')
-            .help("Session name")
-            .required()
-            .metavar("NAME"),
-            ap::OptSpec::new("cwd", |s, ctx: &mut super::Context| {
-                ctx.new.cwd = s.map(std::path::PathBuf::from);
-                Ok(())
-            })
-            .short('c')
-            .help("Working directory")
-            .required()
-            .metavar("PATH"),
-            ap::OptSpec::new("title", |s, ctx: &mut super::Context| {
-                ctx.new.title = s.map(|s| s.to_string());
-                Ok(())
-            })
-            .short('t')
-            .help("Window title")
-            .metavar("TITLE")
-            .required(),
-        ])
+    ap::CmdSpec::new(
+        Some("new"),
+        Some(|args, ctx: &mut super::Context| {
+            let argv: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+            let (session, window, pane) = super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let Response::SessionCreated { session } =
+                    super::request(&mut client, Request::CreateSession { name: ctx.new.session.clone() }).await?
+                else {
+                    return Err(splicer::Error::Ipc("unexpected response".into()));
+                };
+                let req = Request::SpawnPane {
+                    session,
+                    window: None,
+                    title: ctx.new.title.clone(),
+                    cwd: ctx.new.cwd.as_ref().map(|p| p.to_string_lossy().into_owned()),
+                    cwd_from: None,
+                    argv,
+                    env: ctx.new.env.clone(),
+                    term: ctx.new.term.clone(),
+                    shell: ctx.new.shell.clone(),
+                };
+                match super::request(&mut client, req).await? {
+                    Response::PaneSpawned { session, window, pane } => Ok((session, window, pane)),
+                    _ => Err(splicer::Error::Ipc("unexpected response".into())),
+                }
+            })?;
+            if !ctx.quiet {
+                println!("session={session} window={window} pane={pane}");
+            }
+            Ok(())
+        }),
+    )
+    .desc("Create a new session")
+    .opts([
+        ap::OptSpec::new("session", |s, ctx: &mut super::Context| {
+            ctx.new.session = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('s')
+        .help("Session name")
+        .required()
+        .metavar("NAME"),
+        ap::OptSpec::new("cwd", |s, ctx: &mut super::Context| {
+            ctx.new.cwd = s.map(std::path::PathBuf::from);
+            Ok(())
+        })
+        .short('c')
+        .help("Working directory")
+        .required()
+        .metavar("PATH"),
+        ap::OptSpec::new("title", |s, ctx: &mut super::Context| {
+            ctx.new.title = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('t')
+        .help("Window title")
+        .metavar("TITLE")
+        .required(),
+        ap::OptSpec::new("env", |s, ctx: &mut super::Context| {
+            ctx.new.env.push(super::parse_var(s.unwrap_or_default())?);
+            Ok(())
+        })
+        .short('e')
+        .help("Set a variable in the pane (repeatable)")
+        .metavar("KEY=VAL")
+        .required(),
+        ap::OptSpec::new("term", |s, ctx: &mut super::Context| {
+            ctx.new.term = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .help("TERM for the pane (default xterm-256color)")
+        .metavar("TERM")
+        .required(),
+        ap::OptSpec::new("shell", |s, ctx: &mut super::Context| {
+            ctx.new.shell = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .help("Shell to run instead of $SHELL")
+        .metavar("PATH")
+        .required(),
+    ])
+    .pos([ap::PosSpec::new("COMMAND").range(0, usize::MAX).desc("Program and arguments (default: $SHELL)")])
 }
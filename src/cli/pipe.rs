@@ -0,0 +1,62 @@
+use rust_args_parser as ap;
+use splicer::ipc::{PipeTarget, Request};
+
+#[derive(Default)]
+pub struct PipeContext {
+    file: Option<String>,
+    plain: bool,
+    input: bool,
+}
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("pipe-pane"),
+        Some(|args, ctx: &mut super::Context| {
+            let pane = super::target_pane(args.first().copied())?;
+            let cmd = args[1..].join(" ");
+            let target = match ctx.pipe.file.clone() {
+                Some(_) if !cmd.is_empty() => {
+                    return Err(splicer::Error::UserInput("give either a command or --file".into()).into());
+                }
+                Some(path) => Some(PipeTarget::File(path)),
+                None if cmd.is_empty() => None,
+                None => Some(PipeTarget::Command(cmd)),
+            };
+            let req = Request::PipePane { pane, target, plain: ctx.pipe.plain, input: ctx.pipe.input };
+            super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                super::request(&mut client, req).await.map(drop)
+            })?;
+            Ok(())
+        }),
+    )
+    .desc("Stream a pane's output to a command or file; with neither, stop")
+    .opts([
+        ap::OptSpec::new("file", |s, ctx: &mut super::Context| {
+            ctx.pipe.file = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('f')
+        .required()
+        .metavar("PATH")
+        .help("Append to a file instead of piping into a command"),
+        ap::OptSpec::new("plain", |_, ctx: &mut super::Context| {
+            ctx.pipe.plain = true;
+            Ok(())
+        })
+        .short('p')
+        .flag()
+        .help("Strip escape sequences"),
+        ap::OptSpec::new("input", |_, ctx: &mut super::Context| {
+            ctx.pipe.input = true;
+            Ok(())
+        })
+        .short('i')
+        .flag()
+        .help("Type the command's stdout into the pane"),
+    ])
+    .pos([
+        ap::PosSpec::new("PANE").one().desc("Pane ID"),
+        ap::PosSpec::new("COMMAND").range(0, usize::MAX).desc("Shell command fed with the output"),
+    ])
+}
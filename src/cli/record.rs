@@ -0,0 +1,37 @@
+use rust_args_parser as ap;
+use splicer::ipc::{RecordTarget, Request};
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(Some("record"), None).desc("Record panes as asciicast v2; omit FILE to stop").subs([
+        ap::CmdSpec::new(
+            Some("pane"),
+            Some(|args, ctx: &mut super::Context| {
+                let target = RecordTarget::Pane(super::target_pane(Some(args[0]))?);
+                run(ctx, target, args.get(1).copied())
+            }),
+        )
+        .pos([ap::PosSpec::new("PANE").one().desc("Pane ID"), file()]),
+        ap::CmdSpec::new(
+            Some("window"),
+            Some(|args, ctx: &mut super::Context| {
+                let target = RecordTarget::Window(super::parse_id("window", args[0])?);
+                run(ctx, target, args.get(1).copied())
+            }),
+        )
+        .pos([ap::PosSpec::new("WINDOW").one().desc("Window ID"), file()]),
+    ])
+}
+
+fn file<'a>() -> ap::PosSpec<'a> {
+    ap::PosSpec::new("FILE").range(0, 1).desc("Recording path; a window writes one file per pane")
+}
+
+fn run(ctx: &super::Context, target: RecordTarget, file: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    // the server resolves paths from its own cwd
+    let path = file.map(std::path::absolute).transpose()?.map(|p| p.to_string_lossy().into_owned());
+    super::block_on(async {
+        let mut client = super::connect(ctx).await?;
+        super::request(&mut client, Request::Record { target, path }).await.map(drop)
+    })?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use rust_args_parser as ap;
+use splicer::ipc::{PolicyTarget, Request};
+use splicer::server::pane::{PaneId, RemainOnExit};
+
+enum Target<'a> {
+    Session(&'a str),
+    Window(&'a str),
+    Pane(PaneId),
+}
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(Some("remain-on-exit"), None).desc("Set what happens to panes whose program exits").subs([
+        ap::CmdSpec::new(
+            Some("session"),
+            Some(|args, ctx: &mut super::Context| run(ctx, Target::Session(args[0]), args[1])),
+        )
+        .pos(pos(ap::PosSpec::new("SESSION").one().desc("Session name or ID"))),
+        ap::CmdSpec::new(
+            Some("window"),
+            Some(|args, ctx: &mut super::Context| run(ctx, Target::Window(args[0]), args[1])),
+        )
+        .pos(pos(ap::PosSpec::new("WINDOW").one().desc("Window ID"))),
+        ap::CmdSpec::new(
+            Some("pane"),
+            Some(|args, ctx: &mut super::Context| {
+                let (pane, policy) = super::target_pane_and(args)?;
+                run(ctx, Target::Pane(pane), policy)
+            }),
+        )
+        .pos(pos(ap::PosSpec::new("PANE").range(0, 1).desc("Pane ID (default $SPLICER_PANE)"))),
+    ])
+}
+
+fn pos(target: ap::PosSpec<'_>) -> [ap::PosSpec<'_>; 2] {
+    [target, ap::PosSpec::new("POLICY").one().desc("close, keep, on-failure, or inherit")]
+}
+
+fn run(ctx: &super::Context, target: Target<'_>, policy: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let policy = match policy {
+        "inherit" => None,
+        p => Some(p.parse::<RemainOnExit>()?),
+    };
+    super::block_on(async {
+        let mut client = super::connect(ctx).await?;
+        let target = match target {
+            Target::Session(s) => PolicyTarget::Session(super::resolve_session(&mut client, s).await?),
+            Target::Window(w) => PolicyTarget::Window(super::parse_id("window", w)?),
+            Target::Pane(p) => PolicyTarget::Pane(p),
+        };
+        super::request(&mut client, Request::SetRemainOnExit { target, policy }).await.map(drop)
+    })?;
+    Ok(())
+}
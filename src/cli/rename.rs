@@ -0,0 +1,22 @@
+use rust_args_parser as ap;
+use splicer::ipc::Request;
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("rename"),
+        Some(|args, ctx: &mut super::Context| {
+            let window = super::parse_id("window", args.first().copied().unwrap_or_default())?;
+            let name = args.get(1).map(|s| s.to_string());
+            super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                super::request(&mut client, Request::RenameWindow { window, name }).await.map(drop)
+            })?;
+            Ok(())
+        }),
+    )
+    .desc("Rename a window, or let it follow its focused pane again")
+    .pos([
+        ap::PosSpec::new("WINDOW").one().desc("Window ID"),
+        ap::PosSpec::new("NAME").range(0, 1).desc("New name; omit to restore automatic naming"),
+    ])
+}
@@ -0,0 +1,136 @@
+use rust_args_parser as ap;
+use splicer::ipc::{PolicyTarget, Request, Response};
+use splicer::server::pane::RemainOnExit;
+use splicer::term::cast::{self, Frame};
+use std::io::Write;
+use std::time::Duration;
+
+#[derive(Default)]
+pub struct ReplayContext {
+    speed: Option<f64>,
+    idle_limit: Option<Duration>,
+    session: Option<String>,
+}
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("replay"),
+        Some(|args, ctx: &mut super::Context| {
+            let path = std::path::absolute(args[0])?;
+            let (header, _) = cast::parse(&std::fs::read_to_string(&path)?)?;
+            let exe = std::env::current_exe()?;
+            let mut argv = vec![exe.to_string_lossy().into_owned(), "play".into(), path.to_string_lossy().into_owned()];
+            if let Some(speed) = ctx.replay.speed {
+                argv.extend(["--speed".into(), speed.to_string()]);
+            }
+            if let Some(limit) = ctx.replay.idle_limit {
+                argv.extend(["--idle-limit".into(), limit.as_secs_f64().to_string()]);
+            }
+            let (session, window, pane) = super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let Response::SessionCreated { session } =
+                    super::request(&mut client, Request::CreateSession { name: ctx.replay.session.clone() }).await?
+                else {
+                    return Err(splicer::Error::Ipc("unexpected response".into()));
+                };
+                let req = Request::SpawnPane {
+                    session,
+                    window: None,
+                    title: None,
+                    cwd: None,
+                    cwd_from: None,
+                    argv,
+                    env: vec![],
+                    term: None,
+                    shell: None,
+                };
+                let Response::PaneSpawned { session, window, pane } = super::request(&mut client, req).await? else {
+                    return Err(splicer::Error::Ipc("unexpected response".into()));
+                };
+                // keep the final screen around once playback ends
+                let keep =
+                    Request::SetRemainOnExit { target: PolicyTarget::Pane(pane), policy: Some(RemainOnExit::Keep) };
+                super::request(&mut client, keep).await?;
+                let size = Request::Resize { pane, cols: header.width, rows: header.height };
+                super::request(&mut client, size).await?;
+                Ok((session, window, pane))
+            })?;
+            if !ctx.quiet {
+                println!("session={session} window={window} pane={pane}");
+            }
+            Ok(())
+        }),
+    )
+    .desc("Play an asciicast recording back in a new session")
+    .opts([
+        speed_opt(),
+        idle_opt(),
+        ap::OptSpec::new("session", |s, ctx: &mut super::Context| {
+            ctx.replay.session = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('s')
+        .required()
+        .metavar("NAME")
+        .help("Session name"),
+    ])
+    .pos([ap::PosSpec::new("FILE").one().desc("asciicast v2 file")])
+}
+
+pub fn play_command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("play"),
+        Some(|args, ctx: &mut super::Context| {
+            let (_, frames) = cast::parse(&std::fs::read_to_string(args[0])?)?;
+            let speed = ctx.replay.speed.unwrap_or(1.0);
+            let mut stdout = std::io::stdout();
+            let mut last = Duration::ZERO;
+            for (at, frame) in frames {
+                let mut gap = at.saturating_sub(last).div_f64(speed);
+                if let Some(limit) = ctx.replay.idle_limit {
+                    gap = gap.min(limit);
+                }
+                std::thread::sleep(gap);
+                last = at;
+                // resizes are applied once, from the header, by `replay`
+                if let Frame::Output(data) = frame {
+                    stdout.write_all(data.as_bytes())?;
+                    stdout.flush()?;
+                }
+            }
+            Ok(())
+        }),
+    )
+    .desc("Write an asciicast recording to this terminal with its original timing")
+    .opts([speed_opt(), idle_opt()])
+    .pos([ap::PosSpec::new("FILE").one().desc("asciicast v2 file")])
+}
+
+fn speed_opt<'a>() -> ap::OptSpec<'a, super::Context> {
+    ap::OptSpec::new("speed", |s, ctx: &mut super::Context| {
+        let s = s.unwrap_or_default();
+        let speed: f64 = s.parse().map_err(|_| splicer::Error::UserInput(format!("invalid speed: {s}")))?;
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(splicer::Error::UserInput(format!("invalid speed: {s}")).into());
+        }
+        ctx.replay.speed = Some(speed);
+        Ok(())
+    })
+    .short('x')
+    .required()
+    .metavar("FACTOR")
+    .help("Playback speed (default 1)")
+}
+
+fn idle_opt<'a>() -> ap::OptSpec<'a, super::Context> {
+    ap::OptSpec::new("idle-limit", |s, ctx: &mut super::Context| {
+        let s = s.unwrap_or_default();
+        let secs: f64 = s.parse().map_err(|_| splicer::Error::UserInput(format!("invalid idle limit: {s}")))?;
+        ctx.replay.idle_limit = Some(Duration::try_from_secs_f64(secs)?);
+        Ok(())
+    })
+    .short('i')
+    .required()
+    .metavar("SECS")
+    .help("Cap pauses between frames")
+}
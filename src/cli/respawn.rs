@@ -0,0 +1,80 @@
+use rust_args_parser as ap;
+use splicer::ipc::Request;
+use splicer::server::pane::AutoRespawn;
+use std::time::Duration;
+
+/// Upper bound of the respawn backoff when `--auto` names only the initial delay.
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+pub struct RespawnContext {
+    kill: bool,
+    cwd: Option<String>,
+    /// `Some(None)` for `--no-auto`.
+    auto: Option<Option<AutoRespawn>>,
+}
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("respawn"),
+        Some(|args, ctx: &mut super::Context| {
+            let pane = super::target_pane(args.first().copied())?;
+            let argv: Vec<String> = args.iter().skip(1).map(|s| s.to_string()).collect();
+            let req = match ctx.respawn.auto {
+                Some(policy) => Request::SetAutoRespawn { pane, policy },
+                None => Request::RespawnPane { pane, argv, cwd: ctx.respawn.cwd.clone(), kill: ctx.respawn.kill },
+            };
+            super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                super::request(&mut client, req).await.map(drop)
+            })?;
+            Ok(())
+        }),
+    )
+    .desc("Restart an exited pane in place, or set its auto-respawn policy")
+    .opts([
+        ap::OptSpec::new("kill", |_, ctx: &mut super::Context| {
+            ctx.respawn.kill = true;
+            Ok(())
+        })
+        .short('k')
+        .flag()
+        .help("Kill the pane's program first if it is still running"),
+        ap::OptSpec::new("cwd", |s, ctx: &mut super::Context| {
+            ctx.respawn.cwd = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('c')
+        .required()
+        .metavar("PATH")
+        .help("Working directory (default: as last spawned)"),
+        ap::OptSpec::new("auto", |s, ctx: &mut super::Context| {
+            let s = s.unwrap_or_default();
+            let secs = |v: &str| {
+                v.parse::<f64>()
+                    .ok()
+                    .and_then(|v| Duration::try_from_secs_f64(v).ok())
+                    .ok_or_else(|| splicer::Error::UserInput(format!("invalid delay: {v}")))
+            };
+            let (min, max) = match s.split_once(',') {
+                Some((min, max)) => (secs(min)?, secs(max)?),
+                None => (secs(s)?, DEFAULT_MAX_BACKOFF.max(secs(s)?)),
+            };
+            ctx.respawn.auto = Some(Some(AutoRespawn { min, max: max.max(min) }));
+            Ok(())
+        })
+        .required()
+        .metavar("MIN[,MAX]")
+        .help("Respawn automatically after exits, backing off from MIN to MAX seconds (default max 60)"),
+        ap::OptSpec::new("no-auto", |_, ctx: &mut super::Context| {
+            ctx.respawn.auto = Some(None);
+            Ok(())
+        })
+        .flag()
+        .help("Turn automatic respawn off"),
+    ])
+    .pos([
+        ap::PosSpec::new("PANE").one().desc("Pane ID"),
+        ap::PosSpec::new("COMMAND").range(0, usize::MAX).desc("Program and arguments (default: as last spawned)"),
+    ])
+}
@@ -3,21 +3,80 @@ use rust_args_parser as ap;
 #[derive(Default)]
 pub struct ServerContext {
     foreground: bool,
+    max_frame: Option<u32>,
+    allow_uids: Vec<u32>,
 }
 
 pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
     ap::CmdSpec::new(
         Some("server"),
-        Some(|_, _ctx: &mut super::Context| {
-            unimplemented!();
+        Some(|_, ctx: &mut super::Context| {
+            let socket = super::socket_path(ctx);
+            if ctx.server.foreground {
+                let mut config = splicer::runtime::ServerConfig::default();
+                if let Some(max_frame) = ctx.server.max_frame {
+                    config.max_frame = max_frame;
+                }
+                config.allow_uids = ctx.server.allow_uids.clone();
+                super::block_on(splicer::runtime::serve(&socket, config))?;
+                return Ok(());
+            }
+            // re-exec ourselves in the foreground, detached from this terminal
+            use std::os::unix::process::CommandExt;
+            let mut cmd = std::process::Command::new(std::env::current_exe()?);
+            cmd.args(["server", "--foreground"]);
+            if let Some(max_frame) = ctx.server.max_frame {
+                cmd.arg(format!("--max-frame={max_frame}"));
+            }
+            if !ctx.server.allow_uids.is_empty() {
+                let uids: Vec<String> = ctx.server.allow_uids.iter().map(u32::to_string).collect();
+                cmd.arg(format!("--allow-uids={}", uids.join(",")));
+            }
+            let child = cmd
+                .env("SPLICER_SOCKET", &socket)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .process_group(0)
+                .spawn()?;
+            if !ctx.quiet {
+                println!("server started (pid {}) on {}", child.id(), socket.display());
+            }
+            Ok(())
         }),
     )
     .desc("Start splicer server")
-    .opts([ap::OptSpec::new("foreground", |_, ctx: &mut super::Context| {
-        ctx.server.foreground = true;
-        Ok(())
-    })
-    .short('f')
-    .flag()
-    .help("Run in foreground")])
+    .opts([
+        ap::OptSpec::new("foreground", |_, ctx: &mut super::Context| {
+            ctx.server.foreground = true;
+            Ok(())
+        })
+        .short('f')
+        .flag()
+        .help("Run in foreground"),
+        ap::OptSpec::new("max-frame", |s, ctx: &mut super::Context| {
+            let s = s.unwrap_or_default();
+            let max =
+                s.parse().map_err(|_| splicer::Error::UserInput(format!("max-frame must be a byte count: {s}")))?;
+            ctx.server.max_frame = Some(max);
+            Ok(())
+        })
+        .required()
+        .metavar("BYTES")
+        .help("Largest frame a client may send (default 16 MiB)")
+        .env("SPLICER_MAX_FRAME"),
+        ap::OptSpec::new("allow-uids", |s, ctx: &mut super::Context| {
+            let s = s.unwrap_or_default();
+            ctx.server.allow_uids = s
+                .split(',')
+                .filter(|u| !u.is_empty())
+                .map(|u| u.trim().parse().map_err(|_| splicer::Error::UserInput(format!("not a uid: {u}"))))
+                .collect::<splicer::Result<_>>()?;
+            Ok(())
+        })
+        .required()
+        .metavar("UID,...")
+        .help("Other users allowed to connect, besides this one and root")
+        .env("SPLICER_ALLOW_UIDS"),
+    ])
 }
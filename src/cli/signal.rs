@@ -0,0 +1,36 @@
+use rust_args_parser as ap;
+use splicer::ipc::Request;
+use splicer::pty::Sig;
+
+#[derive(Default)]
+pub struct SignalContext {
+    foreground: bool,
+}
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("signal"),
+        Some(|args, ctx: &mut super::Context| {
+            let (pane, name) = super::target_pane_and(args)?;
+            let signal: Sig = name.parse().map_err(|_| splicer::Error::UserInput(format!("unknown signal: {name}")))?;
+            let foreground = ctx.signal.foreground;
+            super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                super::request(&mut client, Request::Signal { pane, signal, foreground }).await.map(drop)
+            })?;
+            Ok(())
+        }),
+    )
+    .desc("Send a signal to a pane's processes")
+    .opts([ap::OptSpec::new("foreground", |_, ctx: &mut super::Context| {
+        ctx.signal.foreground = true;
+        Ok(())
+    })
+    .short('f')
+    .flag()
+    .help("Signal the terminal's foreground job instead of the pane's process group")])
+    .pos([
+        ap::PosSpec::new("PANE").range(0, 1).desc("Pane ID (default $SPLICER_PANE)"),
+        ap::PosSpec::new("SIGNAL").one().desc("TERM, KILL, INT, HUP, QUIT, STOP, CONT, WINCH, USR1 or USR2"),
+    ])
+}
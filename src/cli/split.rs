@@ -0,0 +1,94 @@
+use rust_args_parser as ap;
+use splicer::ipc::{Request, Response, StateScope};
+use splicer::server::{pane::PaneId, session::SessionId, window::WindowId};
+
+#[derive(Default)]
+pub struct SplitContext {
+    target: Option<String>,
+    cwd: Option<String>,
+    env: Vec<(String, String)>,
+}
+
+pub fn command<'a>() -> ap::CmdSpec<'a, super::Context> {
+    ap::CmdSpec::new(
+        Some("split"),
+        Some(|args, ctx: &mut super::Context| {
+            let target = super::target_pane(ctx.split.target.as_deref())?;
+            let argv: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+            let pane = super::block_on(async {
+                let mut client = super::connect(ctx).await?;
+                let (session, window) = locate(&mut client, target).await?;
+                let req = Request::SpawnPane {
+                    session,
+                    window: Some(window),
+                    title: None,
+                    cwd: ctx.split.cwd.clone(),
+                    cwd_from: Some(target),
+                    argv,
+                    env: ctx.split.env.clone(),
+                    term: None,
+                    shell: None,
+                };
+                match super::request(&mut client, req).await? {
+                    Response::PaneSpawned { pane, .. } => Ok(pane),
+                    _ => Err(splicer::Error::Ipc("unexpected response".into())),
+                }
+            })?;
+            if !ctx.quiet {
+                println!("pane={pane}");
+            }
+            Ok(())
+        }),
+    )
+    .desc("Open a new pane next to a pane (default: the one this runs in)")
+    .opts([
+        ap::OptSpec::new("target", |s, ctx: &mut super::Context| {
+            ctx.split.target = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('t')
+        .required()
+        .metavar("PANE")
+        .help("Pane to split (default $SPLICER_PANE)"),
+        ap::OptSpec::new("cwd", |s, ctx: &mut super::Context| {
+            ctx.split.cwd = s.map(|s| s.to_string());
+            Ok(())
+        })
+        .short('c')
+        .required()
+        .metavar("PATH")
+        .help("Working directory (default: the target pane's)"),
+        ap::OptSpec::new("env", |s, ctx: &mut super::Context| {
+            ctx.split.env.push(super::parse_var(s.unwrap_or_default())?);
+            Ok(())
+        })
+        .short('e')
+        .required()
+        .metavar("KEY=VAL")
+        .help("Set a variable in the pane (repeatable)"),
+    ])
+    .pos([ap::PosSpec::new("COMMAND").range(0, usize::MAX).desc("Program and arguments (default: $SHELL)")])
+}
+
+/// Session and window of `pane`, from the server's state dump.
+async fn locate(client: &mut splicer::ipc::client::IpcClient, pane: PaneId) -> splicer::Result<(SessionId, WindowId)> {
+    let missing = || splicer::Error::UserInput(format!("no such pane: {pane}"));
+    let panes = state(client, StateScope::Panes { window: None }).await?;
+    let window = lookup(&panes, &pane.to_string(), "window").ok_or_else(missing)?;
+    let windows = state(client, StateScope::Windows { session: None }).await?;
+    let session = lookup(&windows, &window, "session").ok_or_else(missing)?;
+    Ok((super::parse_id("session", &session)?, super::parse_id("window", &window)?))
+}
+
+async fn state(client: &mut splicer::ipc::client::IpcClient, scope: StateScope) -> splicer::Result<serde_json::Value> {
+    match super::request(client, Request::GetState { scope }).await? {
+        Response::State { json } => Ok(json),
+        _ => Err(splicer::Error::Ipc("unexpected response".into())),
+    }
+}
+
+/// `field` of the entry whose `id` is `id`.
+fn lookup(items: &serde_json::Value, id: &str, field: &str) -> Option<String> {
+    let item = items.as_array()?.iter().find(|i| i["id"] == id)?;
+    item[field].as_str().map(str::to_string)
+}
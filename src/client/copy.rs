@@ -0,0 +1,569 @@
+use super::keys::{Action, Binding, Code, Key, KeyTables, MOD_ALT, MOD_CTRL};
+use crate::{Error, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+pub const COPY_VI: &str = "copy-mode-vi";
+pub const COPY_EMACS: &str = "copy-mode";
+
+/// Copy-mode commands, named as in tmux's `send -X`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyCmd {
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    StartOfLine,
+    EndOfLine,
+    NextWord,
+    PreviousWord,
+    NextWordEnd,
+    TopLine,
+    BottomLine,
+    HistoryTop,
+    HistoryBottom,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    BeginSelection,
+    SelectLine,
+    RectangleToggle,
+    ClearSelection,
+    CopySelection,
+    Cancel,
+    SearchForward,
+    SearchBackward,
+    SearchAgain,
+    SearchReverse,
+}
+
+const NAMES: &[(&str, CopyCmd)] = &[
+    ("cursor-up", CopyCmd::CursorUp),
+    ("cursor-down", CopyCmd::CursorDown),
+    ("cursor-left", CopyCmd::CursorLeft),
+    ("cursor-right", CopyCmd::CursorRight),
+    ("start-of-line", CopyCmd::StartOfLine),
+    ("end-of-line", CopyCmd::EndOfLine),
+    ("next-word", CopyCmd::NextWord),
+    ("previous-word", CopyCmd::PreviousWord),
+    ("next-word-end", CopyCmd::NextWordEnd),
+    ("top-line", CopyCmd::TopLine),
+    ("bottom-line", CopyCmd::BottomLine),
+    ("history-top", CopyCmd::HistoryTop),
+    ("history-bottom", CopyCmd::HistoryBottom),
+    ("page-up", CopyCmd::PageUp),
+    ("page-down", CopyCmd::PageDown),
+    ("halfpage-up", CopyCmd::HalfPageUp),
+    ("halfpage-down", CopyCmd::HalfPageDown),
+    ("begin-selection", CopyCmd::BeginSelection),
+    ("select-line", CopyCmd::SelectLine),
+    ("rectangle-toggle", CopyCmd::RectangleToggle),
+    ("clear-selection", CopyCmd::ClearSelection),
+    ("copy-selection-and-cancel", CopyCmd::CopySelection),
+    ("cancel", CopyCmd::Cancel),
+    ("search-forward", CopyCmd::SearchForward),
+    ("search-backward", CopyCmd::SearchBackward),
+    ("search-again", CopyCmd::SearchAgain),
+    ("search-reverse", CopyCmd::SearchReverse),
+];
+
+impl CopyCmd {
+    pub fn name(self) -> &'static str {
+        NAMES.iter().find(|(_, c)| *c == self).map_or("", |(n, _)| n)
+    }
+}
+
+impl std::str::FromStr for CopyCmd {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        NAMES
+            .iter()
+            .find(|(n, _)| *n == s)
+            .map(|(_, c)| *c)
+            .ok_or_else(|| Error::UserInput(format!("unknown copy command: {s}")))
+    }
+}
+
+/// Which default copy table to use, like tmux's `mode-keys`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeKeys {
+    Vi,
+    Emacs,
+}
+
+impl ModeKeys {
+    pub fn table(self) -> &'static str {
+        match self {
+            Self::Vi => COPY_VI,
+            Self::Emacs => COPY_EMACS,
+        }
+    }
+}
+
+impl std::str::FromStr for ModeKeys {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "vi" => Ok(Self::Vi),
+            "emacs" => Ok(Self::Emacs),
+            _ => Err(Error::UserInput(format!("mode-keys must be vi or emacs, got {s:?}"))),
+        }
+    }
+}
+
+/// Install the stock vi and emacs copy tables.
+pub fn bind_defaults(tables: &mut KeyTables) {
+    use CopyCmd::*;
+    let plain = |c| Key::new(Code::Char(c), 0);
+    let ctrl = Key::ctrl;
+    let alt = |c| Key::new(Code::Char(c), MOD_ALT);
+    let arrows = [
+        (Key::new(Code::Up, 0), CursorUp),
+        (Key::new(Code::Down, 0), CursorDown),
+        (Key::new(Code::Left, 0), CursorLeft),
+        (Key::new(Code::Right, 0), CursorRight),
+        (Key::new(Code::PageUp, 0), PageUp),
+        (Key::new(Code::PageDown, 0), PageDown),
+        (Key::new(Code::Home, 0), StartOfLine),
+        (Key::new(Code::End, 0), EndOfLine),
+        (Key::new(Code::Esc, 0), Cancel),
+        (plain('q'), Cancel),
+    ];
+    let vi = [
+        (plain('k'), CursorUp),
+        (plain('j'), CursorDown),
+        (plain('h'), CursorLeft),
+        (plain('l'), CursorRight),
+        (plain('0'), StartOfLine),
+        (plain('^'), StartOfLine),
+        (plain('$'), EndOfLine),
+        (plain('w'), NextWord),
+        (plain('b'), PreviousWord),
+        (plain('e'), NextWordEnd),
+        (plain('H'), TopLine),
+        (plain('L'), BottomLine),
+        (plain('g'), HistoryTop),
+        (plain('G'), HistoryBottom),
+        (ctrl('b'), PageUp),
+        (ctrl('f'), PageDown),
+        (ctrl('u'), HalfPageUp),
+        (ctrl('d'), HalfPageDown),
+        (plain('v'), BeginSelection),
+        (plain(' '), BeginSelection),
+        (plain('V'), SelectLine),
+        (ctrl('v'), RectangleToggle),
+        (Key::new(Code::Enter, 0), CopySelection),
+        (plain('y'), CopySelection),
+        (plain('/'), SearchForward),
+        (plain('?'), SearchBackward),
+        (plain('n'), SearchAgain),
+        (plain('N'), SearchReverse),
+        (ctrl('c'), Cancel),
+    ];
+    let emacs = [
+        (ctrl('p'), CursorUp),
+        (ctrl('n'), CursorDown),
+        (ctrl('b'), CursorLeft),
+        (ctrl('f'), CursorRight),
+        (ctrl('a'), StartOfLine),
+        (ctrl('e'), EndOfLine),
+        (alt('f'), NextWord),
+        (alt('b'), PreviousWord),
+        (alt('<'), HistoryTop),
+        (alt('>'), HistoryBottom),
+        (alt('v'), PageUp),
+        (ctrl('v'), PageDown),
+        (ctrl(' '), BeginSelection),
+        (plain('R'), RectangleToggle),
+        (alt('w'), CopySelection),
+        (ctrl('w'), CopySelection),
+        (ctrl('s'), SearchForward),
+        (ctrl('r'), SearchBackward),
+        (plain('n'), SearchAgain),
+        (plain('N'), SearchReverse),
+        (ctrl('g'), Cancel),
+    ];
+    for (key, cmd) in arrows.iter().chain(&vi) {
+        tables.bind(COPY_VI, *key, Binding::new(Action::Copy(*cmd)));
+    }
+    for (key, cmd) in arrows.iter().chain(&emacs) {
+        tables.bind(COPY_EMACS, *key, Binding::new(Action::Copy(*cmd)));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelKind {
+    Char,
+    Line,
+    Rect,
+}
+
+#[derive(Debug)]
+pub enum CopyOutcome {
+    Redraw,
+    /// Selection copied; copy mode is over.
+    Copied(String),
+    Exit,
+}
+
+struct Prompt {
+    forward: bool,
+    query: String,
+    origin: (usize, usize),
+}
+
+/// Per-client copy mode over a frozen capture of a pane (scrollback + screen).
+pub struct CopyMode {
+    lines: Vec<Vec<char>>,
+    width: usize,
+    height: usize,
+    top: usize,
+    row: usize,
+    col: usize,
+    anchor: Option<(usize, usize)>,
+    kind: SelKind,
+    prompt: Option<Prompt>,
+    search: Option<(Regex, bool)>,
+}
+
+impl CopyMode {
+    /// `cursor` is (line, col) within `lines`; the view starts at the bottom like the live pane.
+    pub fn new(lines: Vec<String>, cursor: (usize, usize), width: u16, height: u16) -> Self {
+        let mut lines: Vec<Vec<char>> = lines.into_iter().map(|l| l.chars().collect()).collect();
+        if lines.is_empty() {
+            lines.push(Vec::new());
+        }
+        let height = usize::from(height.max(1));
+        let top = lines.len().saturating_sub(height);
+        let mut me = Self {
+            lines,
+            width: usize::from(width.max(1)),
+            height,
+            top,
+            row: cursor.0,
+            col: cursor.1,
+            anchor: None,
+            kind: SelKind::Char,
+            prompt: None,
+            search: None,
+        };
+        me.clamp();
+        me
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+    pub fn prompting(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    pub fn apply(&mut self, cmd: CopyCmd) -> CopyOutcome {
+        use CopyCmd::*;
+        let last = self.lines.len() - 1;
+        match cmd {
+            CursorUp => self.row = self.row.saturating_sub(1),
+            CursorDown => self.row = (self.row + 1).min(last),
+            CursorLeft => self.col = self.col.saturating_sub(1),
+            CursorRight => self.col += 1,
+            StartOfLine => self.col = 0,
+            EndOfLine => self.col = self.line_len(self.row).saturating_sub(1),
+            NextWord => self.next_word(),
+            PreviousWord => self.previous_word(),
+            NextWordEnd => self.next_word_end(),
+            TopLine => self.row = self.top,
+            BottomLine => self.row = (self.top + self.height - 1).min(last),
+            HistoryTop => (self.row, self.col) = (0, 0),
+            HistoryBottom => self.row = last,
+            PageUp => self.scroll_by(-(self.height as isize)),
+            PageDown => self.scroll_by(self.height as isize),
+            HalfPageUp => self.scroll_by(-(self.height as isize / 2)),
+            HalfPageDown => self.scroll_by(self.height as isize / 2),
+            BeginSelection => {
+                self.anchor = Some((self.row, self.col));
+                self.kind = SelKind::Char;
+            }
+            SelectLine => {
+                self.anchor = Some((self.row, 0));
+                self.kind = SelKind::Line;
+            }
+            RectangleToggle => {
+                self.kind = if self.kind == SelKind::Rect { SelKind::Char } else { SelKind::Rect };
+            }
+            ClearSelection => self.anchor = None,
+            CopySelection => {
+                return match self.selection_text() {
+                    Some(text) => CopyOutcome::Copied(text),
+                    None => CopyOutcome::Exit,
+                };
+            }
+            Cancel => return CopyOutcome::Exit,
+            SearchForward | SearchBackward => {
+                self.prompt =
+                    Some(Prompt { forward: cmd == SearchForward, query: String::new(), origin: (self.row, self.col) });
+            }
+            SearchAgain | SearchReverse => {
+                if let Some((re, fwd)) = self.search.clone() {
+                    self.find(&re, fwd == (cmd == SearchAgain), (self.row, self.col));
+                }
+            }
+        }
+        self.clamp();
+        CopyOutcome::Redraw
+    }
+
+    /// Feed a key to the search prompt; matches are found incrementally as the query grows.
+    pub fn prompt_key(&mut self, key: Key) -> CopyOutcome {
+        let Some(mut p) = self.prompt.take() else {
+            return CopyOutcome::Redraw;
+        };
+        match key.code {
+            Code::Enter => return CopyOutcome::Redraw,
+            Code::Esc => {
+                (self.row, self.col) = p.origin;
+                self.clamp();
+                return CopyOutcome::Redraw;
+            }
+            Code::Char('c' | 'g') if key.mods & MOD_CTRL != 0 => {
+                (self.row, self.col) = p.origin;
+                self.clamp();
+                return CopyOutcome::Redraw;
+            }
+            Code::Backspace => {
+                p.query.pop();
+            }
+            Code::Char(c) if key.mods & (MOD_CTRL | MOD_ALT) == 0 => p.query.push(c),
+            _ => {}
+        }
+        (self.row, self.col) = p.origin;
+        if let Some(Ok(re)) = (!p.query.is_empty()).then(|| Regex::new(&p.query)) {
+            // search from just before the origin so a match under the cursor counts
+            self.find_from_origin(&re, p.forward, p.origin);
+            self.search = Some((re, p.forward));
+        }
+        self.clamp();
+        self.prompt = Some(p);
+        CopyOutcome::Redraw
+    }
+
+    pub fn selection_text(&self) -> Option<String> {
+        let (a, b) = self.selection_bounds()?;
+        let text = match self.kind {
+            SelKind::Line => (a.0..=b.0).map(|r| self.line_string(r, 0, usize::MAX) + "\n").collect(),
+            SelKind::Rect => {
+                let (c0, c1) = (a.1.min(b.1), a.1.max(b.1));
+                (a.0..=b.0)
+                    .map(|r| self.line_string(r, c0, c1 + 1).trim_end().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            SelKind::Char if a.0 == b.0 => self.line_string(a.0, a.1, b.1 + 1),
+            SelKind::Char => {
+                let mut out = vec![self.line_string(a.0, a.1, usize::MAX)];
+                out.extend((a.0 + 1..b.0).map(|r| self.line_string(r, 0, usize::MAX)));
+                out.push(self.line_string(b.0, 0, b.1 + 1));
+                out.join("\n")
+            }
+        };
+        Some(text)
+    }
+
+    /// Draw the viewport with the selection in reverse video, a position marker and the prompt.
+    pub fn render(&self) -> Vec<u8> {
+        let mut out = String::from("\x1b[?25l");
+        for vr in 0..self.height {
+            let r = self.top + vr;
+            out.push_str(&format!("\x1b[{};1H\x1b[0m", vr + 1));
+            if let Some(line) = self.lines.get(r) {
+                let (s0, s1) = self.selected_cols(r).unwrap_or((usize::MAX, 0));
+                for (c, ch) in line.iter().take(self.width).enumerate() {
+                    if c == s0 {
+                        out.push_str("\x1b[7m");
+                    }
+                    out.push(*ch);
+                    if c == s1 {
+                        out.push_str("\x1b[0m");
+                    }
+                }
+            }
+            out.push_str("\x1b[0m\x1b[K");
+        }
+        let marker =
+            format!("[{}/{}]", self.lines.len() - 1 - self.top.min(self.lines.len() - 1), self.lines.len() - 1);
+        let at = self.width.saturating_sub(marker.len()) + 1;
+        out.push_str(&format!("\x1b[1;{at}H\x1b[7m{marker}\x1b[0m"));
+        if let Some(ref p) = self.prompt {
+            let label = if p.forward { "Search down" } else { "Search up" };
+            out.push_str(&format!("\x1b[{};1H\x1b[7m{label}: {}\x1b[0m\x1b[K", self.height, p.query));
+        } else {
+            out.push_str(&format!("\x1b[{};{}H", self.row - self.top + 1, self.col + 1));
+        }
+        out.push_str("\x1b[?25h");
+        out.into_bytes()
+    }
+
+    fn line_len(&self, r: usize) -> usize {
+        self.lines.get(r).map_or(0, Vec::len)
+    }
+
+    fn line_string(&self, r: usize, from: usize, to: usize) -> String {
+        let l = &self.lines[r];
+        let to = to.min(l.len());
+        l[from.min(to)..to].iter().collect()
+    }
+
+    fn clamp(&mut self) {
+        self.row = self.row.min(self.lines.len() - 1);
+        self.col = self.col.min(self.line_len(self.row).saturating_sub(1));
+        if self.row < self.top {
+            self.top = self.row;
+        } else if self.row >= self.top + self.height {
+            self.top = self.row + 1 - self.height;
+        }
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        let max_top = self.lines.len().saturating_sub(self.height);
+        self.top = self.top.saturating_add_signed(delta).min(max_top);
+        self.row = self.row.saturating_add_signed(delta).clamp(self.top, self.top + self.height - 1);
+    }
+
+    fn is_word(&self, r: usize, c: usize) -> bool {
+        self.lines[r].get(c).is_some_and(|ch| ch.is_alphanumeric() || *ch == '_')
+    }
+
+    fn next_word(&mut self) {
+        let mut pos = (self.row, self.col);
+        let row = pos.0;
+        // leave the current word (a line break also ends it), then skip to the next one
+        while pos.0 == row && self.is_word(pos.0, pos.1) {
+            if !self.advance(&mut pos) {
+                return;
+            }
+        }
+        while !self.is_word(pos.0, pos.1) {
+            if !self.advance(&mut pos) {
+                return;
+            }
+        }
+        (self.row, self.col) = pos;
+    }
+
+    fn previous_word(&mut self) {
+        let mut pos = (self.row, self.col);
+        if !self.retreat(&mut pos) {
+            return;
+        }
+        while !self.is_word(pos.0, pos.1) {
+            if !self.retreat(&mut pos) {
+                return;
+            }
+        }
+        while pos.1 > 0 && self.is_word(pos.0, pos.1 - 1) {
+            pos.1 -= 1;
+        }
+        (self.row, self.col) = pos;
+    }
+
+    fn next_word_end(&mut self) {
+        let mut pos = (self.row, self.col);
+        if !self.advance(&mut pos) {
+            return;
+        }
+        while !self.is_word(pos.0, pos.1) {
+            if !self.advance(&mut pos) {
+                return;
+            }
+        }
+        while self.is_word(pos.0, pos.1 + 1) {
+            pos.1 += 1;
+        }
+        (self.row, self.col) = pos;
+    }
+
+    fn advance(&self, pos: &mut (usize, usize)) -> bool {
+        if pos.1 + 1 < self.line_len(pos.0) {
+            pos.1 += 1;
+        } else if pos.0 + 1 < self.lines.len() {
+            *pos = (pos.0 + 1, 0);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn retreat(&self, pos: &mut (usize, usize)) -> bool {
+        if pos.1 > 0 {
+            pos.1 = (pos.1 - 1).min(self.line_len(pos.0).saturating_sub(1));
+        } else if pos.0 > 0 {
+            pos.0 -= 1;
+            pos.1 = self.line_len(pos.0).saturating_sub(1);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn find_from_origin(&mut self, re: &Regex, forward: bool, origin: (usize, usize)) {
+        if let Some(hit) =
+            self.match_at(re, origin.0).into_iter().find(|&c| if forward { c >= origin.1 } else { c <= origin.1 })
+        {
+            (self.row, self.col) = (origin.0, hit);
+        } else {
+            self.find(re, forward, origin);
+        }
+    }
+
+    /// Move to the next match strictly after (or before) `from`, wrapping around the history.
+    fn find(&mut self, re: &Regex, forward: bool, from: (usize, usize)) {
+        let n = self.lines.len();
+        for step in 0..=n {
+            let r = if forward { (from.0 + step) % n } else { (from.0 + n - step % n) % n };
+            let hits = self.match_at(re, r);
+            let hit = match (step, forward) {
+                (0, true) => hits.into_iter().find(|&c| c > from.1),
+                (0, false) => hits.into_iter().rev().find(|&c| c < from.1),
+                (_, true) => hits.into_iter().next(),
+                (_, false) => hits.into_iter().next_back(),
+            };
+            if let Some(c) = hit {
+                (self.row, self.col) = (r, c);
+                return;
+            }
+        }
+    }
+
+    /// Char offsets of every match start in line `r`.
+    fn match_at(&self, re: &Regex, r: usize) -> Vec<usize> {
+        let line: String = self.lines[r].iter().collect();
+        re.find_iter(&line).map(|m| line[..m.start()].chars().count()).collect()
+    }
+
+    fn selection_bounds(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.anchor?;
+        let cur = (self.row, self.col);
+        Some(if anchor <= cur { (anchor, cur) } else { (cur, anchor) })
+    }
+
+    /// Inclusive selected column range on row `r`.
+    fn selected_cols(&self, r: usize) -> Option<(usize, usize)> {
+        let (a, b) = self.selection_bounds()?;
+        if r < a.0 || r > b.0 {
+            return None;
+        }
+        Some(match self.kind {
+            SelKind::Line => (0, usize::MAX - 1),
+            SelKind::Rect => (a.1.min(b.1), a.1.max(b.1)),
+            SelKind::Char => (if r == a.0 { a.1 } else { 0 }, if r == b.0 { b.1 } else { usize::MAX - 1 }),
+        })
+    }
+}
+
+/// Repaint a plain capture of the live screen after leaving copy mode.
+pub fn redraw(lines: &[String], cursor: (usize, usize)) -> Vec<u8> {
+    let mut out = String::from("\x1b[0m\x1b[H\x1b[2J");
+    out.push_str(&lines.join("\r\n"));
+    out.push_str(&format!("\x1b[{};{}H", cursor.0 + 1, cursor.1 + 1));
+    out.into_bytes()
+}
@@ -0,0 +1,511 @@
+use super::copy::{self, CopyCmd, ModeKeys};
+use crate::ipc::proto::Request;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+pub const ROOT: &str = "root";
+pub const PREFIX: &str = "prefix";
+
+pub const MOD_SHIFT: u8 = 1 << 0;
+pub const MOD_ALT: u8 = 1 << 1;
+pub const MOD_CTRL: u8 = 1 << 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Code {
+    Char(char),
+    F(u8),
+    Enter,
+    Tab,
+    BackTab,
+    Backspace,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+}
+
+/// A single key press in tmux-like notation, e.g. `C-b`, `M-Left`, `F5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key {
+    pub code: Code,
+    pub mods: u8,
+}
+
+impl Key {
+    pub const fn new(code: Code, mods: u8) -> Self {
+        Self { code, mods }
+    }
+    pub const fn ctrl(c: char) -> Self {
+        Self { code: Code::Char(c), mods: MOD_CTRL }
+    }
+
+    /// Translate a crossterm key event; `None` for keys we do not model.
+    pub fn from_event(ev: &crossterm::event::KeyEvent) -> Option<Self> {
+        use crossterm::event::{KeyCode as K, KeyModifiers as M};
+        let mut mods = 0;
+        if ev.modifiers.contains(M::SHIFT) {
+            mods |= MOD_SHIFT;
+        }
+        if ev.modifiers.contains(M::ALT) {
+            mods |= MOD_ALT;
+        }
+        if ev.modifiers.contains(M::CONTROL) {
+            mods |= MOD_CTRL;
+        }
+        let code = match ev.code {
+            // the char already carries the shift state
+            K::Char(c) => {
+                mods &= !MOD_SHIFT;
+                Code::Char(if mods & MOD_CTRL != 0 { c.to_ascii_lowercase() } else { c })
+            }
+            K::F(n) => Code::F(n),
+            K::Enter => Code::Enter,
+            K::Tab => Code::Tab,
+            K::BackTab => {
+                mods &= !MOD_SHIFT;
+                Code::BackTab
+            }
+            K::Backspace => Code::Backspace,
+            K::Esc => Code::Esc,
+            K::Up => Code::Up,
+            K::Down => Code::Down,
+            K::Left => Code::Left,
+            K::Right => Code::Right,
+            K::Home => Code::Home,
+            K::End => Code::End,
+            K::PageUp => Code::PageUp,
+            K::PageDown => Code::PageDown,
+            K::Insert => Code::Insert,
+            K::Delete => Code::Delete,
+            _ => return None,
+        };
+        Some(Self { code, mods })
+    }
+
+    /// Bytes a terminal would send for this key (xterm conventions).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if self.mods & MOD_ALT != 0 {
+            out.push(0x1b);
+        }
+        let csi_mod = 1
+            + (self.mods & MOD_SHIFT != 0) as u8
+            + 2 * (self.mods & MOD_ALT != 0) as u8
+            + 4 * (self.mods & MOD_CTRL != 0) as u8;
+        let csi = |out: &mut Vec<u8>, fin: char| {
+            if csi_mod > 1 {
+                out.extend_from_slice(format!("\x1b[1;{csi_mod}{fin}").as_bytes());
+            } else {
+                out.extend_from_slice(format!("\x1b[{fin}").as_bytes());
+            }
+        };
+        let tilde = |out: &mut Vec<u8>, n: u8| {
+            if csi_mod > 1 {
+                out.extend_from_slice(format!("\x1b[{n};{csi_mod}~").as_bytes());
+            } else {
+                out.extend_from_slice(format!("\x1b[{n}~").as_bytes());
+            }
+        };
+        match self.code {
+            Code::Char(c) if self.mods & MOD_CTRL != 0 => match c {
+                'a'..='z' => out.push(c as u8 & 0x1f),
+                '@' | ' ' | '2' => out.push(0),
+                '[' | '3' => out.push(0x1b),
+                '\\' | '4' => out.push(0x1c),
+                ']' | '5' => out.push(0x1d),
+                '^' | '6' => out.push(0x1e),
+                '_' | '7' | '/' => out.push(0x1f),
+                '?' | '8' => out.push(0x7f),
+                c => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            },
+            Code::Char(c) => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            Code::Enter => out.push(b'\r'),
+            Code::Tab => out.push(b'\t'),
+            Code::BackTab => out.extend_from_slice(b"\x1b[Z"),
+            Code::Backspace => out.push(0x7f),
+            Code::Esc => out.push(0x1b),
+            Code::Up => csi(&mut out, 'A'),
+            Code::Down => csi(&mut out, 'B'),
+            Code::Right => csi(&mut out, 'C'),
+            Code::Left => csi(&mut out, 'D'),
+            Code::Home => csi(&mut out, 'H'),
+            Code::End => csi(&mut out, 'F'),
+            Code::Insert => tilde(&mut out, 2),
+            Code::Delete => tilde(&mut out, 3),
+            Code::PageUp => tilde(&mut out, 5),
+            Code::PageDown => tilde(&mut out, 6),
+            Code::F(n @ 1..=4) if csi_mod == 1 => {
+                out.extend_from_slice(&[0x1b, b'O', b'P' + (n - 1)]);
+            }
+            Code::F(n @ 1..=4) => csi(&mut out, (b'P' + (n - 1)) as char),
+            Code::F(n) => {
+                let code = match n {
+                    5 => 15,
+                    6 => 17,
+                    7 => 18,
+                    8 => 19,
+                    9 => 20,
+                    10 => 21,
+                    11 => 23,
+                    12 => 24,
+                    _ => return out,
+                };
+                tilde(&mut out, code);
+            }
+        }
+        out
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.mods & MOD_CTRL != 0 {
+            f.write_str("C-")?;
+        }
+        if self.mods & MOD_ALT != 0 {
+            f.write_str("M-")?;
+        }
+        if self.mods & MOD_SHIFT != 0 {
+            f.write_str("S-")?;
+        }
+        match self.code {
+            Code::Char(' ') => f.write_str("Space"),
+            Code::Char(c) => write!(f, "{c}"),
+            Code::F(n) => write!(f, "F{n}"),
+            Code::Enter => f.write_str("Enter"),
+            Code::Tab => f.write_str("Tab"),
+            Code::BackTab => f.write_str("BTab"),
+            Code::Backspace => f.write_str("BSpace"),
+            Code::Esc => f.write_str("Escape"),
+            Code::Up => f.write_str("Up"),
+            Code::Down => f.write_str("Down"),
+            Code::Left => f.write_str("Left"),
+            Code::Right => f.write_str("Right"),
+            Code::Home => f.write_str("Home"),
+            Code::End => f.write_str("End"),
+            Code::PageUp => f.write_str("PPage"),
+            Code::PageDown => f.write_str("NPage"),
+            Code::Insert => f.write_str("IC"),
+            Code::Delete => f.write_str("DC"),
+        }
+    }
+}
+
+impl std::str::FromStr for Key {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let mut rest = s;
+        let mut mods = 0;
+        // a lone "-" or "C--" is the minus key, so only strip while something follows
+        while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+            match rest.as_bytes()[0] {
+                b'C' | b'c' => mods |= MOD_CTRL,
+                b'M' | b'm' => mods |= MOD_ALT,
+                b'S' | b's' => mods |= MOD_SHIFT,
+                _ => break,
+            }
+            rest = &rest[2..];
+        }
+        let code = match rest {
+            "Space" => Code::Char(' '),
+            "Enter" => Code::Enter,
+            "Tab" => Code::Tab,
+            "BTab" => Code::BackTab,
+            "BSpace" => Code::Backspace,
+            "Escape" | "Esc" => Code::Esc,
+            "Up" => Code::Up,
+            "Down" => Code::Down,
+            "Left" => Code::Left,
+            "Right" => Code::Right,
+            "Home" => Code::Home,
+            "End" => Code::End,
+            "PPage" | "PageUp" => Code::PageUp,
+            "NPage" | "PageDown" => Code::PageDown,
+            "IC" | "Insert" => Code::Insert,
+            "DC" | "Delete" => Code::Delete,
+            f if f.len() > 1 && f.starts_with('F') && f[1..].parse::<u8>().is_ok_and(|n| (1..=12).contains(&n)) => {
+                Code::F(f[1..].parse().unwrap_or(1))
+            }
+            c if c.chars().count() == 1 => {
+                let ch = c.chars().next().unwrap_or(' ');
+                Code::Char(if mods & MOD_CTRL != 0 { ch.to_ascii_lowercase() } else { ch })
+            }
+            _ => return Err(Error::UserInput(format!("unknown key: {s}"))),
+        };
+        Ok(Self { code, mods })
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// What a bound key does.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Action {
+    /// Send the prefix key itself to the pane.
+    SendPrefix,
+    /// Make another table current for the next key.
+    SwitchTable(String),
+    /// Detach this client.
+    Detach,
+    /// Forward a request to the server.
+    Request(Box<Request>),
+    /// Run a Lua chunk in the client runtime.
+    Lua(String),
+    /// Enter copy mode on the attached pane.
+    CopyMode,
+    /// Paste the newest paste buffer into the attached pane.
+    PasteBuffer,
+    /// A copy-mode command; ignored outside copy mode.
+    Copy(CopyCmd),
+    /// Ask the attached pane's input owner for input; with `force` (admins only), take it.
+    RequestInput { force: bool },
+    /// Hand input to the peer that last asked for it.
+    GiveInput,
+}
+
+impl std::str::FromStr for Action {
+    type Err = Error;
+    /// Parse the textual form used by configs: `detach`, `send-prefix`,
+    /// `switch-table NAME`, `lua CHUNK`, `copy-mode`, `copy CMD`, `paste-buffer`, `request-input`,
+    /// `take-input`, `give-input`.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (cmd, arg) = s.split_once(char::is_whitespace).map_or((s, ""), |(c, a)| (c, a.trim()));
+        match (cmd, arg) {
+            ("send-prefix", "") => Ok(Self::SendPrefix),
+            ("detach", "") => Ok(Self::Detach),
+            ("switch-table", t) if !t.is_empty() => Ok(Self::SwitchTable(t.to_string())),
+            ("lua", chunk) if !chunk.is_empty() => Ok(Self::Lua(chunk.to_string())),
+            ("copy-mode", "") => Ok(Self::CopyMode),
+            ("paste-buffer", "") => Ok(Self::PasteBuffer),
+            ("copy", cmd) => Ok(Self::Copy(cmd.parse()?)),
+            ("request-input", "") => Ok(Self::RequestInput { force: false }),
+            ("take-input", "") => Ok(Self::RequestInput { force: true }),
+            ("give-input", "") => Ok(Self::GiveInput),
+            _ => Err(Error::UserInput(format!("unknown action: {s}"))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Binding {
+    pub action: Action,
+    /// Keep the table active for another key within the repeat window.
+    pub repeat: bool,
+}
+
+impl Binding {
+    pub fn new(action: Action) -> Self {
+        Self { action, repeat: false }
+    }
+    pub fn repeat(mut self) -> Self {
+        self.repeat = true;
+        self
+    }
+}
+
+pub type KeyTable = BTreeMap<Key, Binding>;
+
+/// Named key tables (`root`, `prefix`, `copy-mode`, user tables).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct KeyTables {
+    tables: BTreeMap<String, KeyTable>,
+}
+
+impl KeyTables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind(&mut self, table: &str, key: Key, binding: Binding) {
+        self.tables.entry(table.to_string()).or_default().insert(key, binding);
+    }
+    pub fn unbind(&mut self, table: &str, key: Key) -> Option<Binding> {
+        self.tables.get_mut(table).and_then(|t| t.remove(&key))
+    }
+    pub fn lookup(&self, table: &str, key: Key) -> Option<&Binding> {
+        self.tables.get(table).and_then(|t| t.get(&key))
+    }
+    pub fn tables(&self) -> impl Iterator<Item = (&String, &KeyTable)> {
+        self.tables.iter()
+    }
+}
+
+/// Result of feeding one key into the engine.
+#[derive(Debug)]
+pub enum Outcome {
+    /// Unbound key; send these bytes to the focused pane.
+    Forward(Vec<u8>),
+    /// Bound key; run the action.
+    Run(Action),
+    /// Swallowed (prefix pressed, unbound key in a non-root table).
+    Consumed,
+}
+
+/// Client-side key dispatcher: prefix handling, table switching and repeat.
+///
+/// A modal table (copy mode) replaces `root` as the base table until left; the prefix key still
+/// works from it and unbound keys are swallowed instead of reaching the pane.
+pub struct KeyEngine {
+    tables: KeyTables,
+    prefix: Key,
+    current: String,
+    mode: Option<String>,
+    mode_keys: ModeKeys,
+    repeat_time: Duration,
+    repeat_until: Option<Instant>,
+}
+
+impl Default for KeyEngine {
+    fn default() -> Self {
+        Self::new(Key::ctrl('b'))
+    }
+}
+
+impl KeyEngine {
+    pub fn new(prefix: Key) -> Self {
+        let mut tables = KeyTables::new();
+        tables.bind(PREFIX, prefix, Binding::new(Action::SendPrefix));
+        tables.bind(PREFIX, Key::new(Code::Char('d'), 0), Binding::new(Action::Detach));
+        tables.bind(PREFIX, Key::new(Code::Char('['), 0), Binding::new(Action::CopyMode));
+        tables.bind(PREFIX, Key::new(Code::Char(']'), 0), Binding::new(Action::PasteBuffer));
+        tables.bind(PREFIX, Key::new(Code::Char('i'), 0), Binding::new(Action::RequestInput { force: false }));
+        tables.bind(PREFIX, Key::new(Code::Char('g'), 0), Binding::new(Action::GiveInput));
+        copy::bind_defaults(&mut tables);
+        Self {
+            tables,
+            prefix,
+            current: ROOT.into(),
+            mode: None,
+            mode_keys: ModeKeys::Emacs,
+            repeat_time: Duration::from_millis(500),
+            repeat_until: None,
+        }
+    }
+
+    pub fn prefix(&self) -> Key {
+        self.prefix
+    }
+    /// Change the prefix; the `send-prefix` default follows it.
+    pub fn set_prefix(&mut self, key: Key) {
+        if matches!(self.tables.lookup(PREFIX, self.prefix), Some(Binding { action: Action::SendPrefix, .. })) {
+            self.tables.unbind(PREFIX, self.prefix);
+            self.tables.bind(PREFIX, key, Binding::new(Action::SendPrefix));
+        }
+        self.prefix = key;
+    }
+    pub fn set_repeat_time(&mut self, d: Duration) {
+        self.repeat_time = d;
+    }
+    pub fn tables(&self) -> &KeyTables {
+        &self.tables
+    }
+    pub fn tables_mut(&mut self) -> &mut KeyTables {
+        &mut self.tables
+    }
+
+    pub fn current_table(&self) -> &str {
+        &self.current
+    }
+
+    pub fn set_mode_keys(&mut self, keys: ModeKeys) {
+        self.mode_keys = keys;
+    }
+    /// Table used for copy mode under the current `mode_keys`.
+    pub fn copy_table(&self) -> &'static str {
+        self.mode_keys.table()
+    }
+
+    /// Make `table` the base table until [`leave_mode`](Self::leave_mode).
+    pub fn enter_mode(&mut self, table: impl Into<String>) {
+        let table = table.into();
+        self.mode = Some(table.clone());
+        self.set_table(table);
+    }
+    pub fn leave_mode(&mut self) {
+        self.mode = None;
+        self.reset();
+    }
+    pub fn in_mode(&self) -> bool {
+        self.mode.is_some()
+    }
+    pub fn set_table(&mut self, name: impl Into<String>) {
+        self.current = name.into();
+        self.repeat_until = None;
+    }
+
+    pub fn feed(&mut self, key: Key, now: Instant) -> Outcome {
+        let base = self.mode.clone().unwrap_or_else(|| ROOT.into());
+        if self.current != base {
+            let repeating = self.repeat_until.is_some();
+            if self.repeat_until.is_some_and(|t| now > t) {
+                self.reset();
+                return self.feed(key, now);
+            }
+            match self.tables.lookup(&self.current, key).cloned() {
+                Some(b) if !repeating || b.repeat => {
+                    if b.repeat {
+                        self.repeat_until = Some(now + self.repeat_time);
+                    } else {
+                        self.reset();
+                    }
+                    return self.run(b);
+                }
+                // a non-repeatable key ends the repeat window and is handled from root
+                _ if repeating => {
+                    self.reset();
+                    return self.feed(key, now);
+                }
+                _ => {
+                    self.reset();
+                    return Outcome::Consumed;
+                }
+            }
+        }
+
+        if key == self.prefix {
+            self.set_table(PREFIX);
+            return Outcome::Consumed;
+        }
+        match self.tables.lookup(&base, key).cloned() {
+            Some(b) => self.run(b),
+            None if self.mode.is_some() => Outcome::Consumed,
+            None => Outcome::Forward(key.to_bytes()),
+        }
+    }
+
+    fn run(&mut self, b: Binding) -> Outcome {
+        match b.action {
+            Action::SwitchTable(name) => {
+                self.set_table(name);
+                Outcome::Consumed
+            }
+            action => Outcome::Run(action),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.current = self.mode.clone().unwrap_or_else(|| ROOT.into());
+        self.repeat_until = None;
+    }
+}
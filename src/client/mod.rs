@@ -0,0 +1,272 @@
+pub mod copy;
+pub mod keys;
+
+use crate::ipc::{
+    client::IpcClient,
+    proto::{Event, FEATURE_LZ4, Request, Response, StateScope},
+};
+use crate::lua::LuaRuntime;
+use crate::pty::Backpressure;
+use crate::server::{pane::PaneId, session::SessionId, window::WindowId};
+use crate::{Error, Result};
+use copy::{CopyMode, CopyOutcome};
+use keys::{Action, Key, KeyEngine, Outcome};
+use std::{io::Write, path::PathBuf, time::Instant};
+use tokio::sync::mpsc;
+
+pub struct AttachOptions {
+    pub socket: PathBuf,
+    /// Offer compressed frames (see [`FEATURE_LZ4`]).
+    pub compress: bool,
+    pub session: SessionId,
+    pub window: Option<WindowId>,
+    pub pane: Option<PaneId>,
+    pub prefix: Option<Key>,
+    /// Lua file run before attaching (key bindings, prefix).
+    pub config: Option<PathBuf>,
+    /// Slow-client policy; the server default (drop and redraw) when `None`.
+    pub backpressure: Option<Backpressure>,
+    /// Output frame rate; the server default when `None`.
+    pub fps: Option<u16>,
+    /// Ask for screen diffs instead of the raw output.
+    pub diffs: bool,
+    /// Watch only; keys still work locally (detach, copy mode) but nothing reaches the pane.
+    pub read_only: bool,
+}
+
+/// Interactive attach: raw terminal in, pane output out, keys routed through the key tables.
+pub async fn attach(opts: AttachOptions) -> Result {
+    let features = if opts.compress { FEATURE_LZ4 } else { 0 };
+    let mut client = IpcClient::connect_with(&opts.socket.to_string_lossy(), features).await?;
+    let mut events = client.take_events();
+
+    let mut engine = KeyEngine::default();
+    if let Some(prefix) = opts.prefix {
+        engine.set_prefix(prefix);
+    }
+    let lua = LuaRuntime::new()?;
+    if let Some(ref path) = opts.config {
+        lua.exec_file(path)?;
+        lua.apply_keys(&mut engine);
+    }
+
+    if let Some(policy) = opts.backpressure {
+        client.request(Request::SetBackpressure { policy }).await?;
+    }
+    if opts.fps.is_some() || opts.diffs {
+        client.request(Request::SetOutputRate { fps: opts.fps, diffs: opts.diffs.then_some(true) }).await?;
+    }
+    let attach =
+        Request::Attach { session: opts.session, window: opts.window, pane: opts.pane, read_only: opts.read_only };
+    let pane = match client.request(attach).await? {
+        Response::Attached { pane, .. } => pane,
+        Response::Err { code, msg } => return Err(Error::Ipc(format!("{code:?}: {msg}"))),
+        other => return Err(Error::Ipc(format!("unexpected response: {other:?}"))),
+    };
+
+    crossterm::terminal::enable_raw_mode()?;
+    let res = run_attached(&mut client, &mut events, &mut engine, &lua, pane).await;
+    let _ = crossterm::terminal::disable_raw_mode();
+    res
+}
+
+async fn run_attached(
+    client: &mut IpcClient,
+    events: &mut mpsc::UnboundedReceiver<Event>,
+    engine: &mut KeyEngine,
+    lua: &LuaRuntime,
+    pane: PaneId,
+) -> Result {
+    if let Ok((cols, rows)) = crossterm::terminal::size() {
+        client.request(Request::Resize { pane, cols, rows }).await?;
+    }
+
+    // crossterm's reader blocks, so it gets its own thread
+    let (term_tx, mut term_rx) = mpsc::channel::<crossterm::event::Event>(64);
+    std::thread::spawn(move || {
+        while let Ok(ev) = crossterm::event::read() {
+            if term_tx.blocking_send(ev).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut stdout = std::io::stdout();
+    // pane output is not drawn while copy mode owns the screen
+    let mut copy: Option<CopyMode> = None;
+    // the peer that last asked us for the pane's input, for `give-input`
+    let mut asked_by = None;
+    loop {
+        tokio::select! {
+            ev = events.recv() => match ev {
+                Some(Event::PtyOutput { pane: p, chunk }) if p == pane && copy.is_none() => {
+                    stdout.write_all(&chunk)?;
+                    stdout.flush()?;
+                }
+                Some(Event::KeyBound { table, key, binding }) => match binding {
+                    Some(b) => engine.tables_mut().bind(&table, key, b),
+                    None => {
+                        engine.tables_mut().unbind(&table, key);
+                    }
+                },
+                Some(Event::Clipboard { selection, data, .. }) => draw(&osc52(&selection, &data))?,
+                Some(Event::PaneExited { pane: p, closed: true, .. }) if p == pane => return Ok(()),
+                Some(Event::InputRequested { pane: p, peer }) if p == pane => {
+                    rustlog::info!("peer {peer} asks for input; give-input hands it over");
+                    asked_by = Some(peer);
+                }
+                Some(Event::InputOwnerChanged { pane: p, mode, owner }) if p == pane => {
+                    rustlog::info!("input is {mode}, owned by {}", owner.map_or("nobody".into(), |o| o.to_string()));
+                    asked_by = None;
+                }
+                Some(Event::Bye { .. }) | None => return Ok(()),
+                Some(_) => {}
+            },
+            ev = term_rx.recv() => match ev {
+                Some(crossterm::event::Event::Key(k)) if k.kind != crossterm::event::KeyEventKind::Release => {
+                    let Some(key) = Key::from_event(&k) else { continue };
+                    if let Some(cm) = copy.as_mut().filter(|cm| cm.prompting()) {
+                        let outcome = cm.prompt_key(key);
+                        copy_outcome(client, engine, &mut copy, pane, outcome).await?;
+                        continue;
+                    }
+                    let action = match engine.feed(key, Instant::now()) {
+                        Outcome::Forward(data) => {
+                            client.request(Request::Input { pane, data }).await?;
+                            continue;
+                        }
+                        Outcome::Consumed => continue,
+                        Outcome::Run(action) => action,
+                    };
+                    match action {
+                        Action::SendPrefix => {
+                            client.request(Request::Input { pane, data: engine.prefix().to_bytes() }).await?;
+                        }
+                        Action::SwitchTable(name) => engine.set_table(name),
+                        Action::Detach => {
+                            client.request(Request::Detach { target: None }).await?;
+                            return Ok(());
+                        }
+                        Action::Request(req) => {
+                            if let Response::Err { code, msg } = client.request(*req).await? {
+                                rustlog::warn!("request failed: {code:?}: {msg}");
+                            }
+                        }
+                        Action::Lua(chunk) => {
+                            let req = Request::GetState { scope: StateScope::Panes { window: None } };
+                            if let Response::State { json } = client.request(req).await? {
+                                lua.set_panes(&pane.to_string(), &json)?;
+                            }
+                            if let Err(e) = lua.exec(&chunk) {
+                                rustlog::warn!("lua: {e}");
+                            }
+                            lua.apply_keys(engine);
+                        }
+                        Action::CopyMode if copy.is_none() => {
+                            let (lines, cursor) = capture(client, pane, true).await?;
+                            let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+                            let cm = CopyMode::new(lines, cursor, cols, rows);
+                            draw(&cm.render())?;
+                            copy = Some(cm);
+                            engine.enter_mode(engine.copy_table());
+                        }
+                        Action::CopyMode => {}
+                        Action::PasteBuffer => {
+                            let req = Request::PasteBuffer { pane, name: None, delete: false };
+                            if let Response::Err { code, msg } = client.request(req).await? {
+                                rustlog::warn!("paste failed: {code:?}: {msg}");
+                            }
+                        }
+                        Action::RequestInput { force } => {
+                            if let Response::Err { code, msg } =
+                                client.request(Request::RequestInput { pane, force }).await?
+                            {
+                                rustlog::warn!("request-input failed: {code:?}: {msg}");
+                            }
+                        }
+                        Action::GiveInput => {
+                            let Some(to) = asked_by.take() else { continue };
+                            if let Response::Err { code, msg } =
+                                client.request(Request::GiveInput { pane, to: Some(to) }).await?
+                            {
+                                rustlog::warn!("give-input failed: {code:?}: {msg}");
+                            }
+                        }
+                        Action::Copy(cmd) => {
+                            if let Some(outcome) = copy.as_mut().map(|cm| cm.apply(cmd)) {
+                                copy_outcome(client, engine, &mut copy, pane, outcome).await?;
+                            }
+                        }
+                    }
+                }
+                Some(crossterm::event::Event::Paste(text)) if copy.is_none() => {
+                    client.request(Request::Input { pane, data: text.into_bytes() }).await?;
+                }
+                Some(crossterm::event::Event::Resize(cols, rows)) => {
+                    client.request(Request::Resize { pane, cols, rows }).await?;
+                    if copy.take().is_some() {
+                        leave_copy(client, engine, pane).await?;
+                    }
+                }
+                Some(_) => {}
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+async fn copy_outcome(
+    client: &mut IpcClient,
+    engine: &mut KeyEngine,
+    copy: &mut Option<CopyMode>,
+    pane: PaneId,
+    outcome: CopyOutcome,
+) -> Result {
+    match outcome {
+        CopyOutcome::Redraw => {
+            if let Some(cm) = copy {
+                draw(&cm.render())?;
+            }
+            return Ok(());
+        }
+        CopyOutcome::Copied(text) => {
+            draw(&osc52("c", text.as_bytes()))?;
+            if let Response::Err { code, msg } =
+                client.request(Request::SetBuffer { name: None, data: text.into_bytes() }).await?
+            {
+                rustlog::warn!("copy failed: {code:?}: {msg}");
+            }
+        }
+        CopyOutcome::Exit => {}
+    }
+    *copy = None;
+    leave_copy(client, engine, pane).await
+}
+
+/// Back to the live pane: restore the root table and repaint what the pane shows now.
+async fn leave_copy(client: &mut IpcClient, engine: &mut KeyEngine, pane: PaneId) -> Result {
+    engine.leave_mode();
+    let (lines, cursor) = capture(client, pane, false).await?;
+    draw(&copy::redraw(&lines, cursor))
+}
+
+async fn capture(client: &mut IpcClient, pane: PaneId, history: bool) -> Result<(Vec<String>, (usize, usize))> {
+    match client.request(Request::Capture { pane, history }).await? {
+        Response::Captured { lines, cursor } => Ok((lines, cursor)),
+        Response::Err { code, msg } => Err(Error::Ipc(format!("{code:?}: {msg}"))),
+        other => Err(Error::Ipc(format!("unexpected response: {other:?}"))),
+    }
+}
+
+/// OSC 52 set-clipboard for the outer terminal.
+fn osc52(selection: &str, data: &[u8]) -> Vec<u8> {
+    use base64::Engine as _;
+    format!("\x1b]52;{selection};{}\x07", base64::engine::general_purpose::STANDARD.encode(data)).into_bytes()
+}
+
+fn draw(bytes: &[u8]) -> Result {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(bytes)?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -54,6 +54,16 @@ impl From<crate::pty::PtyError> for Error {
             ResizeFailed => Self::Pty("resize failed".into()),
             WaitFailed => Self::Pty("wait failed".into()),
             Unsupported => Self::Pty("unsupported PTY op".into()),
+            InputFull => Self::Timeout("the program is not reading its input".into()),
+        }
+    }
+}
+
+impl From<crate::ipc::wire::FrameError> for Error {
+    fn from(e: crate::ipc::wire::FrameError) -> Self {
+        match e {
+            crate::ipc::wire::FrameError::Io(io) => Self::Io(io),
+            e => Self::Ipc(e.to_string()),
         }
     }
 }
@@ -11,36 +11,63 @@ use tokio::{
 
 pub struct IpcClient {
     w: OwnedWriteHalf,
-    ev_rx: mpsc::Receiver<Event>,
+    ev_rx: mpsc::UnboundedReceiver<Event>,
     // Single-flight waiter: next Response will be delivered here by the reader task
     resp_waiter: Arc<tokio::sync::Mutex<Option<oneshot::Sender<Response>>>>,
+    /// The server agreed to [`FEATURE_LZ4`].
+    compress: bool,
 }
 
 impl IpcClient {
     pub async fn connect(path: &str) -> Result<Self> {
+        Self::connect_with(path, 0).await
+    }
+
+    /// Connect offering the given `Hello.features` bits, e.g. [`FEATURE_LZ4`] for a socket
+    /// forwarded over a slow link.
+    pub async fn connect_with(path: &str, features: u64) -> Result<Self> {
         let sock = UnixStream::connect(path).await?;
         let (mut r, mut w): (OwnedReadHalf, OwnedWriteHalf) = sock.into_split();
 
         // Hello
-        let hello = Hello { client_api_major: 1, features: 0 };
-        let bytes = to_vec(&hello).unwrap();
-        let hdr = FrameHeader { api_major: 1, kind: Kind::Request, schema_id: 0, len: bytes.len() as u32 };
+        let hello = Hello { client_api_major: API_MAJOR, features };
+        let (hdr, bytes) = frame(Kind::Request, 0, to_vec(&hello).unwrap(), false);
         write_payload(&mut w, hdr, &bytes).await?;
-        let (_hdr, _ack) = read_payload(&mut r).await?; // could validate majors/features
+        let (hdr, ack) = read_payload(&mut r, MAX_FRAME).await?;
+        if let (Kind::Response, 1) = (hdr.kind, hdr.schema_id) {
+            // refused, e.g. a uid the server does not allow
+            return Err(match from_slice::<Response>(&ack) {
+                Ok(Response::Err { code, msg }) => Error::Ipc(format!("{code:?}: {msg}")),
+                _ => Error::Ipc("unexpected response during handshake".into()),
+            });
+        }
+        if let Kind::Event = hdr.kind {
+            // turned away, e.g. for speaking another API version
+            return Err(match from_slice::<Event>(&ack) {
+                Ok(Event::Bye { reason }) => Error::Ipc(reason),
+                _ => Error::Ipc("unexpected event during handshake".into()),
+            });
+        }
+        let ack: HelloAck = from_slice(&ack).map_err(|e| Error::Ipc(e.to_string()))?;
+        if ack.server_api_major != API_MAJOR {
+            return Err(FrameError::ApiMajor(ack.server_api_major).into());
+        }
+        let compress = ack.features & features & FEATURE_LZ4 != 0;
 
         // Event & response routing
-        let (ev_tx, ev_rx) = mpsc::channel(256);
+        // unbounded: a response queued behind a burst of events must never be blocked by them
+        let (ev_tx, ev_rx) = mpsc::unbounded_channel();
         let resp_waiter: Arc<tokio::sync::Mutex<Option<oneshot::Sender<Response>>>> =
             Arc::new(tokio::sync::Mutex::new(None));
         let resp_waiter_reader = resp_waiter.clone();
 
         // Single reader/demux task owns the read half
         tokio::spawn(async move {
-            while let Ok((hdr, bytes)) = read_payload(&mut r).await {
+            while let Ok((hdr, bytes)) = read_payload(&mut r, MAX_FRAME).await {
                 match hdr.kind {
                     Kind::Event => {
                         if let Ok(ev) = from_slice::<Event>(&bytes) {
-                            let _ = ev_tx.send(ev).await;
+                            let _ = ev_tx.send(ev);
                         }
                     }
                     Kind::Response => {
@@ -55,7 +82,7 @@ impl IpcClient {
             }
         });
 
-        Ok(Self { w, ev_rx, resp_waiter })
+        Ok(Self { w, ev_rx, resp_waiter, compress })
     }
 
     pub async fn request(&mut self, req: Request) -> Result<Response> {
@@ -63,8 +90,7 @@ impl IpcClient {
         let (tx, rx) = oneshot::channel();
         *self.resp_waiter.lock().await = Some(tx);
 
-        let bytes = to_vec(&req).unwrap();
-        let hdr = FrameHeader { api_major: 1, kind: Kind::Request, schema_id: 1, len: bytes.len() as u32 };
+        let (hdr, bytes) = frame(Kind::Request, 1, to_vec(&req).unwrap(), self.compress);
         write_payload(&mut self.w, hdr, &bytes).await?;
 
         match rx.await {
@@ -74,7 +100,7 @@ impl IpcClient {
     }
 
     /// Consume and return the event stream receiver.
-    pub fn take_events(&mut self) -> mpsc::Receiver<Event> {
-        std::mem::replace(&mut self.ev_rx, mpsc::channel(1).1)
+    pub fn take_events(&mut self) -> mpsc::UnboundedReceiver<Event> {
+        std::mem::replace(&mut self.ev_rx, mpsc::unbounded_channel().1)
     }
 }
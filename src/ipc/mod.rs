@@ -3,4 +3,7 @@ pub mod proto;
 pub mod server;
 pub mod wire;
 
-pub use proto::{DetachTarget, ErrorCode, Event, KillTarget, Request, Response, SessionLite, StateScope};
+pub use proto::{
+    BufferLite, DetachTarget, ErrorCode, Event, GrepMatch, GrepScope, KillTarget, PipeTarget, PolicyTarget,
+    RecordTarget, Request, Response, SessionLite, StateScope,
+};
@@ -1,5 +1,14 @@
-use crate::server::{pane::PaneId, peer::PeerId, session::SessionId, window::WindowId};
+use crate::client::keys::{Binding, Key};
+use crate::pty::{Backpressure, ExitStatus, KillStage, Sig};
+use crate::server::{
+    acl::{Grantee, Role},
+    pane::{AutoRespawn, InputMode, PaneId, RemainOnExit},
+    peer::PeerId,
+    session::{ClipboardPolicy, SessionId},
+    window::WindowId,
+};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum ErrorCode {
@@ -13,6 +22,12 @@ pub enum ErrorCode {
     Internal = 255,
 }
 
+/// `Hello.features` bit: large frames may be lz4-compressed (see [`FrameHeader::compressed`]).
+/// The server acks the bits it shares with the client, and only those are used.
+///
+/// [`FrameHeader::compressed`]: super::wire::FrameHeader::compressed
+pub const FEATURE_LZ4: u64 = 1 << 0;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Hello {
     pub client_api_major: u8,
@@ -24,7 +39,7 @@ pub struct HelloAck {
     pub features: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Request {
     CreateSession {
         name: Option<String>,
@@ -39,37 +54,249 @@ pub enum Request {
         window: Option<WindowId>,
         title: Option<String>,
         cwd: Option<String>,
+        /// With no `cwd`, start in this pane's current directory (e.g. the one being split).
+        cwd_from: Option<PaneId>,
         argv: Vec<String>,
+        /// Added to the session environment; later entries win.
+        env: Vec<(String, String)>,
+        /// `TERM` for the pane (default `xterm-256color`).
+        term: Option<String>,
+        /// Run instead of `$SHELL` when `argv` is empty, and exported as `SHELL`.
+        shell: Option<String>,
     },
     Attach {
         session: SessionId,
         window: Option<WindowId>,
         pane: Option<PaneId>,
+        /// Only watch: never take input ownership, and have input and resizes dropped. Peers
+        /// without `input` access to the session always attach this way.
+        read_only: bool,
     },
     Detach {
         target: Option<DetachTarget>,
     },
+    /// Choose what happens to pane output the requesting peer can't keep up with: lose it and
+    /// get a `StreamDropNotice` plus a redraw, or hold the pane's program back. Applies to the
+    /// current attachment at once. Read-only attachments always drop: `Block` is denied while
+    /// attached read-only, and left unused by a later read-only attach.
+    SetBackpressure {
+        policy: Backpressure,
+    },
+    /// Pace pane output to the requesting peer: at most `fps` frames a second (0: unpaced), and
+    /// with `diffs`, redraws of the rows that changed instead of the raw output, which suits
+    /// remote or slow peers. `None` keeps the current setting.
+    SetOutputRate {
+        fps: Option<u16>,
+        diffs: Option<bool>,
+    },
+    /// Staged shutdown of every pane in `target`; `grace` overrides the default TERM grace period.
     Kill {
         target: KillTarget,
         force: bool,
+        grace: Option<Duration>,
     },
     GetState {
         scope: StateScope,
     },
+    /// Type into a pane. In exclusive mode a pane nobody owns goes to the first peer that types.
+    Input {
+        pane: PaneId,
+        data: Vec<u8>,
+    },
+    /// Ask for input ownership of the attached pane. Granted at once when nobody holds it;
+    /// otherwise the owner gets `InputRequested` and may answer with `GiveInput`. With `force`,
+    /// which needs `admin` access, take it from the owner.
+    RequestInput {
+        pane: PaneId,
+        force: bool,
+    },
+    /// Hand input ownership to another attached peer, or release it with `None`. Only the owner
+    /// or an admin may.
+    GiveInput {
+        pane: PaneId,
+        to: Option<PeerId>,
+    },
+    SetInputMode {
+        pane: PaneId,
+        mode: InputMode,
+    },
+    Resize {
+        pane: PaneId,
+        cols: u16,
+        rows: u16,
+    },
+    Bind {
+        table: String,
+        key: Key,
+        binding: Binding,
+    },
+    Unbind {
+        table: String,
+        key: Key,
+    },
+    Capture {
+        pane: PaneId,
+        history: bool,
+    },
+    SetBuffer {
+        name: Option<String>,
+        data: Vec<u8>,
+    },
+    ListBuffers,
+    /// `name: None` addresses the newest buffer.
+    ShowBuffer {
+        name: Option<String>,
+    },
+    DeleteBuffer {
+        name: Option<String>,
+    },
+    PasteBuffer {
+        pane: PaneId,
+        name: Option<String>,
+        /// Remove the buffer once pasted.
+        delete: bool,
+    },
+    SetClipboardPolicy {
+        session: SessionId,
+        policy: ClipboardPolicy,
+    },
+    /// Restart an exited pane's program in place. Empty `argv` and `None` cwd reuse what the pane
+    /// was last spawned with; `kill` restarts a pane that is still running.
+    RespawnPane {
+        pane: PaneId,
+        argv: Vec<String>,
+        cwd: Option<String>,
+        kill: bool,
+    },
+    /// Respawn `pane` automatically whenever it exits; `None` turns that off.
+    SetAutoRespawn {
+        pane: PaneId,
+        policy: Option<AutoRespawn>,
+    },
+    /// Set what happens when a pane's program exits; `None` inherits from the enclosing
+    /// window or session.
+    SetRemainOnExit {
+        target: PolicyTarget,
+        policy: Option<RemainOnExit>,
+    },
+    /// Stream `pane`'s output into a command or file, carrying over to respawned processes
+    /// until the pane is closed; `None` stops it. `plain` strips escape sequences; `input` types
+    /// the command's stdout into the pane.
+    PipePane {
+        pane: PaneId,
+        target: Option<PipeTarget>,
+        plain: bool,
+        input: bool,
+    },
+    /// Record output and resizes as asciicast v2 to `path`, or stop for `None`. A window records
+    /// each of its panes to its own file, named after `path` with the pane ID appended. Recording
+    /// carries over to respawned processes and ends when the pane is closed.
+    Record {
+        target: RecordTarget,
+        path: Option<String>,
+    },
+    /// Set a variable every new pane of `session` inherits, or unset it for `None`.
+    SetEnvironment {
+        session: SessionId,
+        name: String,
+        value: Option<String>,
+    },
+    ShowEnvironment {
+        session: SessionId,
+    },
+    /// Name a window explicitly; `None` lets it follow its focused pane again.
+    RenameWindow {
+        window: WindowId,
+        name: Option<String>,
+    },
+    /// Deliver `signal` to the pane's process group, or to the terminal's foreground group.
+    Signal {
+        pane: PaneId,
+        signal: Sig,
+        foreground: bool,
+    },
+    /// Regex search over the screen and scrollback of every pane in `scope`.
+    Grep {
+        pattern: String,
+        scope: GrepScope,
+        /// Lines of context before and after each match.
+        context: usize,
+        ignore_case: bool,
+    },
+    /// Give a peer, or every connection of a user, `role` in `session`, replacing what it had.
+    /// The server's own user needs no grant.
+    Grant {
+        session: SessionId,
+        grantee: Grantee,
+        role: Role,
+    },
+    Revoke {
+        session: SessionId,
+        grantee: Grantee,
+    },
+    /// Answered with `Access`.
+    ShowAccess {
+        session: SessionId,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Response {
     Ok,
-    SessionCreated { session: SessionId },
-    Sessions { items: Vec<SessionLite> },
-    WindowCreated { window: WindowId },
-    PaneSpawned { session: SessionId, window: WindowId, pane: PaneId },
-    Attached,
+    SessionCreated {
+        session: SessionId,
+    },
+    Sessions {
+        items: Vec<SessionLite>,
+    },
+    WindowCreated {
+        window: WindowId,
+    },
+    PaneSpawned {
+        session: SessionId,
+        window: WindowId,
+        pane: PaneId,
+    },
+    Attached {
+        session: SessionId,
+        window: WindowId,
+        pane: PaneId,
+    },
     Detached,
-    Killed,
-    State { json: serde_json::Value },
-    Err { code: ErrorCode, msg: String },
+    Killed {
+        panes: Vec<(PaneId, KillStage)>,
+    },
+    State {
+        json: serde_json::Value,
+    },
+    /// `cursor` is (row, col) counted from the first returned line.
+    Captured {
+        lines: Vec<String>,
+        cursor: (usize, usize),
+    },
+    BufferSet {
+        name: String,
+    },
+    Buffers {
+        items: Vec<BufferLite>,
+    },
+    Buffer {
+        name: String,
+        data: Vec<u8>,
+    },
+    Environment {
+        vars: Vec<(String, String)>,
+    },
+    Matches {
+        items: Vec<GrepMatch>,
+    },
+    Access {
+        grants: Vec<(Grantee, Role)>,
+    },
+    Err {
+        code: ErrorCode,
+        msg: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
@@ -85,6 +312,24 @@ pub enum KillTarget {
     Pane(PaneId),
 }
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum PolicyTarget {
+    Session(SessionId),
+    Window(WindowId),
+    Pane(PaneId),
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum RecordTarget {
+    Window(WindowId),
+    Pane(PaneId),
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum PipeTarget {
+    /// Run through `sh -c` with the output on its stdin.
+    Command(String),
+    /// Appended to, created if missing.
+    File(String),
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum StateScope {
     Sessions,
     Windows { session: Option<SessionId> },
@@ -92,20 +337,98 @@ pub enum StateScope {
     Peers,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum GrepScope {
+    All,
+    Session(SessionId),
+    Window(WindowId),
+}
+
+/// One matching line; `line` counts from the oldest scrollback line, starting at 1.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GrepMatch {
+    pub session: SessionId,
+    pub window: WindowId,
+    pub pane: PaneId,
+    pub line: usize,
+    pub text: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SessionLite {
     pub id: SessionId,
     pub name: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BufferLite {
+    pub name: String,
+    pub size: usize,
+    pub automatic: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Event {
     // Use Vec<u8> for serde compatibility; convert from your ByteChunk on send
-    PtyOutput { pane: PaneId, chunk: Vec<u8> },
-    TitleChanged { window: WindowId, title: String },
-    LayoutChanged { window: WindowId },
-    PeerAttached { peer: PeerId, session: SessionId, window: WindowId, pane: PaneId },
-    PeerDetached { peer: PeerId },
-    Bye { reason: String },
-    StreamDropNotice { pane: PaneId },
+    PtyOutput {
+        pane: PaneId,
+        chunk: Vec<u8>,
+    },
+    TitleChanged {
+        window: WindowId,
+        title: String,
+    },
+    LayoutChanged {
+        window: WindowId,
+    },
+    PeerAttached {
+        peer: PeerId,
+        session: SessionId,
+        window: WindowId,
+        pane: PaneId,
+    },
+    PeerDetached {
+        peer: PeerId,
+    },
+    /// Who may type into a pane now; sent to its session on every change.
+    InputOwnerChanged {
+        pane: PaneId,
+        mode: InputMode,
+        owner: Option<PeerId>,
+    },
+    /// `peer` asked the owner for the pane's input.
+    InputRequested {
+        pane: PaneId,
+        peer: PeerId,
+    },
+    Bye {
+        reason: String,
+    },
+    /// Output was lost to a slow peer; a `PtyOutput` redrawing the screen follows.
+    StreamDropNotice {
+        pane: PaneId,
+    },
+    /// A program set the clipboard via OSC 52; clients re-emit it to their terminal.
+    Clipboard {
+        pane: PaneId,
+        selection: String,
+        data: Vec<u8>,
+    },
+    KeyBound {
+        table: String,
+        key: Key,
+        binding: Option<Binding>,
+    },
+    /// `closed` when the remain-on-exit policy removed the pane.
+    PaneExited {
+        pane: PaneId,
+        status: ExitStatus,
+        closed: bool,
+    },
+    /// The window's last pane was closed, and the window with it.
+    WindowClosed {
+        window: WindowId,
+    },
 }
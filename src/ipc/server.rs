@@ -1,47 +1,145 @@
 use super::proto::*;
 use super::wire::*;
-use crate::server::peer::PeerId;
+use crate::server::peer::{PeerCred, PeerId};
 use crate::{Error, Result};
 use rmp_serde::{from_slice, to_vec};
+use rustix::fs::Mode;
+use rustix::process::umask;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
     net::{UnixListener, UnixStream},
     sync::{mpsc, oneshot},
+    task::JoinHandle,
 };
 
 pub struct IpcServer {
     lis: UnixListener,
     core_tx: mpsc::Sender<CoreMsg>,
+    policy: PeerPolicy,
+}
+
+/// What every connection is held to.
+#[derive(Clone)]
+struct PeerPolicy {
+    max_frame: u32,
+    /// The server's own uid; it and root may always connect.
+    owner: u32,
+    /// Other uids that may connect.
+    allow_uids: Arc<[u32]>,
+}
+
+impl PeerPolicy {
+    fn admits(&self, cred: &PeerCred) -> bool {
+        cred.uid == self.owner || cred.uid == 0 || self.allow_uids.contains(&cred.uid)
+    }
 }
 
 pub enum CoreMsg {
-    RegisterPeer { ev_tx: mpsc::Sender<Event>, reply: oneshot::Sender<PeerId> },
+    RegisterPeer { ev_tx: mpsc::Sender<Event>, cred: Option<PeerCred>, reply: oneshot::Sender<PeerId> },
     FromPeer { peer: PeerId, req: Request, reply: oneshot::Sender<Response> },
     UnregisterPeer { peer: PeerId },
 }
 
 impl IpcServer {
+    /// Bind `path` readable and writable by its owner only; the caller keeps the directory private.
     pub async fn bind(path: &str, core_tx: mpsc::Sender<CoreMsg>) -> Result<Self> {
-        // tip: unlink stale socket and set 0700 elsewhere
-        Ok(Self { lis: UnixListener::bind(path)?, core_tx })
+        Self::bind_mode(path, 0o600, core_tx).await
+    }
+
+    /// Bind `path` with permissions `mode`, which it has from the start: the umask is narrowed
+    /// around the bind instead of the socket being chmodded after it.
+    pub async fn bind_mode(path: &str, mode: u32, core_tx: mpsc::Sender<CoreMsg>) -> Result<Self> {
+        // tip: unlink stale socket elsewhere
+        let old = umask(Mode::from_raw_mode(!mode & 0o777));
+        let lis = UnixListener::bind(path);
+        umask(old);
+        let lis = lis?;
+        let policy =
+            PeerPolicy { max_frame: MAX_FRAME, owner: rustix::process::getuid().as_raw(), allow_uids: Arc::new([]) };
+        Ok(Self { lis, core_tx, policy })
+    }
+
+    /// Largest frame payload accepted from a peer; a peer sending more is told why and dropped.
+    pub fn with_max_frame(mut self, max_frame: u32) -> Self {
+        self.policy.max_frame = max_frame;
+        self
+    }
+
+    /// Let these uids connect besides the server's own and root; anyone else is denied.
+    pub fn with_allowed_uids(mut self, uids: &[u32]) -> Self {
+        self.policy.allow_uids = uids.into();
+        self
     }
 
     pub async fn run(&self) -> Result<()> {
         loop {
             let (sock, _addr) = self.lis.accept().await?;
             let tx = self.core_tx.clone();
+            let policy = self.policy.clone();
             tokio::spawn(async move {
-                let _ = handle_peer(sock, tx).await;
+                let _ = handle_peer(sock, tx, policy).await;
             });
         }
     }
 }
 
+/// How long a departing peer's queued frames may take to go out.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long a new peer has to send its Hello.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest Hello accepted; it is a handful of bytes.
+const HELLO_MAX: u32 = 1024;
+
 struct Outbound {
     hdr: FrameHeader,
     bytes: Vec<u8>,
 }
 
-async fn handle_peer(sock: UnixStream, core_tx: mpsc::Sender<CoreMsg>) -> Result<()> {
+/// Queue an [`Event::Bye`] telling the peer why it is being dropped.
+async fn bye(write_tx: &mpsc::Sender<Outbound>, reason: String) {
+    rustlog::warn!("dropping peer: {reason}");
+    let (hdr, bytes) = frame(Kind::Event, 2, to_vec(&Event::Bye { reason }).unwrap(), false);
+    let _ = write_tx.send(Outbound { hdr, bytes }).await;
+}
+
+/// Tell a peer that may not connect why, without reading anything it sent: what arrives until it
+/// hangs up is discarded, so its Hello doesn't turn the answer into a reset.
+async fn refuse(mut sock: UnixStream, msg: String) {
+    let denied = Response::Err { code: ErrorCode::Denied, msg };
+    let (hdr, bytes) = frame(Kind::Response, 1, to_vec(&denied).unwrap(), false);
+    let _ = tokio::time::timeout(FLUSH_TIMEOUT, async {
+        write_payload(&mut sock, hdr, &bytes).await?;
+        sock.shutdown().await?;
+        let mut sink = [0u8; 512];
+        while sock.read(&mut sink).await? > 0 {}
+        Ok::<(), std::io::Error>(())
+    })
+    .await;
+}
+
+/// Let the writer send what is queued, unless the peer stopped reading.
+async fn flush(writer: JoinHandle<std::io::Result<()>>) {
+    let abort = writer.abort_handle();
+    if tokio::time::timeout(FLUSH_TIMEOUT, writer).await.is_err() {
+        abort.abort();
+    }
+}
+
+async fn handle_peer(sock: UnixStream, core_tx: mpsc::Sender<CoreMsg>, policy: PeerPolicy) -> Result<()> {
+    let max_frame = policy.max_frame;
+    let cred = sock.peer_cred().ok().map(|c| PeerCred { uid: c.uid(), gid: c.gid(), pid: c.pid() });
+    // Creds are checked before anything is read, so a refused peer costs no more than the answer
+    if !cred.as_ref().is_some_and(|c| policy.admits(c)) {
+        let msg = match cred {
+            Some(c) => format!("uid {} may not connect", c.uid),
+            None => "peer credentials unavailable".into(),
+        };
+        rustlog::warn!("refusing peer: {msg}");
+        refuse(sock, msg.clone()).await;
+        return Err(Error::Ipc(msg));
+    }
     let (mut r, w) = sock.into_split();
 
     // Dedicated writer task owns the write half
@@ -55,69 +153,90 @@ async fn handle_peer(sock: UnixStream, core_tx: mpsc::Sender<CoreMsg>) -> Result
     });
 
     // Handshake (read first frame, reply via writer)
-    let (hdr0, bytes0) = read_payload(&mut r).await?;
-    if hdr0.schema_id != 0 {
-        return Err(Error::InvalidState("expected Hello".into()));
-    }
-    let hello: Hello = from_slice(&bytes0).map_err(|e| Error::Ipc(e.to_string()))?;
-
-    let ack = HelloAck { server_api_major: 1, features: 0 };
-    let ack_bytes = to_vec(&ack).unwrap();
-    let ack_hdr = FrameHeader { api_major: 1, kind: Kind::Response, schema_id: 0, len: ack_bytes.len() as u32 };
-    let _ = write_tx.send(Outbound { hdr: ack_hdr, bytes: ack_bytes }).await;
-    if hello.client_api_major != 1 {
-        return Ok(());
+    let hello = match tokio::time::timeout(HANDSHAKE_TIMEOUT, read_payload(&mut r, HELLO_MAX)).await {
+        Ok(Ok((hdr, bytes))) if hdr.schema_id == 0 && matches!(hdr.kind, Kind::Request) => {
+            from_slice::<Hello>(&bytes).map_err(|e| format!("malformed Hello: {e}"))
+        }
+        Ok(Ok(_)) => Err("expected Hello".to_string()),
+        Ok(Err(e)) if e.is_disconnect() => return Err(e.into()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("no Hello in time".to_string()),
     }
+    .and_then(|hello| match hello.client_api_major {
+        API_MAJOR => Ok(hello),
+        v => Err(FrameError::ApiMajor(v).to_string()),
+    });
+    let hello = match hello {
+        Ok(hello) => hello,
+        Err(reason) => {
+            bye(&write_tx, reason.clone()).await;
+            drop(write_tx);
+            flush(writer).await;
+            return Err(Error::Ipc(reason));
+        }
+    };
+
+    let features = hello.features & FEATURE_LZ4;
+    let compress = features & FEATURE_LZ4 != 0;
+    let ack = HelloAck { server_api_major: API_MAJOR, features };
+    let (hdr, bytes) = frame(Kind::Response, 0, to_vec(&ack).unwrap(), false);
+    let _ = write_tx.send(Outbound { hdr, bytes }).await;
 
     // Register peer with Core — Core allocates PeerId
     let (ev_tx, mut ev_rx) = mpsc::channel::<Event>(256);
     let (id_tx, id_rx) = oneshot::channel();
-    core_tx.send(CoreMsg::RegisterPeer { ev_tx: ev_tx.clone(), reply: id_tx }).await.ok();
+    core_tx.send(CoreMsg::RegisterPeer { ev_tx, cred, reply: id_tx }).await.ok();
     let peer = id_rx.await.map_err(|_| Error::InvalidState("peer id alloc failed".into()))?;
 
     // Event forwarder → writer
     let write_tx_events = write_tx.clone();
     let ev_forward = tokio::spawn(async move {
         while let Some(ev) = ev_rx.recv().await {
-            let bytes = rmp_serde::to_vec(&ev).unwrap();
-            let hdr = FrameHeader { api_major: 1, kind: Kind::Event, schema_id: 2, len: bytes.len() as u32 };
+            let (hdr, bytes) = frame(Kind::Event, 2, rmp_serde::to_vec(&ev).unwrap(), compress);
             if write_tx_events.send(Outbound { hdr, bytes }).await.is_err() {
                 break;
             }
         }
-        Ok::<(), ()>(())
     });
 
-    // Reader: route requests → core; core response → writer
+    // Reader: route requests → core; core response → writer. Ends with why the peer must go, if
+    // it misbehaved rather than hung up.
     let tx_core = core_tx.clone();
     let write_tx_resp = write_tx.clone();
     let reader = tokio::spawn(async move {
         loop {
-            let (hdr, bytes) =
-                read_payload(&mut r).await.map_err(|e| std::io::Error::new(std::io::ErrorKind::BrokenPipe, e))?;
+            let (hdr, bytes) = match read_payload(&mut r, max_frame).await {
+                Ok(frame) => frame,
+                Err(e) if e.is_disconnect() => return None,
+                Err(e) => return Some(e.to_string()),
+            };
             if hdr.schema_id != 1 || !matches!(hdr.kind, Kind::Request) {
                 continue;
             }
-            let req: Request =
-                rmp_serde::from_slice(&bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            let req: Request = match rmp_serde::from_slice(&bytes) {
+                Ok(req) => req,
+                Err(e) => return Some(format!("malformed request: {e}")),
+            };
             let (reply_tx, reply_rx) = oneshot::channel();
-            tx_core
-                .send(CoreMsg::FromPeer { peer, req, reply: reply_tx })
-                .await
-                .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "core gone"))?;
+            if tx_core.send(CoreMsg::FromPeer { peer, req, reply: reply_tx }).await.is_err() {
+                return Some("server shutting down".into());
+            }
             if let Ok(resp) = reply_rx.await {
-                let bytes = rmp_serde::to_vec(&resp).unwrap();
-                let hdr = FrameHeader { api_major: 1, kind: Kind::Response, schema_id: 1, len: bytes.len() as u32 };
+                let (hdr, bytes) = frame(Kind::Response, 1, rmp_serde::to_vec(&resp).unwrap(), compress);
                 if write_tx_resp.send(Outbound { hdr, bytes }).await.is_err() {
-                    break;
+                    return None;
                 }
             }
         }
-        Ok::<(), std::io::Error>(())
     });
 
-    // Wait for tasks (ignore exact errors for now)
-    let _ = tokio::join!(reader, ev_forward, writer);
+    // Once the reader is done, say goodbye if there is a reason to, and let the writer flush
+    if let Ok(Some(reason)) = reader.await {
+        bye(&write_tx, reason).await;
+    }
+    ev_forward.abort();
+    drop(write_tx);
+    flush(writer).await;
     core_tx.send(CoreMsg::UnregisterPeer { peer }).await.ok();
     Ok(())
 }
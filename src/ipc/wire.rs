@@ -1,5 +1,10 @@
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
+/// Protocol major version carried in every frame header and in the Hello exchange.
+pub const API_MAJOR: u8 = 1;
+/// Default limit on a frame's payload, compressed or expanded.
+pub const MAX_FRAME: u32 = 16 << 20;
+
 #[repr(u8)]
 #[derive(Debug, Clone, Copy)]
 pub enum Kind {
@@ -14,37 +19,104 @@ impl From<Kind> for u8 {
     }
 }
 
+/// Payloads smaller than this are sent as they are: compressing them buys little and costs latency.
+pub const COMPRESS_MIN: usize = 2048;
+
+/// Why a frame could not be read.
+#[derive(Debug)]
+pub enum FrameError {
+    Io(std::io::Error),
+    /// The payload, or what a compressed payload expands to, is over the limit.
+    TooLarge {
+        len: u64,
+        max: u32,
+    },
+    UnknownKind(u8),
+    ApiMajor(u8),
+    /// A compressed payload that does not decompress.
+    Corrupt(String),
+}
+
+impl FrameError {
+    /// The peer went away rather than misbehaving.
+    pub fn is_disconnect(&self) -> bool {
+        matches!(self, Self::Io(_))
+    }
+}
+
+impl std::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::TooLarge { len, max } => write!(f, "frame of {len} bytes exceeds the {max} byte limit"),
+            Self::UnknownKind(k) => write!(f, "unknown frame kind {k}"),
+            Self::ApiMajor(v) => write!(f, "unsupported API major version {v} (expected {API_MAJOR})"),
+            Self::Corrupt(e) => write!(f, "corrupt compressed frame: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<std::io::Error> for FrameError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
 pub struct FrameHeader {
     pub api_major: u8,
     pub kind: Kind,
+    /// The payload is lz4-compressed; only set once both ends agreed on [`FEATURE_LZ4`].
+    ///
+    /// [`FEATURE_LZ4`]: super::proto::FEATURE_LZ4
+    pub compressed: bool,
     pub schema_id: u32,
     pub len: u32,
 }
 
 impl FrameHeader {
     pub const SIZE: usize = 1 + 1 + 4 + 4;
+    /// Flag bit of the kind byte marking a compressed payload.
+    const COMPRESSED: u8 = 0x80;
+
     pub fn to_bytes(&self) -> [u8; Self::SIZE] {
         let mut b = [0u8; Self::SIZE];
         b[0] = self.api_major;
-        b[1] = self.kind.into();
+        b[1] = u8::from(self.kind) | if self.compressed { Self::COMPRESSED } else { 0 };
         b[2..6].copy_from_slice(&self.schema_id.to_be_bytes());
         b[6..10].copy_from_slice(&self.len.to_be_bytes());
         b
     }
-    pub fn from_bytes(b: [u8; 10]) -> Self {
-        Self {
+    pub fn from_bytes(b: [u8; 10]) -> Result<Self, FrameError> {
+        if b[0] != API_MAJOR {
+            return Err(FrameError::ApiMajor(b[0]));
+        }
+        Ok(Self {
             api_major: b[0],
-            kind: match b[1] {
+            kind: match b[1] & !Self::COMPRESSED {
                 0 => Kind::Request,
                 1 => Kind::Response,
-                _ => Kind::Event,
+                2 => Kind::Event,
+                k => return Err(FrameError::UnknownKind(k)),
             },
+            compressed: b[1] & Self::COMPRESSED != 0,
             schema_id: u32::from_be_bytes(b[2..6].try_into().unwrap()),
             len: u32::from_be_bytes(b[6..10].try_into().unwrap()),
-        }
+        })
     }
 }
 
+/// Frame an encoded message, compressing it if `compress` was negotiated and it is large enough.
+pub fn frame(kind: Kind, schema_id: u32, bytes: Vec<u8>, compress: bool) -> (FrameHeader, Vec<u8>) {
+    let packed = (compress && bytes.len() >= COMPRESS_MIN)
+        .then(|| lz4_flex::compress_prepend_size(&bytes))
+        .filter(|packed| packed.len() < bytes.len());
+    let compressed = packed.is_some();
+    let bytes = packed.unwrap_or(bytes);
+    (FrameHeader { api_major: API_MAJOR, kind, compressed, schema_id, len: bytes.len() as u32 }, bytes)
+}
+
 pub async fn write_payload<W: AsyncWrite + Unpin>(mut w: W, hdr: FrameHeader, buf: &[u8]) -> std::io::Result<()> {
     debug_assert_eq!(hdr.len as usize, buf.len());
     w.write_all(&hdr.to_bytes()).await?;
@@ -52,11 +124,25 @@ pub async fn write_payload<W: AsyncWrite + Unpin>(mut w: W, hdr: FrameHeader, bu
     w.flush().await
 }
 
-pub async fn read_payload<R: AsyncRead + Unpin>(mut r: R) -> std::io::Result<(FrameHeader, Vec<u8>)> {
+/// Read one frame whose payload is at most `max` bytes, both as sent and once expanded; the
+/// length is checked before anything is allocated.
+pub async fn read_payload<R: AsyncRead + Unpin>(mut r: R, max: u32) -> Result<(FrameHeader, Vec<u8>), FrameError> {
     let mut hdr = [0u8; FrameHeader::SIZE];
     r.read_exact(&mut hdr).await?;
-    let hdr = FrameHeader::from_bytes(hdr);
+    let hdr = FrameHeader::from_bytes(hdr)?;
+    if hdr.len > max {
+        return Err(FrameError::TooLarge { len: hdr.len.into(), max });
+    }
     let mut buf = vec![0u8; hdr.len as usize];
     r.read_exact(&mut buf).await?;
-    Ok((hdr, buf))
+    if !hdr.compressed {
+        return Ok((hdr, buf));
+    }
+    let corrupt = |e: lz4_flex::block::DecompressError| FrameError::Corrupt(e.to_string());
+    let (size, _) = lz4_flex::block::uncompressed_size(&buf).map_err(corrupt)?;
+    if size > max as usize {
+        return Err(FrameError::TooLarge { len: size as u64, max });
+    }
+    let buf = lz4_flex::decompress_size_prepended(&buf).map_err(corrupt)?;
+    Ok((FrameHeader { compressed: false, len: buf.len() as u32, ..hdr }, buf))
 }
@@ -6,6 +6,7 @@ pub mod runtime;
 pub mod ipc;
 pub mod server;
 pub mod pty;
+pub mod term;
 pub mod client;
 pub mod lua;
 pub mod agent;
@@ -0,0 +1,138 @@
+use crate::Result;
+use crate::client::{
+    copy::ModeKeys,
+    keys::{Action, Binding, Key, KeyEngine},
+};
+use mlua::{Lua, LuaSerdeExt, Table, Value};
+use std::path::Path;
+
+/// Key table edits requested from Lua, applied to the engine after each chunk runs.
+#[derive(Debug)]
+pub enum KeyOp {
+    Bind { table: String, key: Key, binding: Binding },
+    Unbind { table: String, key: Key },
+    Prefix(Key),
+    ModeKeys(ModeKeys),
+}
+
+#[derive(Default)]
+struct Pending {
+    ops: Vec<KeyOp>,
+    callbacks: usize,
+}
+
+/// Client-side Lua runtime exposing the `splicer` global.
+pub struct LuaRuntime {
+    lua: Lua,
+}
+
+impl LuaRuntime {
+    pub fn new() -> Result<Self> {
+        let lua = Lua::new();
+        lua.set_app_data(Pending::default());
+
+        let splicer = lua.create_table()?;
+        splicer.set("_callbacks", lua.create_table()?)?;
+
+        // splicer.bind(table, key, action[, { repeat = bool }])
+        // action: "detach" | "send-prefix" | "switch-table NAME" | "lua CHUNK" | function | serde table
+        let bind = lua.create_function(|lua, (table, key, action, opts): (String, String, Value, Option<Table>)| {
+            let key: Key = key.parse().map_err(mlua::Error::external)?;
+            let action = lua_action(lua, action)?;
+            let repeat = opts.map(|o| o.get::<Option<bool>>("repeat")).transpose()?.flatten().unwrap_or(false);
+            push_op(lua, KeyOp::Bind { table, key, binding: Binding { action, repeat } });
+            Ok(())
+        })?;
+        splicer.set("bind", bind)?;
+
+        let unbind = lua.create_function(|lua, (table, key): (String, String)| {
+            let key: Key = key.parse().map_err(mlua::Error::external)?;
+            push_op(lua, KeyOp::Unbind { table, key });
+            Ok(())
+        })?;
+        splicer.set("unbind", unbind)?;
+
+        let prefix = lua.create_function(|lua, key: String| {
+            let key: Key = key.parse().map_err(mlua::Error::external)?;
+            push_op(lua, KeyOp::Prefix(key));
+            Ok(())
+        })?;
+        splicer.set("prefix", prefix)?;
+
+        // splicer.mode_keys("vi" | "emacs"): which copy-mode table prefix-[ enters
+        let mode_keys = lua.create_function(|lua, keys: String| {
+            let keys: ModeKeys = keys.parse().map_err(mlua::Error::external)?;
+            push_op(lua, KeyOp::ModeKeys(keys));
+            Ok(())
+        })?;
+        splicer.set("mode_keys", mode_keys)?;
+
+        lua.globals().set("splicer", splicer)?;
+        Ok(Self { lua })
+    }
+
+    pub fn exec(&self, chunk: &str) -> Result {
+        self.lua.load(chunk).exec()?;
+        Ok(())
+    }
+
+    pub fn exec_file(&self, path: &Path) -> Result {
+        let src = std::fs::read_to_string(path)?;
+        self.lua.load(src).set_name(path.to_string_lossy()).exec()?;
+        Ok(())
+    }
+
+    /// Publish server state for callbacks: `splicer.panes` is the `GetState` pane list (with
+    /// `pid`, `current_command`, `current_path`) and `splicer.pane` the attached pane's ID.
+    pub fn set_panes(&self, current: &str, panes: &serde_json::Value) -> Result {
+        let splicer: Table = self.lua.globals().get("splicer")?;
+        let opts = mlua::SerializeOptions::new().serialize_none_to_null(false);
+        splicer.set("panes", self.lua.to_value_with(panes, opts)?)?;
+        splicer.set("pane", current)?;
+        Ok(())
+    }
+
+    /// Drain key edits queued by `splicer.bind` and friends.
+    pub fn take_key_ops(&self) -> Vec<KeyOp> {
+        self.lua.app_data_mut::<Pending>().map(|mut p| std::mem::take(&mut p.ops)).unwrap_or_default()
+    }
+
+    /// Apply queued key edits to `engine`.
+    pub fn apply_keys(&self, engine: &mut KeyEngine) {
+        for op in self.take_key_ops() {
+            match op {
+                KeyOp::Bind { table, key, binding } => engine.tables_mut().bind(&table, key, binding),
+                KeyOp::Unbind { table, key } => {
+                    engine.tables_mut().unbind(&table, key);
+                }
+                KeyOp::Prefix(key) => engine.set_prefix(key),
+                KeyOp::ModeKeys(keys) => engine.set_mode_keys(keys),
+            }
+        }
+    }
+}
+
+fn push_op(lua: &Lua, op: KeyOp) {
+    if let Some(mut p) = lua.app_data_mut::<Pending>() {
+        p.ops.push(op);
+    }
+}
+
+fn lua_action(lua: &Lua, v: Value) -> mlua::Result<Action> {
+    match v {
+        Value::String(s) => s.to_str()?.parse().map_err(mlua::Error::external),
+        Value::Function(f) => {
+            // keep the function in the registry table; the action just calls it back
+            let n = {
+                let mut p = lua.app_data_mut::<Pending>().ok_or_else(|| mlua::Error::runtime("lua state gone"))?;
+                p.callbacks += 1;
+                p.callbacks
+            };
+            let cbs: Table = lua.globals().get::<Table>("splicer")?.get("_callbacks")?;
+            cbs.set(n, f)?;
+            Ok(Action::Lua(format!("splicer._callbacks[{n}]()")))
+        }
+        v @ Value::Table(_) => lua.from_value(v),
+        _ => Err(mlua::Error::runtime("action must be a string, function or table")),
+    }
+}
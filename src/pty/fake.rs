@@ -0,0 +1,125 @@
+use super::fanout::Fanout;
+use super::*;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+
+/// Scripted in-memory backend for tests: output is injected, input, resizes and signals are
+/// recorded, and the exit happens on demand. SIGKILL ends it, as it would a real process.
+pub struct FakePty {
+    script: Arc<Script>,
+}
+
+/// The test's side of a [`FakePty`].
+#[derive(Clone)]
+pub struct FakeControl {
+    script: Arc<Script>,
+}
+
+struct Script {
+    out: Fanout,
+    input: Mutex<Vec<u8>>,
+    resizes: Mutex<Vec<(u16, u16)>>,
+    signals: Mutex<Vec<(Sig, SigTarget)>>,
+    /// Input is refused as if the program's queue were full.
+    stalled: AtomicBool,
+    exit_tx: watch::Sender<Option<ExitStatus>>,
+}
+
+impl FakePty {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> (PtyHandle, FakeControl) {
+        let (exit_tx, _) = watch::channel(None);
+        let script = Arc::new(Script {
+            out: Fanout::default(),
+            input: Mutex::default(),
+            resizes: Mutex::default(),
+            signals: Mutex::default(),
+            stalled: AtomicBool::new(false),
+            exit_tx,
+        });
+        (PtyHandle::new(FakePty { script: script.clone() }), FakeControl { script })
+    }
+}
+
+impl FakeControl {
+    /// Emit bytes as if the program had written them; waits while a flow-controlled tap is full.
+    pub async fn output(&self, bytes: &[u8]) {
+        self.script.out.send(Arc::from(bytes)).await;
+    }
+    /// Everything written to the program since the last call.
+    pub fn take_input(&self) -> Vec<u8> {
+        self.script.input.lock().map(|mut v| std::mem::take(&mut *v)).unwrap_or_default()
+    }
+    /// Stop (or resume) reading input, as a stopped program would; writes then find the queue full.
+    pub fn stall_input(&self, stalled: bool) {
+        self.script.stalled.store(stalled, Ordering::Relaxed);
+    }
+    /// Every resize so far, oldest first.
+    pub fn resizes(&self) -> Vec<(u16, u16)> {
+        self.script.resizes.lock().map(|v| v.clone()).unwrap_or_default()
+    }
+    /// Every signal delivered so far, oldest first.
+    pub fn signals(&self) -> Vec<(Sig, SigTarget)> {
+        self.script.signals.lock().map(|v| v.clone()).unwrap_or_default()
+    }
+    /// End the program: output reaches EOF and the exit watch fires.
+    pub fn exit(&self, status: ExitStatus) {
+        self.script.exit(status);
+    }
+}
+
+impl Script {
+    fn exited(&self) -> bool {
+        self.exit_tx.borrow().is_some()
+    }
+
+    fn exit(&self, status: ExitStatus) {
+        self.out.close();
+        self.exit_tx.send_replace(Some(status));
+    }
+}
+
+impl PtyBackend for FakePty {
+    fn write<'a>(&'a self, bytes: &'a [u8]) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move { self.try_write(bytes) })
+    }
+
+    fn try_write(&self, bytes: &[u8]) -> Result<usize> {
+        if self.script.exited() {
+            return Err(PtyError::Io(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pty stdin closed")));
+        }
+        if self.script.stalled.load(Ordering::Relaxed) {
+            return Err(PtyError::InputFull);
+        }
+        if let Ok(mut input) = self.script.input.lock() {
+            input.extend_from_slice(bytes);
+        }
+        Ok(bytes.len())
+    }
+
+    fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        self.script.resizes.lock().map_err(|_| PtyError::ResizeFailed)?.push((cols, rows));
+        Ok(())
+    }
+
+    fn subscribe(&self, backpressure: Backpressure) -> OutputRx {
+        self.script.out.subscribe(backpressure)
+    }
+
+    fn signal(&self, sig: Sig, target: SigTarget) -> Result<()> {
+        if self.script.exited() {
+            return Err(PtyError::InvalidArgs("process has exited"));
+        }
+        if let Ok(mut signals) = self.script.signals.lock() {
+            signals.push((sig, target));
+        }
+        if sig == Sig::Kill {
+            self.script.exit(ExitStatus { code: 1, signal: Some(sig.name().to_string()) });
+        }
+        Ok(())
+    }
+
+    fn exit_watch(&self) -> watch::Receiver<Option<ExitStatus>> {
+        self.script.exit_tx.subscribe()
+    }
+}
@@ -0,0 +1,73 @@
+use super::{Backpressure, ByteChunk, OutputRx};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Per-subscriber output queue depth.
+const TAP_DEPTH: usize = 512;
+
+/// Output taps of one process. A tap is only removed once its receiver is gone: a full lossy
+/// tap loses the chunk and counts it, a full flow-controlled tap makes the reader wait.
+#[derive(Default)]
+pub(super) struct Fanout {
+    taps: Mutex<Vec<Tap>>,
+}
+
+struct Tap {
+    tx: mpsc::Sender<ByteChunk>,
+    dropped: Arc<AtomicU64>,
+    backpressure: Backpressure,
+}
+
+impl Fanout {
+    pub(super) fn subscribe(&self, backpressure: Backpressure) -> OutputRx {
+        let (tx, rx) = mpsc::channel(TAP_DEPTH);
+        let dropped = Arc::new(AtomicU64::new(0));
+        if let Ok(mut taps) = self.taps.lock() {
+            taps.push(Tap { tx, dropped: dropped.clone(), backpressure });
+        }
+        OutputRx { rx, dropped }
+    }
+
+    /// Deliver a chunk from async code; resolves once every flow-controlled tap took it.
+    pub(super) async fn send(&self, chunk: ByteChunk) {
+        for tx in self.offer(&chunk) {
+            let _ = tx.send(chunk.clone()).await;
+        }
+    }
+
+    /// [`send`](Self::send) for a reader thread outside the runtime.
+    pub(super) fn blocking_send(&self, chunk: ByteChunk) {
+        for tx in self.offer(&chunk) {
+            let _ = tx.blocking_send(chunk.clone());
+        }
+    }
+
+    /// Hand `chunk` to every tap with room; returns the full flow-controlled ones to wait on.
+    fn offer(&self, chunk: &ByteChunk) -> Vec<mpsc::Sender<ByteChunk>> {
+        let mut blocked = Vec::new();
+        if let Ok(mut taps) = self.taps.lock() {
+            taps.retain(|tap| match tap.tx.try_send(chunk.clone()) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    match tap.backpressure {
+                        Backpressure::Drop => {
+                            tap.dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Backpressure::Block => blocked.push(tap.tx.clone()),
+                    }
+                    true
+                }
+            });
+        }
+        blocked
+    }
+
+    /// End every tap, as when the program's output reaches EOF.
+    pub(super) fn close(&self) {
+        if let Ok(mut taps) = self.taps.lock() {
+            taps.clear();
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use std::{ffi::OsString, path::PathBuf, sync::Arc};
+use rustix::process::{Pid, Signal, kill_process_group};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{ffi::OsString, future::Future, path::PathBuf, pin::Pin, sync::Arc, time::Duration};
 use tokio::sync::{mpsc, watch};
 
 pub type ByteChunk = Arc<[u8]>;
@@ -20,14 +22,95 @@ pub enum Program {
     Argv { argv: Vec<OsString> },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum Sig {
     Term,
     Kill,
     Int,
     Hup,
+    Quit,
+    Stop,
+    Cont,
+    Winch,
+    Usr1,
+    Usr2,
 }
 
+impl Sig {
+    pub const ALL: [Sig; 10] =
+        [Sig::Term, Sig::Kill, Sig::Int, Sig::Hup, Sig::Quit, Sig::Stop, Sig::Cont, Sig::Winch, Sig::Usr1, Sig::Usr2];
+
+    /// Name without the `SIG` prefix, as `kill -l` prints it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Sig::Term => "TERM",
+            Sig::Kill => "KILL",
+            Sig::Int => "INT",
+            Sig::Hup => "HUP",
+            Sig::Quit => "QUIT",
+            Sig::Stop => "STOP",
+            Sig::Cont => "CONT",
+            Sig::Winch => "WINCH",
+            Sig::Usr1 => "USR1",
+            Sig::Usr2 => "USR2",
+        }
+    }
+}
+
+impl std::fmt::Display for Sig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for Sig {
+    type Err = PtyError;
+    /// Accepts `TERM`, `SIGTERM` or `term`.
+    fn from_str(s: &str) -> Result<Self> {
+        let up = s.to_ascii_uppercase();
+        let name = up.strip_prefix("SIG").unwrap_or(&up);
+        Sig::ALL.into_iter().find(|sig| sig.name() == name).ok_or(PtyError::InvalidArgs("unknown signal"))
+    }
+}
+
+/// Which process group a signal is delivered to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SigTarget {
+    /// The spawned child's own process group (it is a session leader).
+    Group,
+    /// The terminal's foreground group (`tcgetpgrp`), e.g. the job running under a shell.
+    Foreground,
+}
+
+/// Step of a staged shutdown that ended the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum KillStage {
+    /// Had already exited; nothing was sent.
+    Exited,
+    Hup,
+    Term,
+    Kill,
+    /// Still running after SIGKILL (e.g. stuck in uninterruptible sleep).
+    Survived,
+}
+
+impl std::fmt::Display for KillStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KillStage::Exited => "exited",
+            KillStage::Hup => "hup",
+            KillStage::Term => "term",
+            KillStage::Kill => "kill",
+            KillStage::Survived => "survived",
+        })
+    }
+}
+
+/// How long a process gets to react to SIGHUP before SIGTERM follows.
+const HUP_SETTLE: Duration = Duration::from_millis(100);
+/// How long to wait for the exit to be observed after SIGKILL.
+const KILL_WAIT: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
 pub enum PtyError {
     InvalidArgs(&'static str),
@@ -37,6 +120,8 @@ pub enum PtyError {
     ResizeFailed,
     WaitFailed,
     Unsupported,
+    /// The program's input queue is full: it isn't reading.
+    InputFull,
 }
 impl From<std::io::Error> for PtyError {
     fn from(e: std::io::Error) -> Self {
@@ -46,46 +131,252 @@ impl From<std::io::Error> for PtyError {
 
 pub type Result<T> = std::result::Result<T, PtyError>;
 
+/// What a tap does when its reader falls a full queue behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum Backpressure {
+    /// Lose the chunk and count it (see [`OutputRx::take_dropped`]); the program never waits.
+    #[default]
+    Drop,
+    /// Stop reading the program's output until there is room again.
+    Block,
+}
+
+impl std::fmt::Display for Backpressure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Backpressure::Drop => "drop",
+            Backpressure::Block => "block",
+        })
+    }
+}
+
+impl std::str::FromStr for Backpressure {
+    type Err = PtyError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "drop" => Ok(Backpressure::Drop),
+            "block" => Ok(Backpressure::Block),
+            _ => Err(PtyError::InvalidArgs("backpressure must be drop or block")),
+        }
+    }
+}
+
 /// Receiver of output chunks (fan‑out tap).
-pub type OutputRx = mpsc::Receiver<ByteChunk>;
+pub struct OutputRx {
+    rx: mpsc::Receiver<ByteChunk>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl OutputRx {
+    /// Next chunk, or `None` once the output reached EOF.
+    pub async fn recv(&mut self) -> Option<ByteChunk> {
+        self.rx.recv().await
+    }
+    /// A chunk that is already queued, without waiting.
+    pub fn try_recv(&mut self) -> Option<ByteChunk> {
+        self.rx.try_recv().ok()
+    }
+    /// Chunks lost to a full queue since the last call; always 0 for [`Backpressure::Block`].
+    pub fn take_dropped(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+}
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
-/// Handle to a running PTY‑backed process.
+/// One process on a terminal, as driven through a [`PtyHandle`].
+pub trait PtyBackend: Send + Sync {
+    /// Queue bytes for the program's input.
+    fn write<'a>(&'a self, bytes: &'a [u8]) -> BoxFuture<'a, Result<usize>>;
+    /// Queue bytes without waiting; fails with [`PtyError::InputFull`] rather than wait for room.
+    fn try_write(&self, bytes: &[u8]) -> Result<usize>;
+    fn resize(&self, cols: u16, rows: u16) -> Result<()>;
+    /// Resize, also reporting the cell area in pixels; backends that can't carry it just resize.
+    fn resize_pixels(&self, cols: u16, rows: u16, width: u16, height: u16) -> Result<()> {
+        let _ = (width, height);
+        self.resize(cols, rows)
+    }
+    /// A new output tap; every tap sees every chunk from now on and ends at EOF.
+    fn subscribe(&self, backpressure: Backpressure) -> OutputRx;
+    fn signal(&self, sig: Sig, target: SigTarget) -> Result<()>;
+    /// First `Some` is the exit status.
+    fn exit_watch(&self) -> watch::Receiver<Option<ExitStatus>>;
+    /// PID of the spawned child, when there is a real one.
+    fn pid(&self) -> Option<u32> {
+        None
+    }
+    /// Leader of the terminal's foreground process group.
+    fn foreground_pid(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// Handle to a running PTY‑backed process. Clones share the same process.
+#[derive(Clone)]
 pub struct PtyHandle {
-    pub(crate) inner: Arc<portable::Inner>,
+    inner: Arc<dyn PtyBackend>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct ExitStatus {
     pub code: u32,              // Some(code) if exited normally
     pub signal: Option<String>, // Some(name) if signaled (Unix)
 }
 
-/// Spawn a PTY process and return its handle.
+impl ExitStatus {
+    /// Stands in for a status that could not be read; a failure, so on-failure policies still fire.
+    pub(crate) const UNKNOWN: Self = Self { code: 255, signal: None };
+
+    pub fn success(&self) -> bool {
+        self.code == 0 && self.signal.is_none()
+    }
+}
+
+impl std::fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.signal {
+            Some(ref sig) => write!(f, "signal {sig}"),
+            None => write!(f, "code {}", self.code),
+        }
+    }
+}
+
+/// Which implementation runs a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// rustix PTY on tokio's reactor: no threads per process.
+    #[cfg(target_os = "linux")]
+    #[default]
+    Native,
+    /// portable-pty, with a reader thread per process.
+    #[cfg_attr(not(target_os = "linux"), default)]
+    Portable,
+}
+
+/// Starts a pane's program in place of [`spawn`], e.g. on a [`fake::FakePty`] in tests.
+pub type Spawner = Arc<dyn Fn(Program, PtyConfig) -> Result<PtyHandle> + Send + Sync>;
+
+/// Spawn a PTY process on the default backend and return its handle.
 pub fn spawn(program: Program, cfg: PtyConfig) -> Result<PtyHandle> {
-    portable::spawn(program, cfg)
+    spawn_with(Backend::default(), program, cfg)
+}
+
+/// Spawn a PTY process on the given backend.
+pub fn spawn_with(backend: Backend, program: Program, cfg: PtyConfig) -> Result<PtyHandle> {
+    match backend {
+        #[cfg(target_os = "linux")]
+        Backend::Native => native::spawn(program, cfg),
+        Backend::Portable => portable::spawn(program, cfg),
+    }
+}
+
+/// Signal `pgrp`, or else the child's own group (it is a session leader, so its pid is the pgid).
+fn kill_group(pgrp: Option<Pid>, pid: u32, sig: Sig) -> Result<()> {
+    let pgrp = pgrp.or_else(|| Pid::from_raw(pid as i32)).ok_or(PtyError::InvalidArgs("bad pid"))?;
+    kill_process_group(pgrp, to_signal(sig)).map_err(|e| match e {
+        rustix::io::Errno::SRCH => PtyError::InvalidArgs("process has exited"),
+        e => PtyError::Io(e.into()),
+    })
+}
+
+fn to_signal(sig: Sig) -> Signal {
+    match sig {
+        Sig::Term => Signal::TERM,
+        Sig::Kill => Signal::KILL,
+        Sig::Int => Signal::INT,
+        Sig::Hup => Signal::HUP,
+        Sig::Quit => Signal::QUIT,
+        Sig::Stop => Signal::STOP,
+        Sig::Cont => Signal::CONT,
+        Sig::Winch => Signal::WINCH,
+        Sig::Usr1 => Signal::USR1,
+        Sig::Usr2 => Signal::USR2,
+    }
 }
 
 impl PtyHandle {
+    pub fn new(backend: impl PtyBackend + 'static) -> Self {
+        Self { inner: Arc::new(backend) }
+    }
+
     /// Non‑blocking write to the PTY. Buffers internally.
     pub async fn write(&self, bytes: &[u8]) -> Result<usize> {
-        portable::write(self, bytes).await
+        self.inner.write(bytes).await
+    }
+    /// Like [`write`](Self::write), but a program that stopped reading its input is an error
+    /// instead of a wait; for callers that must never block on one process.
+    pub fn try_write(&self, bytes: &[u8]) -> Result<usize> {
+        self.inner.try_write(bytes)
     }
     /// Request a resize; clamped by server‑level validation.
     pub fn resize(&self, cols: u16, rows: u16) -> Result<()> {
-        portable::resize(self, cols, rows)
+        self.inner.resize(cols, rows)
     }
-    /// Subscribe to output. Each call creates a new tap.
+    /// Resize and report the cell area in pixels (`TIOCSWINSZ` `ws_xpixel`/`ws_ypixel`).
+    pub fn resize_pixels(&self, cols: u16, rows: u16, width: u16, height: u16) -> Result<()> {
+        self.inner.resize_pixels(cols, rows, width, height)
+    }
+    /// Subscribe to output. Each call creates a new tap, which loses chunks rather than stall
+    /// the program when its reader falls behind.
     pub fn subscribe(&self) -> OutputRx {
-        portable::subscribe(self)
+        self.inner.subscribe(Backpressure::Drop)
+    }
+    /// Subscribe with an explicit slow-reader policy.
+    pub fn subscribe_with(&self, backpressure: Backpressure) -> OutputRx {
+        self.inner.subscribe(backpressure)
     }
-    /// Try to send a signal to the child process.
+    /// Send a signal to the child's process group.
     pub fn signal(&self, sig: Sig) -> Result<()> {
-        portable::signal(self, sig)
+        self.inner.signal(sig, SigTarget::Group)
+    }
+    /// Send a signal to the chosen process group.
+    pub fn signal_to(&self, sig: Sig, target: SigTarget) -> Result<()> {
+        self.inner.signal(sig, target)
+    }
+    /// Staged shutdown of the process group: HUP, then TERM with up to `grace` to exit, then KILL.
+    /// `force` goes straight to KILL. Resolves to the stage that ended the process.
+    pub async fn shutdown(&self, force: bool, grace: Duration) -> KillStage {
+        let stages: &[(Sig, KillStage, Duration)] = if force {
+            &[(Sig::Kill, KillStage::Kill, KILL_WAIT)]
+        } else {
+            &[
+                (Sig::Hup, KillStage::Hup, HUP_SETTLE),
+                (Sig::Term, KillStage::Term, grace),
+                (Sig::Kill, KillStage::Kill, KILL_WAIT),
+            ]
+        };
+        let mut exit = self.exit_watch();
+        let mut last = KillStage::Exited;
+        for &(sig, stage, wait) in stages {
+            if exit.borrow().is_some() {
+                return last;
+            }
+            // a failure here means the process is already gone; the exit watch will say so
+            let _ = self.signal(sig);
+            last = stage;
+            if tokio::time::timeout(wait, exit.wait_for(Option::is_some)).await.is_ok() {
+                return stage;
+            }
+        }
+        KillStage::Survived
+    }
+    /// Leader of the terminal's foreground process group (the job in front), if any.
+    pub fn foreground_pid(&self) -> Option<u32> {
+        self.inner.foreground_pid()
+    }
+    /// PID of the spawned child, if known.
+    pub fn pid(&self) -> Option<u32> {
+        self.inner.pid()
     }
     /// Watch for process exit. First Some(status) means the child has exited.
     pub fn exit_watch(&self) -> watch::Receiver<Option<ExitStatus>> {
-        portable::exit_watch(self)
+        self.inner.exit_watch()
     }
 }
 
+pub mod fake;
+mod fanout;
+#[cfg(target_os = "linux")]
+mod native;
 mod portable;
+pub mod procinfo;
@@ -0,0 +1,225 @@
+use super::fanout::Fanout;
+use super::*;
+use rustix::process::{ioctl_tiocsctty, setsid};
+use rustix::pty::{OpenptFlags, grantpt, ioctl_tiocgptpeer, openpt, unlockpt};
+use rustix::termios::{Winsize, tcgetpgrp, tcsetwinsize};
+use std::os::fd::{BorrowedFd, OwnedFd};
+use std::os::unix::process::ExitStatusExt;
+use std::process::Stdio;
+use tokio::io::unix::AsyncFd;
+use tokio::sync::{mpsc, watch};
+
+const READ_CHUNK: usize = 16 * 1024; // 16 KiB
+/// How long output already in the master may take to drain once the child is reaped.
+const DRAIN: Duration = Duration::from_millis(100);
+
+struct Native {
+    master: Arc<AsyncFd<OwnedFd>>, // non-blocking, shared with the reader/writer tasks
+    in_tx: mpsc::Sender<Vec<u8>>,  // buffered stdin pipeline
+    out: Arc<Fanout>,              // fan‑out taps
+    exit_tx: watch::Sender<Option<ExitStatus>>, // exit watcher
+    pid: u32,                      // child pid == its pgid (setsid)
+}
+
+pub(super) fn spawn(program: Program, cfg: PtyConfig) -> Result<PtyHandle> {
+    let flags = OpenptFlags::RDWR | OpenptFlags::NOCTTY | OpenptFlags::CLOEXEC;
+    let master = openpt(flags).map_err(|_| PtyError::OpenFailed)?;
+    grantpt(&master).map_err(|_| PtyError::OpenFailed)?;
+    unlockpt(&master).map_err(|_| PtyError::OpenFailed)?;
+    let slave = ioctl_tiocgptpeer(&master, flags).map_err(|_| PtyError::OpenFailed)?;
+    tcsetwinsize(&master, winsize(cfg.cols, cfg.rows, 0, 0)).map_err(|_| PtyError::ResizeFailed)?;
+
+    let mut cmd = match program {
+        Program::Shell => tokio::process::Command::new(std::env::var_os("SHELL").unwrap_or_else(|| "/bin/sh".into())),
+        Program::Argv { argv } => {
+            let mut it = argv.into_iter();
+            let Some(prog) = it.next() else {
+                return Err(PtyError::InvalidArgs("empty argv"));
+            };
+            let mut c = tokio::process::Command::new(prog);
+            c.args(it);
+            c
+        }
+    };
+    // like portable-pty: a missing or unusable cwd means $HOME
+    match cfg.cwd {
+        Some(ref cwd) if cwd.is_dir() => {
+            cmd.current_dir(cwd);
+        }
+        _ => {
+            if let Some(home) = std::env::var_os("HOME") {
+                cmd.current_dir(home);
+            }
+        }
+    }
+    cmd.envs(cfg.env.iter().map(|(k, v)| (k, v)));
+    // an explicit term wins over TERM in the environment, which wins over the default
+    match cfg.term {
+        Some(ref term) => {
+            cmd.env("TERM", term);
+        }
+        None if !cfg.env.iter().any(|(k, _)| k == "TERM") => {
+            cmd.env("TERM", "xterm-256color");
+        }
+        None => {}
+    }
+    cmd.stdin(Stdio::from(slave.try_clone()?)).stdout(Stdio::from(slave.try_clone()?)).stderr(Stdio::from(slave));
+    // SAFETY: only async-signal-safe calls (signal, sigprocmask, setsid, ioctl) between fork and exec.
+    unsafe {
+        cmd.pre_exec(|| {
+            // dispositions and the mask survive exec; start the program from a clean slate
+            for sig in [libc::SIGCHLD, libc::SIGHUP, libc::SIGINT, libc::SIGQUIT, libc::SIGTERM, libc::SIGALRM] {
+                libc::signal(sig, libc::SIG_DFL);
+            }
+            let empty: libc::sigset_t = std::mem::zeroed();
+            libc::sigprocmask(libc::SIG_SETMASK, &empty, std::ptr::null_mut());
+            setsid()?;
+            // stdin is the slave by now; make it the controlling terminal so SIGWINCH and job control work
+            ioctl_tiocsctty(BorrowedFd::borrow_raw(0))?;
+            Ok(())
+        });
+    }
+    let mut child = cmd.spawn().map_err(|_| PtyError::SpawnFailed)?;
+    // the Command holds our copies of the slave; without closing them the master never sees EOF
+    drop(cmd);
+    let pid = child.id().ok_or(PtyError::SpawnFailed)?;
+
+    rustix::io::ioctl_fionbio(&master, true).map_err(|e| PtyError::Io(e.into()))?;
+    let master = Arc::new(AsyncFd::new(master)?);
+    let out = Arc::new(Fanout::default());
+    let (exit_tx, _exit_rx) = watch::channel::<Option<ExitStatus>>(None);
+
+    let (in_tx, in_rx) = mpsc::channel::<Vec<u8>>(256);
+    tokio::spawn(write_loop(master.clone(), in_rx));
+    let reader = tokio::spawn(read_loop(master.clone(), out.clone()));
+    // tokio reaps through a pidfd where the kernel has them, SIGCHLD otherwise
+    let exit_tx_task = exit_tx.clone();
+    tokio::spawn(async move {
+        let status = match child.wait().await {
+            Ok(s) => exit_status(s),
+            Err(e) => {
+                rustlog::warn!("pid {pid}: wait failed: {e}");
+                ExitStatus::UNKNOWN
+            }
+        };
+        // let the tail of the output reach the taps before the exit is announced
+        let _ = tokio::time::timeout(DRAIN, reader).await;
+        exit_tx_task.send_replace(Some(status));
+    });
+
+    Ok(PtyHandle::new(Native { master, in_tx, out, exit_tx, pid }))
+}
+
+async fn read_loop(master: Arc<AsyncFd<OwnedFd>>, out: Arc<Fanout>) {
+    let mut buf = vec![0u8; READ_CHUNK];
+    loop {
+        let Ok(mut guard) = master.readable().await else {
+            break;
+        };
+        match guard.try_io(|fd| rustix::io::read(fd.get_ref(), &mut buf).map_err(Into::into)) {
+            Ok(Ok(0)) => break,
+            Ok(Ok(n)) => out.send(Arc::from(&buf[..n])).await,
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            // EIO: every slave fd is closed
+            Ok(Err(_)) => break,
+            Err(_would_block) => continue,
+        }
+    }
+    out.close();
+}
+
+async fn write_loop(master: Arc<AsyncFd<OwnedFd>>, mut in_rx: mpsc::Receiver<Vec<u8>>) {
+    while let Some(buf) = in_rx.recv().await {
+        let mut rest = &buf[..];
+        while !rest.is_empty() {
+            let Ok(mut guard) = master.writable().await else {
+                return;
+            };
+            match guard.try_io(|fd| rustix::io::write(fd.get_ref(), rest).map_err(Into::into)) {
+                Ok(Ok(n)) => rest = &rest[n..],
+                Ok(Err(e)) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Ok(Err(_)) => return,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+fn winsize(cols: u16, rows: u16, width: u16, height: u16) -> Winsize {
+    Winsize { ws_row: rows, ws_col: cols, ws_xpixel: width, ws_ypixel: height }
+}
+
+/// Same shape as portable-pty reports: signal deaths carry `strsignal`'s name and code 1.
+fn exit_status(status: std::process::ExitStatus) -> ExitStatus {
+    match status.signal() {
+        Some(sig) => {
+            // SAFETY: strsignal returns a pointer to a static (or thread-local) NUL-terminated string.
+            let name = unsafe { libc::strsignal(sig) };
+            let signal = if name.is_null() {
+                format!("Signal {sig}")
+            } else {
+                unsafe { std::ffi::CStr::from_ptr(name) }.to_string_lossy().into_owned()
+            };
+            ExitStatus { code: status.code().map(|c| c as u32).unwrap_or(1), signal: Some(signal) }
+        }
+        None => ExitStatus { code: status.code().map(|c| c as u32).unwrap_or(0), signal: None },
+    }
+}
+
+impl PtyBackend for Native {
+    fn write<'a>(&'a self, bytes: &'a [u8]) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            self.in_tx
+                .send(bytes.to_vec())
+                .await
+                .map_err(|_| PtyError::Io(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pty stdin closed")))?;
+            Ok(bytes.len())
+        })
+    }
+
+    fn try_write(&self, bytes: &[u8]) -> Result<usize> {
+        self.in_tx.try_send(bytes.to_vec()).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => PtyError::InputFull,
+            mpsc::error::TrySendError::Closed(_) => {
+                PtyError::Io(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pty stdin closed"))
+            }
+        })?;
+        Ok(bytes.len())
+    }
+
+    fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        self.resize_pixels(cols, rows, 0, 0)
+    }
+
+    fn resize_pixels(&self, cols: u16, rows: u16, width: u16, height: u16) -> Result<()> {
+        tcsetwinsize(self.master.get_ref(), winsize(cols, rows, width, height)).map_err(|_| PtyError::ResizeFailed)
+    }
+
+    fn subscribe(&self, backpressure: Backpressure) -> OutputRx {
+        self.out.subscribe(backpressure)
+    }
+
+    fn signal(&self, sig: Sig, target: SigTarget) -> Result<()> {
+        // once reaped, the pid (and its group) may belong to someone else
+        if self.exit_tx.borrow().is_some() {
+            return Err(PtyError::InvalidArgs("process has exited"));
+        }
+        let pgrp = match target {
+            SigTarget::Group => None,
+            SigTarget::Foreground => tcgetpgrp(self.master.get_ref()).ok(),
+        };
+        kill_group(pgrp, self.pid, sig)
+    }
+
+    fn exit_watch(&self) -> watch::Receiver<Option<ExitStatus>> {
+        self.exit_tx.subscribe()
+    }
+
+    fn pid(&self) -> Option<u32> {
+        Some(self.pid)
+    }
+
+    fn foreground_pid(&self) -> Option<u32> {
+        tcgetpgrp(self.master.get_ref()).ok().map(|p| p.as_raw_nonzero().get() as u32)
+    }
+}
@@ -1,5 +1,9 @@
+use super::fanout::Fanout;
 use super::*;
 use portable_pty::{CommandBuilder, MasterPty, NativePtySystem, PtyPair, PtySize, PtySystem};
+use rustix::process::Pid;
+use rustix::termios::tcgetpgrp;
+use std::os::fd::BorrowedFd;
 use std::thread;
 use std::{
     io::{Read, Write},
@@ -9,12 +13,12 @@ use tokio::sync::{mpsc, watch};
 
 const READ_CHUNK: usize = 16 * 1024; // 16 KiB
 
-pub(crate) struct Inner {
-    master: Mutex<Box<dyn MasterPty + Send>>,               // writer+resize target
-    child: Arc<Mutex<Box<dyn portable_pty::Child + Send>>>, // shared child handle
-    in_tx: mpsc::Sender<Vec<u8>>,                           // buffered stdin pipeline
-    out_taps: Arc<Mutex<Vec<mpsc::Sender<ByteChunk>>>>,     // fan‑out taps (Arc for sharing)
-    exit_tx: watch::Sender<Option<ExitStatus>>,             // exit watcher
+struct Inner {
+    master: Mutex<Box<dyn MasterPty + Send>>,   // writer+resize target
+    in_tx: mpsc::Sender<Vec<u8>>,               // buffered stdin pipeline
+    out: Arc<Fanout>,                           // fan‑out taps (Arc for sharing)
+    exit_tx: watch::Sender<Option<ExitStatus>>, // exit watcher
+    pid: Option<u32>,                           // child pid == its pgid (setsid)
 }
 
 pub(super) fn spawn(program: Program, cfg: PtyConfig) -> Result<PtyHandle> {
@@ -43,29 +47,30 @@ pub(super) fn spawn(program: Program, cfg: PtyConfig) -> Result<PtyHandle> {
         }
     };
 
-    cmd.env("TERM", cfg.term.as_deref().unwrap_or("xterm-256color"));
     if let Some(ref cwd) = cfg.cwd {
         cmd.cwd(cwd);
     }
     for (k, v) in &cfg.env {
         cmd.env(k, v);
     }
+    // an explicit term wins over TERM in the environment, which wins over the default
+    match cfg.term {
+        Some(ref term) => cmd.env("TERM", term),
+        None if !cfg.env.iter().any(|(k, _)| k == "TERM") => cmd.env("TERM", "xterm-256color"),
+        None => {}
+    }
 
     // Spawn the child attached to the slave
     let child = pair.slave.spawn_command(cmd).map_err(|_| PtyError::SpawnFailed)?;
+    let pid = child.process_id();
 
     // Master PTY handles
     let master: Box<dyn MasterPty + Send> = pair.master; // keep for resize
-    let mut reader = master
-        .try_clone_reader()
-        .map_err(|e| PtyError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?; // portable-pty uses anyhow::Error
+    let mut reader = master.try_clone_reader().map_err(|e| PtyError::Io(std::io::Error::other(e.to_string())))?; // portable-pty uses anyhow::Error
 
     // Writer: wrap in Arc<Mutex<...>> so we can offload each write into spawn_blocking
-    let writer = Arc::new(Mutex::new(
-        master
-            .take_writer()
-            .map_err(|e| PtyError::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))?,
-    ));
+    let writer =
+        Arc::new(Mutex::new(master.take_writer().map_err(|e| PtyError::Io(std::io::Error::other(e.to_string())))?));
 
     // Writer pipeline (stdin): async recv → blocking write in blocking pool
     let (in_tx, mut in_rx) = mpsc::channel::<Vec<u8>>(256);
@@ -89,96 +94,112 @@ pub(super) fn spawn(program: Program, cfg: PtyConfig) -> Result<PtyHandle> {
 
     // Reader fan‑out: dedicated OS thread (blocking `read`) → MPSC taps
     let (exit_tx, _exit_rx) = watch::channel::<Option<ExitStatus>>(None);
-    let out_taps = Arc::new(Mutex::new(Vec::<mpsc::Sender<ByteChunk>>::new()));
+    let out = Arc::new(Fanout::default());
 
-    // Shared child for wait(), cloned into the reader thread and stored in Inner
-    let child_shared: Arc<Mutex<Box<dyn portable_pty::Child + Send>>> = Arc::new(Mutex::new(child));
-    let child_for_wait = child_shared.clone();
+    // Child for wait() in the reader thread; signals go through rustix by pid instead
+    let child_for_wait: Arc<Mutex<Box<dyn portable_pty::Child + Send>>> = Arc::new(Mutex::new(child));
     let exit_tx_thr = exit_tx.clone();
-    let out_taps_thr = out_taps.clone();
+    let out_thr = out.clone();
 
     thread::spawn(move || {
         let mut buf = vec![0u8; READ_CHUNK];
         loop {
             match reader.read(&mut buf) {
                 Ok(0) => break,
-                Ok(n) => {
-                    let chunk: ByteChunk = Arc::from(&buf[..n]);
-                    let mut dead = Vec::new();
-                    if let Ok(mut taps) = out_taps_thr.lock() {
-                        for (i, tx) in taps.iter_mut().enumerate() {
-                            if tx.try_send(chunk.clone()).is_err() {
-                                dead.push(i);
-                            }
-                        }
-                        for i in dead.into_iter().rev() {
-                            taps.remove(i);
-                        }
-                    }
-                }
+                Ok(n) => out_thr.blocking_send(Arc::from(&buf[..n])),
                 Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
                 Err(_) => break,
             }
         }
+        out_thr.close();
         // Wait for child and announce exit
         if let Ok(mut c) = child_for_wait.lock() {
-            let status = c.wait().ok().map(|s| {
-                let code = s.exit_code();
-                let sig: Option<String> = s.signal().map(|name| name.to_string());
-                ExitStatus { code, signal: sig }
-            });
-            let _ = exit_tx_thr.send(status);
+            let status = match c.wait() {
+                Ok(s) => ExitStatus { code: s.exit_code(), signal: s.signal().map(|name| name.to_string()) },
+                Err(e) => {
+                    rustlog::warn!("pid {pid:?}: wait failed: {e}");
+                    ExitStatus::UNKNOWN
+                }
+            };
+            let _ = exit_tx_thr.send(Some(status));
         } else {
-            let _ = exit_tx_thr.send(Some(ExitStatus { code: 0, signal: None }));
+            rustlog::warn!("pid {pid:?}: wait failed: child lock poisoned");
+            let _ = exit_tx_thr.send(Some(ExitStatus::UNKNOWN));
         }
     });
 
     // Build handle (keep master for future resizes)
-    let inner = Arc::new(Inner { master: Mutex::new(master), child: child_shared, in_tx, out_taps, exit_tx });
-
-    Ok(PtyHandle { inner })
+    Ok(PtyHandle::new(Inner { master: Mutex::new(master), in_tx, out, exit_tx, pid }))
 }
 
-pub(super) async fn write(h: &PtyHandle, bytes: &[u8]) -> Result<usize> {
-    h.inner
-        .in_tx
-        .send(bytes.to_vec())
-        .await
-        .map_err(|_| PtyError::Io(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pty stdin closed")))?;
-    Ok(bytes.len())
-}
+impl PtyBackend for Inner {
+    fn write<'a>(&'a self, bytes: &'a [u8]) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            self.in_tx
+                .send(bytes.to_vec())
+                .await
+                .map_err(|_| PtyError::Io(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pty stdin closed")))?;
+            Ok(bytes.len())
+        })
+    }
 
-pub(super) fn resize(h: &PtyHandle, cols: u16, rows: u16) -> Result<()> {
-    let size = PtySize { rows, cols, pixel_width: 0, pixel_height: 0 };
-    let master = h.inner.master.lock().map_err(|_| PtyError::ResizeFailed)?;
-    master.resize(size).map_err(|_| PtyError::ResizeFailed)
-}
+    fn try_write(&self, bytes: &[u8]) -> Result<usize> {
+        self.in_tx.try_send(bytes.to_vec()).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => PtyError::InputFull,
+            mpsc::error::TrySendError::Closed(_) => {
+                PtyError::Io(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pty stdin closed"))
+            }
+        })?;
+        Ok(bytes.len())
+    }
 
-pub(super) fn subscribe(h: &PtyHandle) -> OutputRx {
-    let (tx, rx) = mpsc::channel::<ByteChunk>(512);
-    if let Ok(mut taps) = h.inner.out_taps.lock() {
-        taps.push(tx);
+    fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        self.resize_pixels(cols, rows, 0, 0)
     }
-    rx
-}
 
-pub(super) fn signal(h: &PtyHandle, sig: Sig) -> Result<()> {
-    match sig {
-        Sig::Int => {
-            h.inner
-                .in_tx
-                .try_send(vec![0x03])
-                .map_err(|_| PtyError::Io(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pty stdin closed")))?;
-            Ok(())
-        }
-        Sig::Hup => Ok(()),
-        Sig::Term | Sig::Kill => {
-            let mut child = h.inner.child.lock().map_err(|_| PtyError::WaitFailed)?;
-            child.kill().map_err(|_| PtyError::Io(std::io::Error::from(std::io::ErrorKind::Other)))
+    fn resize_pixels(&self, cols: u16, rows: u16, width: u16, height: u16) -> Result<()> {
+        let size = PtySize { rows, cols, pixel_width: width, pixel_height: height };
+        let master = self.master.lock().map_err(|_| PtyError::ResizeFailed)?;
+        master.resize(size).map_err(|_| PtyError::ResizeFailed)
+    }
+
+    fn subscribe(&self, backpressure: Backpressure) -> OutputRx {
+        self.out.subscribe(backpressure)
+    }
+
+    fn signal(&self, sig: Sig, target: SigTarget) -> Result<()> {
+        let pid = self.pid.ok_or(PtyError::Unsupported)?;
+        // once reaped, the pid (and its group) may belong to someone else
+        if self.exit_tx.borrow().is_some() {
+            return Err(PtyError::InvalidArgs("process has exited"));
         }
+        let pgrp = match target {
+            SigTarget::Group => None,
+            SigTarget::Foreground => self.foreground_pgrp(),
+        };
+        kill_group(pgrp, pid, sig)
+    }
+
+    fn exit_watch(&self) -> watch::Receiver<Option<ExitStatus>> {
+        self.exit_tx.subscribe()
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    fn foreground_pid(&self) -> Option<u32> {
+        self.foreground_pgrp().map(|p| p.as_raw_nonzero().get() as u32)
     }
 }
 
-pub(super) fn exit_watch(h: &PtyHandle) -> watch::Receiver<Option<ExitStatus>> {
-    h.inner.exit_tx.subscribe()
+impl Inner {
+    /// Foreground process group of the PTY, if the master can tell.
+    fn foreground_pgrp(&self) -> Option<Pid> {
+        let master = self.master.lock().ok()?;
+        let fd = master.as_raw_fd()?;
+        // SAFETY: the fd belongs to `master`, which stays locked (and open) for this call.
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+        tcgetpgrp(fd).ok()
+    }
 }
@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+/// What `/proc` says about a process; Linux only, `None` elsewhere or once it is gone.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProcInfo {
+    pub pid: u32,
+    /// Short name from `/proc/<pid>/comm`, e.g. `vim`.
+    pub command: String,
+    pub cmdline: Vec<String>,
+    pub cwd: Option<PathBuf>,
+}
+
+pub fn read(pid: u32) -> Option<ProcInfo> {
+    let dir = PathBuf::from(format!("/proc/{pid}"));
+    let command = std::fs::read_to_string(dir.join("comm")).ok()?.trim_end().to_string();
+    let cmdline = std::fs::read(dir.join("cmdline"))
+        .map(|raw| {
+            raw.split(|&b| b == 0).filter(|a| !a.is_empty()).map(|a| String::from_utf8_lossy(a).into_owned()).collect()
+        })
+        .unwrap_or_default();
+    // another user's process hides its cwd; keep the rest
+    let cwd = std::fs::read_link(dir.join("cwd")).ok();
+    Some(ProcInfo { pid, command, cmdline, cwd })
+}
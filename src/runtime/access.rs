@@ -0,0 +1,154 @@
+use super::Core;
+use crate::ipc::proto::{
+    ErrorCode, Event, GrepScope, KillTarget, PolicyTarget, RecordTarget, Request, Response, StateScope,
+};
+use crate::server::{acl::Role, pane::PaneId, peer::PeerId, session::SessionId, window::WindowId};
+
+/// What a request asks of the requesting peer.
+enum Need {
+    /// Anyone admitted to the server; the request only concerns the peer itself.
+    Nothing,
+    /// The server's own user: the request reaches beyond any one session.
+    Owner,
+    /// At least these roles in these sessions.
+    Roles(Vec<(SessionId, Role)>),
+}
+
+impl Core {
+    /// Whether `peer` is the server's own user (or root, or inside the server process), who may
+    /// do everything without grants.
+    fn is_owner(&self, peer: PeerId) -> bool {
+        match self.state.peer(peer).map(|p| p.cred) {
+            Some(Some(cred)) => cred.uid == self.uid || cred.uid == 0,
+            Some(None) => true,
+            None => false,
+        }
+    }
+
+    /// `peer`'s role in `session`; owners hold every role.
+    pub(super) fn role(&self, peer: PeerId, session: SessionId) -> Option<Role> {
+        if self.is_owner(peer) {
+            return Some(Role::Admin);
+        }
+        let cred = self.state.peer(peer)?.cred;
+        self.state.session(session)?.acl.role(peer, cred.as_ref())
+    }
+
+    /// Check `req` against the sessions' access lists, answering `Denied` if `peer` falls short.
+    pub(super) fn authorize(&self, peer: PeerId, req: &Request) -> Result<(), Response> {
+        match self.need(req) {
+            Need::Nothing => Ok(()),
+            _ if self.is_owner(peer) => Ok(()),
+            Need::Owner => Err(denied("only the server's owner may do that".into())),
+            Need::Roles(needs) => match needs.into_iter().find(|&(s, role)| self.role(peer, s) < Some(role)) {
+                Some((session, role)) => Err(denied(format!("needs {role} access to session {session}"))),
+                None => Ok(()),
+            },
+        }
+    }
+
+    fn need(&self, req: &Request) -> Need {
+        use Role::*;
+        let pane = |p: PaneId| self.state.locate_pane(p).map(|(s, _)| s);
+        let window = |w: WindowId| self.state.locate_window(w);
+        // targets that don't exist are left for the request itself to report
+        let on = |session: Option<SessionId>, role| Need::Roles(session.map(|s| (s, role)).into_iter().collect());
+        match *req {
+            Request::ListSessions
+            | Request::SetBackpressure { .. }
+            | Request::SetOutputRate { .. }
+            | Request::Detach { .. } => Need::Nothing,
+            Request::CreateSession { .. }
+            | Request::Bind { .. }
+            | Request::Unbind { .. }
+            | Request::SetBuffer { .. }
+            | Request::ListBuffers
+            | Request::ShowBuffer { .. }
+            | Request::DeleteBuffer { .. }
+            | Request::PasteBuffer { .. }
+            | Request::GetState { scope: StateScope::Sessions | StateScope::Peers }
+            | Request::GetState { scope: StateScope::Windows { session: None } }
+            | Request::GetState { scope: StateScope::Panes { window: None } }
+            | Request::Grep { scope: GrepScope::All, .. } => Need::Owner,
+            Request::Attach { session, .. } => on(Some(session), View),
+            Request::GetState { scope: StateScope::Windows { session: Some(s) } } => on(Some(s), View),
+            Request::GetState { scope: StateScope::Panes { window: Some(w) } } => on(window(w), View),
+            Request::Capture { pane: p, .. } => on(pane(p), View),
+            Request::Grep { scope: GrepScope::Session(s), .. } => on(Some(s), View),
+            Request::Grep { scope: GrepScope::Window(w), .. } => on(window(w), View),
+            Request::Input { pane: p, .. }
+            | Request::Resize { pane: p, .. }
+            | Request::Signal { pane: p, .. }
+            | Request::RequestInput { pane: p, force: false }
+            | Request::GiveInput { pane: p, .. } => on(pane(p), Input),
+            Request::SpawnPane { session, cwd_from, .. } => {
+                let from = cwd_from.and_then(pane).map(|s| (s, View));
+                Need::Roles([(session, Admin)].into_iter().chain(from).collect())
+            }
+            Request::CreateWindow { session, .. }
+            | Request::SetClipboardPolicy { session, .. }
+            | Request::SetEnvironment { session, .. }
+            | Request::ShowEnvironment { session }
+            | Request::Grant { session, .. }
+            | Request::Revoke { session, .. }
+            | Request::ShowAccess { session } => on(Some(session), Admin),
+            Request::Kill { target, .. } => on(
+                match target {
+                    KillTarget::Session(s) => Some(s),
+                    KillTarget::Window(w) => window(w),
+                    KillTarget::Pane(p) => pane(p),
+                },
+                Admin,
+            ),
+            Request::SetRemainOnExit { target, .. } => on(
+                match target {
+                    PolicyTarget::Session(s) => Some(s),
+                    PolicyTarget::Window(w) => window(w),
+                    PolicyTarget::Pane(p) => pane(p),
+                },
+                Admin,
+            ),
+            Request::Record { target, .. } => on(
+                match target {
+                    RecordTarget::Window(w) => window(w),
+                    RecordTarget::Pane(p) => pane(p),
+                },
+                Admin,
+            ),
+            Request::RespawnPane { pane: p, .. }
+            | Request::SetAutoRespawn { pane: p, .. }
+            | Request::RequestInput { pane: p, force: true }
+            | Request::SetInputMode { pane: p, .. }
+            | Request::PipePane { pane: p, .. } => on(pane(p), Admin),
+            Request::RenameWindow { window: w, .. } => on(window(w), Admin),
+        }
+    }
+
+    /// Bring `session`'s peers in line with its grants: those that may no longer watch it are
+    /// detached and told why, those that may no longer type are left watching read-only.
+    pub(super) fn enforce_access(&mut self, session: SessionId) {
+        let Some(s) = self.state.session(session) else {
+            return;
+        };
+        let peers: Vec<(PeerId, Option<Role>)> = s.peers().map(|&p| (p, self.role(p, session))).collect();
+        for (peer, role) in peers {
+            if role.is_none() {
+                self.detach_all(peer);
+                self.broadcast(Some(session), |_| Some(Event::PeerDetached { peer }));
+                self.emit(peer, Event::Bye { reason: format!("access to session {session} revoked") });
+            } else if role < Some(Role::Input) {
+                let Some(at) = self.state.peer_mut(peer).and_then(|p| p.attachment.as_mut()) else {
+                    continue;
+                };
+                at.read_only = true;
+                let pane = at.pane;
+                self.input_change(pane, |p| p.set_read_only(peer));
+                self.pump(pane);
+            }
+        }
+    }
+}
+
+pub(crate) fn denied(msg: String) -> Response {
+    Response::Err { code: ErrorCode::Denied, msg }
+}
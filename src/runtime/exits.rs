@@ -0,0 +1,143 @@
+use super::Core;
+use crate::ipc::proto::Event;
+use crate::pty::Program;
+use crate::server::{pane::PaneId, peer::PeerId, session::SessionId, window::WindowId};
+use crate::{Error, Result};
+use std::path::PathBuf;
+
+/// Pane process changes observed off the core loop.
+pub(super) enum Lifecycle {
+    Exited(PaneId),
+    /// An automatic respawn whose backoff has elapsed.
+    Respawn(PaneId),
+}
+
+impl Core {
+    /// Report the exit of `pane`'s current process back to the core loop.
+    pub(super) fn watch_exit(&self, pane: PaneId) {
+        let Some(mut exit) = self.state.pane(pane).and_then(|p| p.exit_watch()) else {
+            return;
+        };
+        let tx = self.lifecycle_tx.clone();
+        tokio::spawn(async move {
+            if exit.wait_for(Option::is_some).await.is_ok() {
+                let _ = tx.send(Lifecycle::Exited(pane));
+            }
+        });
+    }
+
+    pub(super) fn lifecycle(&mut self, ev: Lifecycle) {
+        match ev {
+            Lifecycle::Exited(pane) => self.exited(pane),
+            Lifecycle::Respawn(pane) => {
+                // a manual respawn or a policy change may have happened during the backoff
+                if self.state.pane(pane).is_none_or(|p| p.is_running() || p.auto_respawn().is_none()) {
+                    return;
+                }
+                match self.respawn(pane, None, None, false) {
+                    Ok(()) => rustlog::info!("pane {pane}: respawned"),
+                    Err(e) => {
+                        rustlog::warn!("pane {pane}: respawn failed: {e}");
+                        self.schedule_respawn(pane);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Tell the session, then respawn, keep or close the pane as its policies say.
+    fn exited(&mut self, pane: PaneId) {
+        let Some((session, window)) = self.state.locate_pane(pane) else {
+            return;
+        };
+        let Some(s) = self.state.session_mut(session) else {
+            return;
+        };
+        let fallback = s.remain_on_exit;
+        let Some(w) = s.window_mut(window) else {
+            return;
+        };
+        let fallback = w.remain_on_exit.or(fallback);
+        let Some(p) = w.pane_mut(pane) else {
+            return;
+        };
+        // a stale watch from before a respawn
+        p.poll_exit();
+        let Some(status) = p.exit_status().cloned() else {
+            return;
+        };
+        let respawns = p.auto_respawn().is_some();
+        let keep = respawns || p.remain_on_exit.or(fallback).unwrap_or_default().keeps(&status);
+        rustlog::info!("pane {pane}: exited with {status}");
+
+        if keep {
+            p.finish(format!("\r\n[exited with {status}]\r\n").into_bytes());
+        }
+        self.broadcast(Some(session), |_| Some(Event::PaneExited { pane, status: status.clone(), closed: !keep }));
+        if respawns {
+            self.schedule_respawn(pane);
+        } else if !keep {
+            self.close_pane(session, window, pane);
+        }
+    }
+
+    /// Remove an exited pane, then its window and session if that left them empty.
+    fn close_pane(&mut self, session: SessionId, window: WindowId, pane: PaneId) {
+        let peers: Vec<PeerId> = self.state.pane(pane).map(|p| p.attached().copied().collect()).unwrap_or_default();
+        for peer in peers {
+            self.detach_all(peer);
+        }
+        self.pipes.remove(&pane);
+        self.end_recording(pane);
+        let Some(w) = self.state.session_mut(session).and_then(|s| s.window_mut(window)) else {
+            return;
+        };
+        w.remove_pane(pane);
+        if !w.is_empty() {
+            self.broadcast(Some(session), |_| Some(Event::LayoutChanged { window }));
+            return;
+        }
+        if let Some(s) = self.state.session_mut(session) {
+            s.remove_window(window);
+        }
+        self.broadcast(Some(session), |_| Some(Event::WindowClosed { window }));
+        if self.state.session(session).is_some_and(|s| s.is_empty()) {
+            rustlog::info!("session {session}: last pane exited");
+            self.state.remove_session(session);
+        }
+    }
+
+    fn schedule_respawn(&mut self, pane: PaneId) {
+        let Some(delay) = self.state.pane_mut(pane).and_then(|p| p.respawn_delay()) else {
+            return;
+        };
+        rustlog::info!("pane {pane}: respawning in {delay:?}");
+        let tx = self.lifecycle_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = tx.send(Lifecycle::Respawn(pane));
+        });
+    }
+
+    /// Respawn `pane` in place and rewire its peers' output to the new process.
+    pub(super) fn respawn(
+        &mut self,
+        pane: PaneId,
+        program: Option<Program>,
+        cwd: Option<PathBuf>,
+        kill: bool,
+    ) -> Result {
+        let (notify, spawner) = (self.notice_tx.clone(), self.spawner.clone());
+        let p = self.state.pane_mut(pane).ok_or_else(|| Error::InvalidState("no such pane".into()))?;
+        p.set_notify(notify);
+        if let Some(spawner) = spawner {
+            p.set_spawner(spawner);
+        }
+        p.respawn(program, cwd, kill)?;
+        self.repipe(pane);
+        self.rerecord(pane);
+        self.watch_exit(pane);
+        self.pump(pane);
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use super::access::denied;
+use super::{Core, failed, not_found};
+use crate::ipc::proto::{Event, Response};
+use crate::server::{
+    acl::Role,
+    pane::{InputMode, Pane, PaneId},
+    peer::PeerId,
+};
+
+impl Core {
+    /// Apply `change` to `pane`, telling its session when that moved input ownership or changed
+    /// the input mode; `None` if there is no such pane.
+    pub(super) fn input_change<T>(&mut self, pane: PaneId, change: impl FnOnce(&mut Pane) -> T) -> Option<T> {
+        let p = self.state.pane_mut(pane)?;
+        let before = (p.input_mode(), p.input_owner());
+        let out = change(p);
+        let (mode, owner) = (p.input_mode(), p.input_owner());
+        if (mode, owner) != before {
+            let session = self.state.locate_pane(pane).map(|(s, _)| s);
+            self.broadcast(session, |_| Some(Event::InputOwnerChanged { pane, mode, owner }));
+        }
+        Some(out)
+    }
+
+    /// In exclusive mode, a pane nobody owns goes to the first peer that types into it.
+    pub(super) fn claim_input(&mut self, peer: PeerId, pane: PaneId) {
+        let unowned = self.state.pane(pane).is_some_and(|p| {
+            p.input_mode() == InputMode::Exclusive && p.input_owner().is_none() && p.may_own(peer).is_ok()
+        });
+        if unowned {
+            self.input_change(pane, |p| p.set_input_owner(Some(peer)));
+        }
+    }
+
+    pub(super) fn request_input(&mut self, peer: PeerId, pane: PaneId, force: bool) -> Response {
+        let Some(p) = self.state.pane(pane) else {
+            return not_found("pane");
+        };
+        if let Err(e) = p.may_own(peer) {
+            return failed(e);
+        }
+        match p.input_owner() {
+            Some(owner) if owner != peer && !force => {
+                rustlog::info!("pane {pane}: peer {peer} asks {owner} for input");
+                self.emit(owner, Event::InputRequested { pane, peer });
+            }
+            Some(owner) if owner == peer => {}
+            _ => {
+                self.input_change(pane, |p| p.set_input_owner(Some(peer)));
+            }
+        }
+        Response::Ok
+    }
+
+    pub(super) fn give_input(&mut self, peer: PeerId, pane: PaneId, to: Option<PeerId>) -> Response {
+        let Some(p) = self.state.pane(pane) else {
+            return not_found("pane");
+        };
+        let admin = || self.state.locate_pane(pane).is_some_and(|(s, _)| self.role(peer, s) >= Some(Role::Admin));
+        if p.input_owner() != Some(peer) && !admin() {
+            return denied("only the input owner or an admin may hand input over".into());
+        }
+        match self.input_change(pane, |p| p.set_input_owner(to)) {
+            Some(Ok(())) => Response::Ok,
+            Some(Err(e)) => failed(e),
+            None => not_found("pane"),
+        }
+    }
+}
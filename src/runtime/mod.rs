@@ -0,0 +1,286 @@
+use crate::ipc::proto::{ErrorCode, Event, Request, Response};
+use crate::ipc::server::{CoreMsg, IpcServer};
+use crate::pty::Spawner;
+use crate::server::{
+    pane::NoticeTx, pane::PaneId, peer::PeerId, session::SessionId, state::ServerState,
+    window::WindowId,
+};
+use crate::term::Notice;
+use crate::{Error, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::{sync::mpsc, task::AbortHandle};
+
+mod access;
+mod exits;
+mod input;
+mod notices;
+mod pipes;
+mod record;
+mod requests;
+mod taps;
+
+/// How often each pane's foreground process and cwd are re-read.
+const PROC_POLL: Duration = Duration::from_secs(1);
+/// Window renames are coalesced and broadcast at most this often.
+const TITLE_RATE: Duration = Duration::from_millis(250);
+
+/// Settings for [`serve`].
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Largest frame payload a peer may send; see [`IpcServer::with_max_frame`].
+    pub max_frame: u32,
+    /// Uids besides the server's own (and root) allowed to connect. Any makes the socket
+    /// reachable by every user, and connections are admitted by their `SO_PEERCRED` uid.
+    pub allow_uids: Vec<u32>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self { max_frame: crate::ipc::wire::MAX_FRAME, allow_uids: Vec::new() }
+    }
+}
+
+/// Owner of [`ServerState`]; serialises every peer request through one loop.
+pub struct Core {
+    state: ServerState,
+    links: HashMap<PeerId, mpsc::Sender<Event>>,
+    forwarders: HashMap<(PeerId, PaneId), AbortHandle>,
+    pipes: HashMap<PaneId, pipes::Pipe>,
+    recordings: HashMap<PaneId, mpsc::UnboundedSender<record::Cue>>,
+    notice_tx: NoticeTx,
+    notice_rx: Option<mpsc::UnboundedReceiver<(PaneId, Notice)>>,
+    lifecycle_tx: mpsc::UnboundedSender<exits::Lifecycle>,
+    lifecycle_rx: Option<mpsc::UnboundedReceiver<exits::Lifecycle>>,
+    /// Exported to panes in `SPLICER` so programs inside can reach the server.
+    socket: Option<PathBuf>,
+    /// Windows renamed since the last `TitleChanged` flush.
+    titles: HashSet<WindowId>,
+    /// The server's user, who needs no grants.
+    uid: u32,
+    /// Starts pane programs; `None` spawns real PTYs.
+    spawner: Option<Spawner>,
+}
+
+impl Core {
+    pub fn new(state: ServerState) -> Self {
+        let (notice_tx, notice_rx) = mpsc::unbounded_channel();
+        let (lifecycle_tx, lifecycle_rx) = mpsc::unbounded_channel();
+        Self {
+            state,
+            links: HashMap::new(),
+            forwarders: HashMap::new(),
+            pipes: HashMap::new(),
+            recordings: HashMap::new(),
+            notice_tx,
+            notice_rx: Some(notice_rx),
+            lifecycle_tx,
+            lifecycle_rx: Some(lifecycle_rx),
+            titles: HashSet::new(),
+            socket: None,
+            uid: rustix::process::getuid().as_raw(),
+            spawner: None,
+        }
+    }
+
+    pub fn with_socket(mut self, socket: &Path) -> Self {
+        self.socket = Some(std::path::absolute(socket).unwrap_or_else(|_| socket.to_path_buf()));
+        self
+    }
+
+    /// Start every pane's program with `spawner`, e.g. on a [`crate::pty::fake::FakePty`].
+    pub fn with_spawner(mut self, spawner: Spawner) -> Self {
+        self.spawner = Some(spawner);
+        self
+    }
+
+    pub fn state(&self) -> &ServerState {
+        &self.state
+    }
+
+    pub async fn run(mut self, mut rx: mpsc::Receiver<CoreMsg>) {
+        let (Some(mut notices), Some(mut lifecycle)) = (self.notice_rx.take(), self.lifecycle_rx.take()) else {
+            return;
+        };
+        let mut poll = tokio::time::interval(PROC_POLL);
+        poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut titles = tokio::time::interval(TITLE_RATE);
+        titles.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            let msg = tokio::select! {
+                msg = rx.recv() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                Some((pane, n)) = notices.recv() => {
+                    self.notice(pane, n).await;
+                    continue;
+                }
+                Some(ev) = lifecycle.recv() => {
+                    self.lifecycle(ev);
+                    continue;
+                }
+                _ = poll.tick() => {
+                    self.refresh_processes();
+                    continue;
+                }
+                _ = titles.tick() => {
+                    self.flush_titles();
+                    continue;
+                }
+            };
+            match msg {
+                CoreMsg::RegisterPeer { ev_tx, cred, reply } => {
+                    let id = self.state.new_peer("peer");
+                    if let Some(p) = self.state.peer_mut(id) {
+                        p.cred = cred;
+                    }
+                    self.links.insert(id, ev_tx);
+                    let _ = reply.send(id);
+                }
+                CoreMsg::FromPeer { peer, req, reply } => match (self.authorize(peer, &req), req) {
+                    (Err(resp), _) => {
+                        let _ = reply.send(resp);
+                    }
+                    // kills wait out grace periods, so they answer from their own task
+                    (Ok(()), Request::Kill { target, force, grace }) => match self.kill(target, force, grace) {
+                        Ok(job) => {
+                            tokio::spawn(async move {
+                                let _ = reply.send(job.await);
+                            });
+                        }
+                        Err(resp) => {
+                            let _ = reply.send(resp);
+                        }
+                    },
+                    (Ok(()), req) => {
+                        let resp = self.handle(peer, req).await;
+                        let _ = reply.send(resp);
+                    }
+                },
+                CoreMsg::UnregisterPeer { peer } => self.drop_peer(peer),
+            }
+        }
+    }
+
+    /// Re-read every pane's process and let automatically named windows follow it.
+    fn refresh_processes(&mut self) {
+        for (_, s) in self.state.sessions_mut() {
+            for (&wid, w) in s.windows_mut() {
+                for (_, p) in w.panes_mut() {
+                    p.refresh_process();
+                }
+                if w.update_name() {
+                    self.titles.insert(wid);
+                }
+            }
+        }
+    }
+
+    /// Broadcast `TitleChanged` once per renamed window, however often it changed meanwhile.
+    fn flush_titles(&mut self) {
+        for window in std::mem::take(&mut self.titles) {
+            let Some(session) = self.state.locate_window(window) else {
+                continue;
+            };
+            let Some(title) = self.state.session(session).and_then(|s| s.window(window)).map(|w| w.name.clone()) else {
+                continue;
+            };
+            self.broadcast(Some(session), |_| Some(Event::TitleChanged { window, title: title.clone() }));
+        }
+    }
+
+    fn drop_peer(&mut self, peer: PeerId) {
+        self.detach_all(peer);
+        for (_, s) in self.state.sessions_mut() {
+            s.acl.forget_peer(peer);
+        }
+        self.state.remove_peer(peer);
+        self.links.remove(&peer);
+        self.broadcast(None, |_| Some(Event::PeerDetached { peer }));
+    }
+
+    /// Queue an event for one peer; a full queue drops the event rather than stalling the core.
+    fn emit(&self, peer: PeerId, ev: Event) {
+        if let Some(tx) = self.links.get(&peer) {
+            let _ = tx.try_send(ev);
+        }
+    }
+
+    /// Send an event to every peer attached to `session` (or every peer when `None`).
+    fn broadcast(&self, session: Option<SessionId>, mut ev: impl FnMut(PeerId) -> Option<Event>) {
+        let peers: Vec<PeerId> = match session.and_then(|sid| self.state.session(sid)) {
+            Some(s) => s.peers().copied().collect(),
+            None if session.is_none() => self.links.keys().copied().collect(),
+            None => Vec::new(),
+        };
+        for p in peers {
+            if let Some(e) = ev(p) {
+                self.emit(p, e);
+            }
+        }
+    }
+
+    fn pane_or_err(&mut self, id: PaneId) -> std::result::Result<&mut crate::server::pane::Pane, Response> {
+        self.state.pane_mut(id).ok_or_else(|| not_found("pane"))
+    }
+}
+
+/// Bind the IPC socket and run the core until the listener fails. A missing socket directory is
+/// created private to this user, and an existing one must already be.
+pub async fn serve(socket: &Path, config: ServerConfig) -> Result {
+    // uids on the allow list must reach the socket, so they get search access to the directory
+    // (not listing it or creating in it) and the socket is left open; SO_PEERCRED sorts them out
+    let (dir_mode, socket_mode) = if config.allow_uids.is_empty() { (0o700, 0o600) } else { (0o711, 0o666) };
+    let dir = socket.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if std::fs::symlink_metadata(dir).is_err_and(|e| e.kind() == std::io::ErrorKind::NotFound) {
+        use std::os::unix::fs::DirBuilderExt;
+        std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    }
+    socket_dir(dir, dir_mode)?;
+    if socket.exists() {
+        // a live server answers connects; anything else is a stale socket file
+        if tokio::net::UnixStream::connect(socket).await.is_ok() {
+            return Err(Error::InvalidState(format!("server already running at {}", socket.display())));
+        }
+        std::fs::remove_file(socket)?;
+    }
+    let (core_tx, core_rx) = mpsc::channel(1024);
+    let ipc = IpcServer::bind_mode(&socket.to_string_lossy(), socket_mode, core_tx)
+        .await?
+        .with_max_frame(config.max_frame)
+        .with_allowed_uids(&config.allow_uids);
+    rustlog::info!("listening on {}", socket.display());
+    tokio::spawn(Core::new(ServerState::new()).with_socket(socket).run(core_rx));
+    ipc.run().await
+}
+
+/// Check that `dir` is a directory itself, not a symlink to one, owned by this user and closed to
+/// everyone else, then give it `mode`. Only 0700 and 0711 pass, and either becomes `mode`.
+fn socket_dir(dir: &Path, mode: u32) -> Result {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    let meta = std::fs::symlink_metadata(dir)?;
+    let uid = rustix::process::getuid().as_raw();
+    if !meta.is_dir() || meta.uid() != uid {
+        return Err(Error::InvalidState(format!("{} is not a directory owned by uid {uid}", dir.display())));
+    }
+    match meta.mode() & 0o7777 {
+        m if m == mode => Ok(()),
+        0o700 | 0o711 => Ok(std::fs::set_permissions(dir, std::fs::Permissions::from_mode(mode))?),
+        m => Err(Error::InvalidState(format!("{} has mode {m:o}; the socket needs it {mode:o}", dir.display()))),
+    }
+}
+
+pub(crate) fn not_found(what: &str) -> Response {
+    Response::Err { code: ErrorCode::NotFound, msg: format!("no such {what}") }
+}
+
+pub(crate) fn failed(e: Error) -> Response {
+    let code = match e {
+        Error::UserInput(_) | Error::InvalidState(_) => ErrorCode::InvalidArgs,
+        Error::Timeout(_) => ErrorCode::Timeout,
+        _ => ErrorCode::Internal,
+    };
+    Response::Err { code, msg: e.to_string() }
+}
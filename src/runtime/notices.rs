@@ -0,0 +1,63 @@
+use super::Core;
+use crate::ipc::proto::Event;
+use crate::server::pane::PaneId;
+use crate::term::Notice;
+use base64::Engine as _;
+
+impl Core {
+    /// Act on an escape-sequence notice raised by `pane`'s screen model.
+    pub(super) async fn notice(&mut self, pane: PaneId, n: Notice) {
+        let Some((session, window)) = self.state.locate_pane(pane) else {
+            return;
+        };
+        let Some((policy, shared)) = self.state.session(session).map(|s| (s.clipboard, !s.acl.is_empty())) else {
+            return;
+        };
+        match n {
+            Notice::Title(title) => {
+                let Some(w) = self.state.session_mut(session).and_then(|s| s.window_mut(window)) else {
+                    return;
+                };
+                if let Some(p) = w.pane_mut(pane) {
+                    p.title = title;
+                }
+                if w.update_name() {
+                    self.titles.insert(window);
+                }
+            }
+            Notice::ClipboardSet { selection, data } => {
+                if !policy.write {
+                    rustlog::debug!("pane {pane}: clipboard write denied by session policy");
+                    return;
+                }
+                // guests' programs don't get to fill the owner's buffers; clients still get it
+                if shared {
+                    rustlog::debug!("pane {pane}: clipboard write kept out of the buffers in a shared session");
+                } else {
+                    self.state.buffers_mut().set(None, data.clone());
+                }
+                self.broadcast(Some(session), |_| {
+                    Some(Event::Clipboard { pane, selection: selection.clone(), data: data.clone() })
+                });
+            }
+            Notice::ClipboardQuery { selection } => {
+                if !policy.read {
+                    rustlog::debug!("pane {pane}: clipboard read denied by session policy");
+                    return;
+                }
+                if shared {
+                    rustlog::debug!("pane {pane}: clipboard read denied in a shared session");
+                    return;
+                }
+                let data = self.state.buffers().top().map(|b| b.data.as_slice()).unwrap_or_default();
+                let reply =
+                    format!("\x1b]52;{selection};{}\x07", base64::engine::general_purpose::STANDARD.encode(data));
+                if let Some(p) = self.state.pane(pane) {
+                    if let Err(e) = p.reply(reply.as_bytes()) {
+                        rustlog::warn!("pane {pane}: clipboard reply: {e}");
+                    }
+                }
+            }
+        }
+    }
+}
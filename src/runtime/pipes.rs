@@ -0,0 +1,148 @@
+use super::{Core, failed, not_found};
+use crate::Error;
+use crate::ipc::proto::{PipeTarget, Response};
+use crate::pty::{OutputRx, PtyHandle};
+use crate::server::pane::PaneId;
+use crate::term::EscapeStripper;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::process::{Child, ChildStdout, Command};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+
+/// How long a pipe command gets to finish after its input ends, before it is killed.
+const PIPE_GRACE: Duration = Duration::from_secs(1);
+
+/// A running `pipe-pane`, carried over to each new process of its pane.
+pub(super) struct Pipe {
+    task: AbortHandle,
+    /// Hands the pipe the next process's PTY, already tapped; dropped when the pane goes.
+    retap: mpsc::UnboundedSender<(PtyHandle, OutputRx)>,
+}
+
+impl Pipe {
+    pub(super) fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl Core {
+    /// Replace `pane`'s pipe with a new one to `target`, or just stop it for `None`.
+    pub(super) fn pipe_pane(
+        &mut self,
+        pane: PaneId,
+        target: Option<PipeTarget>,
+        plain: bool,
+        input: bool,
+    ) -> Result<(), Response> {
+        let pty = self.state.pane(pane).ok_or_else(|| not_found("pane"))?.pty().cloned();
+        if let Some(old) = self.pipes.remove(&pane) {
+            old.task.abort();
+        }
+        let Some(target) = target else {
+            return Ok(());
+        };
+        let pty = pty.ok_or_else(|| failed(Error::InvalidState("pane has no PTY".into())))?;
+
+        let (sink, child, back): (Box<dyn AsyncWrite + Send + Unpin>, _, _) = match target {
+            PipeTarget::Command(cmd) => {
+                let mut child = Command::new("sh")
+                    .args(["-c", &cmd])
+                    .stdin(Stdio::piped())
+                    .stdout(if input { Stdio::piped() } else { Stdio::null() })
+                    .kill_on_drop(true)
+                    .spawn()
+                    .map_err(|e| failed(e.into()))?;
+                let stdin = child
+                    .stdin
+                    .take()
+                    .ok_or_else(|| failed(Error::InvalidState("pipe command has no stdin".into())))?;
+                let back = child.stdout.take();
+                (Box::new(stdin), Some(child), back)
+            }
+            PipeTarget::File(_) if input => {
+                return Err(failed(Error::UserInput("only a command can feed input back".into())));
+            }
+            PipeTarget::File(path) => {
+                let file =
+                    std::fs::OpenOptions::new().create(true).append(true).open(path).map_err(|e| failed(e.into()))?;
+                (Box::new(tokio::fs::File::from_std(file)), None, None)
+            }
+        };
+        let (retap, taps) = mpsc::unbounded_channel();
+        let rx = pty.subscribe();
+        let task = tokio::spawn(pipe(pty, rx, taps, sink, child, back, plain)).abort_handle();
+        self.pipes.insert(pane, Pipe { task, retap });
+        Ok(())
+    }
+
+    /// Point `pane`'s pipe, if any, at its freshly spawned process.
+    pub(super) fn repipe(&mut self, pane: PaneId) {
+        let Some(pipe) = self.pipes.get(&pane) else {
+            return;
+        };
+        let Some(pty) = self.state.pane(pane).and_then(|p| p.pty()) else {
+            return;
+        };
+        if pipe.retap.send((pty.clone(), pty.subscribe())).is_err() {
+            self.pipes.remove(&pane);
+        }
+    }
+}
+
+/// Runs until the pane is gone and its last output written, the sink fails or the command
+/// closes its stdout, then gives the command [`PIPE_GRACE`] to finish. Dropping the task kills
+/// the command.
+async fn pipe(
+    mut pty: PtyHandle,
+    rx: OutputRx,
+    mut taps: mpsc::UnboundedReceiver<(PtyHandle, OutputRx)>,
+    mut sink: Box<dyn AsyncWrite + Send + Unpin>,
+    child: Option<Child>,
+    mut back: Option<ChildStdout>,
+    plain: bool,
+) {
+    let mut strip = plain.then(EscapeStripper::new);
+    // `None` between a process ending and the next one starting
+    let mut rx = Some(rx);
+    let mut closed = false;
+    let mut buf = [0u8; 4096];
+    loop {
+        tokio::select! {
+            chunk = async { rx.as_mut()?.recv().await }, if rx.is_some() => {
+                let Some(chunk) = chunk else {
+                    rx = None;
+                    if closed {
+                        break;
+                    }
+                    continue;
+                };
+                let chunk = match strip {
+                    Some(ref mut s) => s.strip(&chunk),
+                    None => chunk.to_vec(),
+                };
+                if sink.write_all(&chunk).await.is_err() || sink.flush().await.is_err() {
+                    break;
+                }
+            }
+            tap = taps.recv(), if !closed => match tap {
+                Some((next, next_rx)) => (pty, rx) = (next, Some(next_rx)),
+                // the pane is gone: finish what its last process wrote
+                None if rx.is_some() => closed = true,
+                None => break,
+            },
+            n = async { back.as_mut()?.read(&mut buf).await.ok() }, if back.is_some() => match n {
+                // typed into whichever process is running; lost while none is
+                Some(n @ 1..) => {
+                    let _ = pty.write(&buf[..n]).await;
+                }
+                _ => break,
+            },
+        }
+    }
+    drop(sink);
+    if let Some(mut child) = child {
+        let _ = tokio::time::timeout(PIPE_GRACE, child.wait()).await;
+    }
+}
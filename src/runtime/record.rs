@@ -0,0 +1,149 @@
+use super::{Core, failed, not_found};
+use crate::Error;
+use crate::ipc::proto::{RecordTarget, Response};
+use crate::pty::OutputRx;
+use crate::server::pane::{PaneId, TermSize};
+use crate::term::cast::{Encoder, Header};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// What the core tells a running recording, besides the output it taps itself.
+pub(super) enum Cue {
+    Resize(TermSize),
+    /// The pane's freshly spawned process, to record from here on.
+    Retap(OutputRx),
+    /// The pane is gone: record what its process still writes, then finish.
+    Close,
+}
+
+impl Core {
+    /// Start recording `target` to `path`, replacing any running recording, or stop for `None`.
+    pub(super) fn record(&mut self, target: RecordTarget, path: Option<String>) -> Result<(), Response> {
+        let panes: Vec<PaneId> = match target {
+            RecordTarget::Pane(pane) => {
+                self.state.pane(pane).ok_or_else(|| not_found("pane"))?;
+                vec![pane]
+            }
+            RecordTarget::Window(window) => self
+                .state
+                .locate_window(window)
+                .and_then(|sid| self.state.session(sid)?.window(window))
+                .ok_or_else(|| not_found("window"))?
+                .panes()
+                .map(|(&id, _)| id)
+                .collect(),
+        };
+        // closing a recorder's channel makes it flush and finish
+        for pane in &panes {
+            self.recordings.remove(pane);
+        }
+        let Some(path) = path else {
+            return Ok(());
+        };
+        for pane in panes {
+            let Some(p) = self.state.pane(pane) else {
+                continue;
+            };
+            let Some(pty) = p.pty() else {
+                match target {
+                    RecordTarget::Pane(_) => return Err(failed(Error::InvalidState("pane has no PTY".into()))),
+                    RecordTarget::Window(_) => continue,
+                }
+            };
+            let path = match target {
+                RecordTarget::Pane(_) => PathBuf::from(&path),
+                RecordTarget::Window(_) => per_pane(Path::new(&path), pane),
+            };
+            let file = std::fs::File::create(&path).map_err(|e| failed(e.into()))?;
+            let header = Header {
+                timestamp: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).ok().map(|d| d.as_secs()),
+                title: Some(p.title.clone()).filter(|t| !t.is_empty()),
+                ..Header::new(p.size.cols, p.size.rows)
+            };
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(record(pty.subscribe(), rx, tokio::fs::File::from_std(file), header));
+            self.recordings.insert(pane, tx);
+        }
+        Ok(())
+    }
+
+    pub(super) fn record_resize(&self, pane: PaneId, size: TermSize) {
+        if let Some(tx) = self.recordings.get(&pane) {
+            let _ = tx.send(Cue::Resize(size));
+        }
+    }
+
+    /// Carry `pane`'s recording, if any, over to its freshly spawned process.
+    pub(super) fn rerecord(&mut self, pane: PaneId) {
+        let Some(tx) = self.recordings.get(&pane) else {
+            return;
+        };
+        let Some(pty) = self.state.pane(pane).and_then(|p| p.pty()) else {
+            return;
+        };
+        if tx.send(Cue::Retap(pty.subscribe())).is_err() {
+            self.recordings.remove(&pane);
+        }
+    }
+
+    /// Finish `pane`'s recording, if any, once its process has written its last output.
+    pub(super) fn end_recording(&mut self, pane: PaneId) {
+        if let Some(tx) = self.recordings.remove(&pane) {
+            let _ = tx.send(Cue::Close);
+        }
+    }
+}
+
+/// `dir/rec.cast` becomes `dir/rec-<pane>.cast`.
+fn per_pane(path: &Path, pane: PaneId) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}-{pane}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{pane}"),
+    };
+    path.with_file_name(name)
+}
+
+/// Runs until the recording is stopped, or the pane is gone and its last output recorded.
+async fn record(rx: OutputRx, mut cues: mpsc::UnboundedReceiver<Cue>, mut file: tokio::fs::File, header: Header) {
+    let start = Instant::now();
+    let mut enc = Encoder::new();
+    let mut line = Some(enc.header(&header));
+    // `None` between a process ending and the next one starting
+    let mut rx = Some(rx);
+    let mut closing = false;
+    loop {
+        if let Some(l) = line.take() {
+            if let Err(e) = file.write_all(l.as_bytes()).await {
+                rustlog::warn!("recording: {e}");
+                break;
+            }
+        }
+        line = tokio::select! {
+            chunk = async { rx.as_mut()?.recv().await }, if rx.is_some() => match chunk {
+                Some(chunk) => enc.output(start.elapsed(), &chunk),
+                None if closing => break,
+                None => {
+                    rx = None;
+                    None
+                }
+            },
+            cue = cues.recv(), if !closing => match cue {
+                Some(Cue::Resize(s)) => Some(enc.resize(start.elapsed(), s.cols, s.rows)),
+                Some(Cue::Retap(next)) => {
+                    rx = Some(next);
+                    None
+                }
+                Some(Cue::Close) if rx.is_some() => {
+                    closing = true;
+                    None
+                }
+                // stopped, or closed with no process left to hear from
+                Some(Cue::Close) | None => break,
+            },
+        };
+    }
+    let _ = file.flush().await;
+}
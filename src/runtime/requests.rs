@@ -0,0 +1,650 @@
+use super::{Core, access::denied, failed, not_found};
+use crate::ipc::proto::{
+    BufferLite, DetachTarget, ErrorCode, Event, GrepMatch, GrepScope, KillTarget, PolicyTarget, Request, Response,
+    SessionLite, StateScope,
+};
+use crate::pty::{Backpressure, Program, PtyConfig, SigTarget};
+use crate::server::{
+    acl::Role,
+    pane::{KILL_GRACE, Pane, PaneId, TermSize},
+    peer::{Attachment, PeerId},
+    session::SessionId,
+    window::WindowId,
+};
+use serde_json::json;
+use std::{ffi::OsString, path::PathBuf, time::Duration};
+
+pub(crate) const DEFAULT_SIZE: TermSize = TermSize::new(80, 24);
+
+impl Core {
+    pub(super) async fn handle(&mut self, peer: PeerId, req: Request) -> Response {
+        match req {
+            Request::CreateSession { name } => {
+                let id = self.state.new_session(name.unwrap_or_default());
+                if let Some(s) = self.state.session_mut(id).filter(|s| s.name.is_empty()) {
+                    s.name = id.to_string();
+                }
+                Response::SessionCreated { session: id }
+            }
+            // only the sessions the peer may see
+            Request::ListSessions => Response::Sessions {
+                items: self
+                    .state
+                    .sessions()
+                    .filter(|&(&id, _)| self.role(peer, id).is_some())
+                    .map(|(&id, s)| SessionLite { id, name: Some(s.name.clone()) })
+                    .collect(),
+            },
+            Request::CreateWindow { session, title } => match self.create_window(session, title) {
+                Ok(window) => Response::WindowCreated { window },
+                Err(resp) => resp,
+            },
+            Request::SpawnPane { session, window, title, cwd, cwd_from, argv, env, term, shell } => {
+                let cwd = cwd.map(PathBuf::from).or_else(|| cwd_from.and_then(|p| self.current_path(p)));
+                let Some(s) = self.state.session(session) else {
+                    return not_found("session");
+                };
+                if let Some((name, _)) = env.iter().find(|(name, _)| !valid_var(name)) {
+                    return invalid_var(name);
+                }
+                let mut vars: Vec<(OsString, OsString)> = s
+                    .environment
+                    .iter()
+                    .chain(env.iter().map(|(k, v)| (k, v)))
+                    .map(|(k, v)| (k.into(), v.into()))
+                    .collect();
+                vars.extend(shell.iter().map(|sh| ("SHELL".into(), sh.into())));
+                let program = match shell {
+                    _ if !argv.is_empty() => Program::Argv { argv: argv.into_iter().map(OsString::from).collect() },
+                    Some(sh) => Program::Argv { argv: vec![sh.into()] },
+                    None => Program::Shell,
+                };
+                let cfg = PtyConfig { cols: DEFAULT_SIZE.cols, rows: DEFAULT_SIZE.rows, cwd, env: vars, term };
+                self.spawn_pane(session, window, title, program, cfg)
+            }
+            Request::Attach { session, window, pane, read_only } => self.attach(peer, session, window, pane, read_only),
+            Request::Detach { target } => self.detach(peer, target),
+            Request::SetBackpressure { policy } => {
+                let Some(p) = self.state.peer_mut(peer) else {
+                    return not_found("peer");
+                };
+                // only a peer that may type gets to hold the program back for everyone
+                if policy == Backpressure::Block && p.attachment.is_some_and(|a| a.read_only) {
+                    return denied("a read-only attachment can't block output".into());
+                }
+                p.backpressure = policy;
+                // a fresh tap with the new policy replaces the current one
+                let pane = p.attachment.map(|a| a.pane);
+                if let Some(pane) = pane {
+                    if self.state.pane_mut(pane).is_some_and(|p| p.set_backpressure(peer, policy)) {
+                        self.pump(pane);
+                    }
+                }
+                Response::Ok
+            }
+            Request::SetOutputRate { fps, diffs } => {
+                let Some(p) = self.state.peer_mut(peer) else {
+                    return not_found("peer");
+                };
+                p.fps = fps.unwrap_or(p.fps);
+                p.diffs = diffs.unwrap_or(p.diffs);
+                let pane = p.attachment.map(|a| a.pane);
+                if let Some(pane) = pane {
+                    if self.state.pane_mut(pane).is_some_and(|p| p.retap(peer)) {
+                        self.pump(pane);
+                    }
+                }
+                Response::Ok
+            }
+            Request::Kill { .. } => unreachable!("kills are answered from their own task in Core::run"),
+            Request::GetState { scope } => Response::State { json: self.state_json(scope) },
+            Request::Input { pane, data } => {
+                self.claim_input(peer, pane);
+                let p = match self.pane_or_err(pane) {
+                    Ok(p) => p,
+                    Err(resp) => return resp,
+                };
+                match p.write_from(peer, &data) {
+                    Ok(_) => Response::Ok,
+                    Err(e) => failed(e),
+                }
+            }
+            Request::RequestInput { pane, force } => self.request_input(peer, pane, force),
+            Request::GiveInput { pane, to } => self.give_input(peer, pane, to),
+            Request::SetInputMode { pane, mode } => match self.input_change(pane, |p| p.set_input_mode(mode)) {
+                Some(()) => Response::Ok,
+                None => not_found("pane"),
+            },
+            Request::Resize { pane, cols, rows } => {
+                if cols == 0 || rows == 0 {
+                    return Response::Err { code: ErrorCode::InvalidArgs, msg: "zero size".into() };
+                }
+                let size = TermSize::new(cols, rows);
+                // watchers follow the pane's size rather than set it
+                if self.state.pane(pane).is_some_and(|p| p.is_read_only(peer)) {
+                    return Response::Ok;
+                }
+                match self.pane_or_err(pane).map(|p| p.resize(size)) {
+                    Ok(Ok(())) => {
+                        self.record_resize(pane, size);
+                        Response::Ok
+                    }
+                    Ok(Err(e)) => failed(e),
+                    Err(resp) => resp,
+                }
+            }
+            Request::Bind { table, key, binding } => {
+                self.state.keys_mut().bind(&table, key, binding.clone());
+                self.broadcast(None, |_| {
+                    Some(Event::KeyBound { table: table.clone(), key, binding: Some(binding.clone()) })
+                });
+                Response::Ok
+            }
+            Request::Unbind { table, key } => {
+                if self.state.keys_mut().unbind(&table, key).is_none() {
+                    return not_found("binding");
+                }
+                self.broadcast(None, |_| Some(Event::KeyBound { table: table.clone(), key, binding: None }));
+                Response::Ok
+            }
+            Request::Capture { pane, history } => {
+                let Some(p) = self.state.pane(pane) else {
+                    return not_found("pane");
+                };
+                let screen = p.screen();
+                let (row, col) = screen.cursor();
+                let (row, col) = (usize::from(row), usize::from(col));
+                if history {
+                    Response::Captured { lines: screen.all_lines(), cursor: (row + screen.history_len(), col) }
+                } else {
+                    Response::Captured { lines: screen.lines(), cursor: (row, col) }
+                }
+            }
+            Request::SetBuffer { name, data } => Response::BufferSet { name: self.state.buffers_mut().set(name, data) },
+            Request::ListBuffers => Response::Buffers {
+                items: self
+                    .state
+                    .buffers()
+                    .iter()
+                    .map(|b| BufferLite { name: b.name.clone(), size: b.data.len(), automatic: b.automatic })
+                    .collect(),
+            },
+            Request::ShowBuffer { name } => match self.state.buffers().get(name.as_deref()) {
+                Some(b) => Response::Buffer { name: b.name.clone(), data: b.data.clone() },
+                None => not_found("buffer"),
+            },
+            Request::DeleteBuffer { name } => match self.state.buffers_mut().delete(name.as_deref()) {
+                Some(_) => Response::Ok,
+                None => not_found("buffer"),
+            },
+            Request::PasteBuffer { pane, name, delete } => self.paste_buffer(peer, pane, name, delete).await,
+            Request::Signal { pane, signal, foreground } => match self.state.pane(pane) {
+                Some(p) => {
+                    let target = if foreground { SigTarget::Foreground } else { SigTarget::Group };
+                    p.signal(signal, target).map_or_else(failed, |()| Response::Ok)
+                }
+                None => not_found("pane"),
+            },
+            Request::Grep { pattern, scope, context, ignore_case } => {
+                match regex::RegexBuilder::new(&pattern).case_insensitive(ignore_case).build() {
+                    Ok(re) => Response::Matches { items: self.grep(&re, scope, context) },
+                    Err(e) => Response::Err { code: ErrorCode::InvalidArgs, msg: e.to_string() },
+                }
+            }
+            Request::SetClipboardPolicy { session, policy } => match self.state.session_mut(session) {
+                Some(s) => {
+                    s.clipboard = policy;
+                    Response::Ok
+                }
+                None => not_found("session"),
+            },
+            Request::RespawnPane { pane, argv, cwd, kill } => {
+                let program =
+                    (!argv.is_empty()).then(|| Program::Argv { argv: argv.into_iter().map(OsString::from).collect() });
+                match self.state.pane(pane) {
+                    Some(_) => match self.respawn(pane, program, cwd.map(PathBuf::from), kill) {
+                        Ok(()) => Response::Ok,
+                        Err(e) => failed(e),
+                    },
+                    None => not_found("pane"),
+                }
+            }
+            Request::SetAutoRespawn { pane, policy } => match self.state.pane_mut(pane) {
+                Some(p) => {
+                    p.set_auto_respawn(policy);
+                    Response::Ok
+                }
+                None => not_found("pane"),
+            },
+            Request::SetRemainOnExit { target, policy } => {
+                let slot = match target {
+                    PolicyTarget::Session(sid) => self.state.session_mut(sid).map(|s| &mut s.remain_on_exit),
+                    PolicyTarget::Window(wid) => self
+                        .state
+                        .locate_window(wid)
+                        .and_then(|sid| self.state.session_mut(sid)?.window_mut(wid))
+                        .map(|w| &mut w.remain_on_exit),
+                    PolicyTarget::Pane(pid) => self.state.pane_mut(pid).map(|p| &mut p.remain_on_exit),
+                };
+                match slot {
+                    Some(slot) => {
+                        *slot = policy;
+                        Response::Ok
+                    }
+                    None => not_found("target"),
+                }
+            }
+            Request::PipePane { pane, target, plain, input } => match self.pipe_pane(pane, target, plain, input) {
+                Ok(()) => Response::Ok,
+                Err(resp) => resp,
+            },
+            Request::Record { target, path } => match self.record(target, path) {
+                Ok(()) => Response::Ok,
+                Err(resp) => resp,
+            },
+            Request::SetEnvironment { session, name, value } => match self.state.session_mut(session) {
+                Some(_) if !valid_var(&name) => invalid_var(&name),
+                Some(s) => {
+                    match value {
+                        Some(v) => s.environment.insert(name, v),
+                        None => s.environment.remove(&name),
+                    };
+                    Response::Ok
+                }
+                None => not_found("session"),
+            },
+            Request::ShowEnvironment { session } => match self.state.session(session) {
+                Some(s) => {
+                    Response::Environment { vars: s.environment.iter().map(|(k, v)| (k.clone(), v.clone())).collect() }
+                }
+                None => not_found("session"),
+            },
+            Request::Grant { session, grantee, role } => match self.state.session_mut(session) {
+                Some(s) => {
+                    s.acl.grant(grantee, role);
+                    rustlog::info!("session {session}: granted {role} to {grantee}");
+                    self.enforce_access(session);
+                    Response::Ok
+                }
+                None => not_found("session"),
+            },
+            Request::Revoke { session, grantee } => {
+                match self.state.session_mut(session).map(|s| s.acl.revoke(grantee)) {
+                    Some(true) => {
+                        rustlog::info!("session {session}: revoked access of {grantee}");
+                        self.enforce_access(session);
+                        Response::Ok
+                    }
+                    Some(false) => not_found("grant"),
+                    None => not_found("session"),
+                }
+            }
+            Request::ShowAccess { session } => match self.state.session(session) {
+                Some(s) => Response::Access { grants: s.acl.grants().collect() },
+                None => not_found("session"),
+            },
+            Request::RenameWindow { window, name } => {
+                let Some(w) =
+                    self.state.locate_window(window).and_then(|sid| self.state.session_mut(sid)?.window_mut(window))
+                else {
+                    return not_found("window");
+                };
+                match name {
+                    Some(name) => {
+                        w.name = name;
+                        w.set_automatic_name(false);
+                    }
+                    None => {
+                        w.set_automatic_name(true);
+                        w.update_name();
+                    }
+                }
+                self.titles.insert(window);
+                Response::Ok
+            }
+        }
+    }
+
+    /// Write a buffer into `pane` as if typed by its input owner (or `peer` if it holds input).
+    async fn paste_buffer(&mut self, peer: PeerId, pane: PaneId, name: Option<String>, delete: bool) -> Response {
+        let Some(buf) = self.state.buffers().get(name.as_deref()) else {
+            return not_found("buffer");
+        };
+        let (name, mut data) = (buf.name.clone(), buf.data.clone());
+        let p = match self.pane_or_err(pane) {
+            Ok(p) => p,
+            Err(resp) => return resp,
+        };
+        if p.screen().bracketed_paste() {
+            // the payload must not be able to end the paste early
+            const END: &[u8] = b"\x1b[201~";
+            while let Some(i) = data.windows(END.len()).position(|w| w == END) {
+                data.drain(i..i + END.len());
+            }
+            data = [b"\x1b[200~".as_slice(), &data, END].concat();
+        }
+        let who = p.input_owner().unwrap_or(peer);
+        if let Err(e) = p.write_from(who, &data) {
+            return failed(e);
+        }
+        if delete {
+            self.state.buffers_mut().delete(Some(&name));
+        }
+        Response::Ok
+    }
+
+    fn create_window(&mut self, session: SessionId, title: Option<String>) -> Result<WindowId, Response> {
+        if self.state.session(session).is_none() {
+            return Err(not_found("session"));
+        }
+        let id = self.state.new_window(session, title.unwrap_or_default()).map_err(failed)?;
+        if let Some(w) = self.state.session_mut(session).and_then(|s| s.window_mut(id)) {
+            // a name given at creation counts as an explicit rename
+            if w.name.is_empty() {
+                w.name = id.to_string();
+            } else {
+                w.set_automatic_name(false);
+            }
+        }
+        Ok(id)
+    }
+
+    /// Fresh read of a pane's foreground cwd.
+    fn current_path(&mut self, pane: PaneId) -> Option<PathBuf> {
+        let p = self.state.pane_mut(pane)?;
+        p.refresh_process();
+        p.process()?.cwd.clone()
+    }
+
+    fn spawn_pane(
+        &mut self,
+        session: SessionId,
+        window: Option<WindowId>,
+        title: Option<String>,
+        program: Program,
+        mut cfg: PtyConfig,
+    ) -> Response {
+        let window = match window {
+            Some(w) if self.state.session(session).is_some_and(|s| s.window(w).is_some()) => w,
+            Some(_) => return not_found("window"),
+            None => match self.create_window(session, title.clone()) {
+                Ok(w) => w,
+                Err(resp) => return resp,
+            },
+        };
+        let pane = match self.state.new_pane(session, window, title.unwrap_or_default(), DEFAULT_SIZE) {
+            Ok(id) => id,
+            Err(e) => return failed(e),
+        };
+        if let Some(ref socket) = self.socket {
+            let splicer = format!("{},{},{session}", socket.display(), std::process::id());
+            cfg.env.push(("SPLICER".into(), splicer.into()));
+        }
+        cfg.env.push(("SPLICER_PANE".into(), pane.to_string().into()));
+        let (notify, spawner) = (self.notice_tx.clone(), self.spawner.clone());
+        let spawned = self.state.pane_mut(pane).map(|p| {
+            p.set_notify(notify);
+            if let Some(spawner) = spawner {
+                p.set_spawner(spawner);
+            }
+            p.spawn(program, cfg)
+        });
+        if let Some(Err(e)) = spawned {
+            if let Some(w) = self.state.session_mut(session).and_then(|s| s.window_mut(window)) {
+                w.remove_pane(pane);
+            }
+            return failed(e);
+        }
+        self.watch_exit(pane);
+        self.pump(pane);
+        Response::PaneSpawned { session, window, pane }
+    }
+
+    fn attach(
+        &mut self,
+        peer: PeerId,
+        session: SessionId,
+        window: Option<WindowId>,
+        pane: Option<PaneId>,
+        read_only: bool,
+    ) -> Response {
+        let Some(s) = self.state.session(session) else {
+            return not_found("session");
+        };
+        let Some(window) = window.or(s.focused()) else {
+            return not_found("window");
+        };
+        let Some(w) = s.window(window) else {
+            return not_found("window");
+        };
+        let Some(pane) = pane.or(w.focused()).filter(|&p| w.pane(p).is_some()) else {
+            return not_found("pane");
+        };
+
+        let read_only = read_only || self.role(peer, session) < Some(Role::Input);
+        self.detach_all(peer);
+        let backpressure = self.state.peer(peer).map(|p| p.backpressure).unwrap_or_default();
+        if let Some(s) = self.state.session_mut(session) {
+            s.attach_peer(peer);
+        }
+        if let Some(Err(e)) = self.input_change(pane, |p| p.attach_peer(peer, backpressure, read_only)) {
+            return failed(e);
+        }
+        if let Some(p) = self.state.peer_mut(peer) {
+            p.attachment = Some(Attachment { session, window, pane, read_only });
+        }
+        self.pump(pane);
+
+        // replay server-side bindings so the client's tables match
+        for (table, keys) in self.state.keys().tables() {
+            for (&key, binding) in keys {
+                self.emit(peer, Event::KeyBound { table: table.clone(), key, binding: Some(binding.clone()) });
+            }
+        }
+        self.broadcast(Some(session), |_| Some(Event::PeerAttached { peer, session, window, pane }));
+        Response::Attached { session, window, pane }
+    }
+
+    fn detach(&mut self, peer: PeerId, target: Option<DetachTarget>) -> Response {
+        let Some(at) = self.state.peer(peer).and_then(|p| p.attachment) else {
+            return Response::Err { code: ErrorCode::NotAttached, msg: "peer is not attached".into() };
+        };
+        let hit = match target {
+            None => true,
+            Some(DetachTarget::Session(s)) => s == at.session,
+            Some(DetachTarget::Window(w)) => w == at.window,
+            Some(DetachTarget::Pane(p)) => p == at.pane,
+        };
+        if !hit {
+            return Response::Err { code: ErrorCode::NotAttached, msg: "peer is not attached to target".into() };
+        }
+        self.detach_all(peer);
+        self.broadcast(Some(at.session), |_| Some(Event::PeerDetached { peer }));
+        Response::Detached
+    }
+
+    /// Drop whatever attachment `peer` holds; dropping the tap ends its forwarder.
+    pub(super) fn detach_all(&mut self, peer: PeerId) {
+        let Some(at) = self.state.peer_mut(peer).and_then(|p| p.attachment.take()) else {
+            return;
+        };
+        self.input_change(at.pane, |p| p.detach_peer(peer));
+        self.unpump(peer, at.pane);
+        if let Some(s) = self.state.session_mut(at.session) {
+            s.detach_peer(peer);
+        }
+    }
+
+    /// Detach and remove the target's panes now; the returned job shuts their processes down in
+    /// parallel and reports the stage that ended each one.
+    pub(super) fn kill(
+        &mut self,
+        target: KillTarget,
+        force: bool,
+        grace: Option<Duration>,
+    ) -> Result<impl Future<Output = Response> + Send + 'static, Response> {
+        let mut panes: Vec<Pane> = Vec::new();
+        match target {
+            KillTarget::Session(sid) => {
+                let Some(mut s) = self.state.remove_session(sid) else {
+                    return Err(not_found("session"));
+                };
+                let wids: Vec<WindowId> = s.windows().map(|(&w, _)| w).collect();
+                for wid in wids {
+                    if let Some(mut w) = s.remove_window(wid) {
+                        let pids: Vec<PaneId> = w.panes().map(|(&p, _)| p).collect();
+                        panes.extend(pids.into_iter().filter_map(|p| w.remove_pane(p)));
+                    }
+                }
+            }
+            KillTarget::Window(wid) => {
+                let Some(sid) = self.state.locate_window(wid) else {
+                    return Err(not_found("window"));
+                };
+                if let Some(mut w) = self.state.session_mut(sid).and_then(|s| s.remove_window(wid)) {
+                    let pids: Vec<PaneId> = w.panes().map(|(&p, _)| p).collect();
+                    panes.extend(pids.into_iter().filter_map(|p| w.remove_pane(p)));
+                }
+            }
+            KillTarget::Pane(pid) => {
+                let Some((sid, wid)) = self.state.locate_pane(pid) else {
+                    return Err(not_found("pane"));
+                };
+                panes.extend(
+                    self.state.session_mut(sid).and_then(|s| s.window_mut(wid)).and_then(|w| w.remove_pane(pid)),
+                );
+            }
+        }
+
+        let grace = grace.unwrap_or(KILL_GRACE);
+        let mut jobs = tokio::task::JoinSet::new();
+        for p in panes {
+            let peers: Vec<PeerId> = p.attached().copied().collect();
+            for peer in peers {
+                if let Some(pr) = self.state.peer_mut(peer) {
+                    pr.attachment = None;
+                }
+                self.unpump(peer, p.id);
+            }
+            // the pipe and recording finish with the process's last output
+            self.pipes.remove(&p.id);
+            self.end_recording(p.id);
+            let (id, job) = (p.id, p.shutdown(force, grace));
+            jobs.spawn(async move { (id, job.await) });
+        }
+        Ok(async move {
+            let mut panes = jobs.join_all().await;
+            panes.sort_by_key(|&(id, _)| id);
+            Response::Killed { panes }
+        })
+    }
+
+    fn grep(&self, re: &regex::Regex, scope: GrepScope, context: usize) -> Vec<GrepMatch> {
+        let mut out = Vec::new();
+        for (&sid, s) in self.state.sessions() {
+            if matches!(scope, GrepScope::Session(x) if x != sid) {
+                continue;
+            }
+            for (&wid, w) in s.windows() {
+                if matches!(scope, GrepScope::Window(x) if x != wid) {
+                    continue;
+                }
+                for (&pid, p) in w.panes() {
+                    let lines = p.screen().all_lines();
+                    for (i, text) in lines.iter().enumerate().filter(|(_, l)| re.is_match(l)) {
+                        out.push(GrepMatch {
+                            session: sid,
+                            window: wid,
+                            pane: pid,
+                            line: i + 1,
+                            text: text.clone(),
+                            before: lines[i.saturating_sub(context)..i].to_vec(),
+                            after: lines[i + 1..(i + 1).saturating_add(context).min(lines.len())].to_vec(),
+                        });
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    fn state_json(&self, scope: StateScope) -> serde_json::Value {
+        match scope {
+            StateScope::Sessions => json!(
+                self.state
+                    .sessions()
+                    .map(|(id, s)| json!({
+                        "id": id.to_string(),
+                        "name": s.name,
+                        "windows": s.windows().count(),
+                        "focused": s.focused().map(|w| w.to_string()),
+                        "clipboard": { "read": s.clipboard.read, "write": s.clipboard.write },
+                    }))
+                    .collect::<Vec<_>>()
+            ),
+            StateScope::Windows { session } => json!(
+                self.state
+                    .sessions()
+                    .filter(|(id, _)| session.is_none_or(|s| s == **id))
+                    .flat_map(|(sid, s)| s.windows().map(move |(id, w)| json!({
+                        "id": id.to_string(),
+                        "session": sid.to_string(),
+                        "name": w.name,
+                        "panes": w.panes().count(),
+                        "focused": w.focused().map(|p| p.to_string()),
+                    })))
+                    .collect::<Vec<_>>()
+            ),
+            StateScope::Panes { window } => json!(
+                self.state
+                    .sessions()
+                    .flat_map(|(_, s)| s.windows())
+                    .filter(|(id, _)| window.is_none_or(|w| w == **id))
+                    .flat_map(|(wid, w)| w.panes().map(move |(id, p)| json!({
+                        "id": id.to_string(),
+                        "window": wid.to_string(),
+                        "title": p.title,
+                        "size": p.size.to_string(),
+                        "running": p.is_running(),
+                        "auto_respawn": p.auto_respawn().is_some(),
+                        "exit_status": p.exit_status().map(ToString::to_string),
+                        "piped": self.pipes.get(id).is_some_and(|t| t.is_running()),
+                        "recording": self.recordings.get(id).is_some_and(|t| !t.is_closed()),
+                        "pid": p.process().map(|i| i.pid),
+                        "current_command": p.process().map(|i| &i.command),
+                        "current_path": p.process().and_then(|i| i.cwd.as_ref()),
+                        "peers": p.attached().map(|x| x.to_string()).collect::<Vec<_>>(),
+                        "input_mode": p.input_mode().to_string(),
+                        "input_owner": p.input_owner().map(|x| x.to_string()),
+                    })))
+                    .collect::<Vec<_>>()
+            ),
+            StateScope::Peers => json!(
+                self.state
+                    .peers()
+                    .map(|(id, p)| json!({
+                        "id": id.to_string(),
+                        "name": p.name,
+                        "pane": p.attachment.map(|a| a.pane.to_string()),
+                        "backpressure": p.backpressure.to_string(),
+                        "fps": p.fps,
+                        "diffs": p.diffs,
+                        "read_only": p.attachment.map(|a| a.read_only),
+                        "uid": p.cred.map(|c| c.uid),
+                        "gid": p.cred.map(|c| c.gid),
+                        "pid": p.cred.and_then(|c| c.pid),
+                    }))
+                    .collect::<Vec<_>>()
+            ),
+        }
+    }
+}
+
+/// Whether `name` can be put in a process environment.
+fn valid_var(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['=', '\0'])
+}
+
+fn invalid_var(name: &str) -> Response {
+    Response::Err { code: ErrorCode::InvalidArgs, msg: format!("invalid variable name: {name:?}") }
+}
@@ -0,0 +1,229 @@
+use super::Core;
+use crate::ipc::proto::Event;
+use crate::pty::{Backpressure, OutputRx};
+use crate::server::{pane::Epilogue, pane::PaneId, peer::PeerId};
+use crate::term::{Frame, Osc52Filter, Screen};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{Instant, sleep, sleep_until};
+
+/// Largest `PtyOutput` chunk sent to a peer.
+const FRAME_MAX: usize = 64 * 1024;
+/// How long a screen-diff peer waits after output stops before checking the screen once more,
+/// for output the screen model had not taken in yet.
+const SETTLE: Duration = Duration::from_millis(20);
+
+impl Core {
+    /// Move any fresh per-peer taps of `pane` into forwarder tasks.
+    pub(super) fn pump(&mut self, pane: PaneId) {
+        let Some(p) = self.state.pane(pane) else {
+            return;
+        };
+        let peers: Vec<(PeerId, u16, bool)> =
+            p.attached().filter_map(|&peer| self.state.peer(peer).map(|pr| (peer, pr.fps, pr.diffs))).collect();
+        let Some(p) = self.state.pane_mut(pane) else {
+            return;
+        };
+        for (peer, fps, diffs) in peers {
+            let (Some(rx), Some(tx)) = (p.take_tap(peer), self.links.get(&peer).cloned()) else {
+                continue;
+            };
+            let interval = if fps == 0 { Duration::ZERO } else { Duration::from_secs(1) / u32::from(fps) };
+            let (screen, end) = (p.shared_screen(), p.epilogue());
+            let task = match p.backpressure(peer).unwrap_or_default() {
+                _ if diffs => tokio::spawn(forward_diffs(pane, rx, tx, screen, end, interval)),
+                Backpressure::Drop => tokio::spawn(forward(pane, Frames::new(rx, interval), tx, screen, end)),
+                Backpressure::Block => tokio::spawn(forward_blocking(pane, Frames::new(rx, interval), tx, end)),
+            }
+            .abort_handle();
+            if let Some(old) = self.forwarders.insert((peer, pane), task) {
+                old.abort();
+            }
+        }
+    }
+
+    /// Stop forwarding `pane` output to `peer`.
+    pub(super) fn unpump(&mut self, peer: PeerId, pane: PaneId) {
+        if let Some(task) = self.forwarders.remove(&(peer, pane)) {
+            task.abort();
+        }
+    }
+}
+
+/// Coalesces a tap into frames of at most [`FRAME_MAX`] bytes, one per `interval` at most.
+/// Clipboard sequences are stripped here; they reach clients as [`Event::Clipboard`] instead.
+struct Frames {
+    rx: OutputRx,
+    osc52: Osc52Filter,
+    /// Output that did not fit the last frame.
+    carry: Vec<u8>,
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl Frames {
+    fn new(rx: OutputRx, interval: Duration) -> Self {
+        Self { rx, osc52: Osc52Filter::new(), carry: Vec::new(), interval, last: None }
+    }
+
+    /// Wait for output, then gather whatever else arrives until the frame is due or full.
+    /// `None` once the output ended and everything was handed out.
+    async fn next(&mut self) -> Option<Vec<u8>> {
+        let mut frame = std::mem::take(&mut self.carry);
+        while frame.is_empty() {
+            let chunk = self.rx.recv().await?;
+            frame = self.osc52.filter(&chunk);
+        }
+        if frame.len() > FRAME_MAX {
+            self.carry = frame.split_off(FRAME_MAX);
+        }
+        let due = self.last.map_or_else(Instant::now, |t| t + self.interval);
+        while self.carry.is_empty() {
+            tokio::select! {
+                _ = sleep_until(due) => break,
+                chunk = self.rx.recv() => match chunk {
+                    Some(chunk) => {
+                        let chunk = self.osc52.filter(&chunk);
+                        let room = FRAME_MAX - frame.len();
+                        frame.extend_from_slice(&chunk[..room.min(chunk.len())]);
+                        self.carry.extend_from_slice(&chunk[room.min(chunk.len())..]);
+                    }
+                    None => break,
+                },
+            }
+        }
+        sleep_until(due).await;
+        self.last = Some(Instant::now());
+        Some(frame)
+    }
+
+    /// Forget everything queued or held back, after a redraw made it moot.
+    fn skip(&mut self) {
+        while self.rx.try_recv().is_some() {}
+        self.rx.take_dropped();
+        self.carry.clear();
+        self.osc52 = Osc52Filter::new();
+    }
+}
+
+/// Never waits on the peer: output it has no room for is dropped, and once there is room again it
+/// gets a [`Event::StreamDropNotice`] and a redraw of the screen as it is by then. Only the
+/// [`Epilogue`], with the program already gone, waits for room.
+async fn forward(pane: PaneId, mut frames: Frames, tx: mpsc::Sender<Event>, screen: Arc<Mutex<Screen>>, end: Epilogue) {
+    // drawn before this peer attached
+    let mut shown = end.borrow().is_some();
+    let (mut lagging, mut eof) = (false, false);
+    loop {
+        if lagging {
+            tokio::select! {
+                permits = tx.reserve_many(2) => {
+                    let Ok(mut permits) = permits else {
+                        break;
+                    };
+                    frames.skip();
+                    let chunk = {
+                        let screen = screen.lock().unwrap_or_else(|e| e.into_inner());
+                        shown |= end.borrow().is_some();
+                        screen.snapshot()
+                    };
+                    for ev in [Event::StreamDropNotice { pane }, Event::PtyOutput { pane, chunk }] {
+                        if let Some(permit) = permits.next() {
+                            permit.send(ev);
+                        }
+                    }
+                    lagging = false;
+                }
+                // the redraw covers this; after EOF, the redraw is still owed
+                chunk = frames.rx.recv(), if !eof => eof = chunk.is_none(),
+            }
+            if eof && !lagging {
+                break;
+            }
+            continue;
+        }
+        let Some(chunk) = frames.next().await else {
+            eof = true;
+            break;
+        };
+        if frames.rx.take_dropped() > 0 {
+            lagging = true;
+            continue;
+        }
+        match tx.try_send(Event::PtyOutput { pane, chunk }) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(_)) => lagging = true,
+            Err(mpsc::error::TrySendError::Closed(_)) => return,
+        }
+    }
+    if eof && !shown {
+        send_epilogue(pane, &tx, end).await;
+    }
+}
+
+/// Waits on the peer; a full tap then holds the pane's program back (see [`Backpressure::Block`]).
+async fn forward_blocking(pane: PaneId, mut frames: Frames, tx: mpsc::Sender<Event>, end: Epilogue) {
+    let shown = end.borrow().is_some();
+    while let Some(chunk) = frames.next().await {
+        if tx.send(Event::PtyOutput { pane, chunk }).await.is_err() {
+            return;
+        }
+    }
+    if !shown {
+        send_epilogue(pane, &tx, end).await;
+    }
+}
+
+/// After the last output, the exit line a kept pane draws under it.
+async fn send_epilogue(pane: PaneId, tx: &mpsc::Sender<Event>, mut end: Epilogue) {
+    let line = match end.wait_for(Option::is_some).await {
+        Ok(line) => line.as_deref().map(<[u8]>::to_vec).unwrap_or_default(),
+        Err(_) => return,
+    };
+    let _ = tx.send(Event::PtyOutput { pane, chunk: line }).await;
+}
+
+/// Sends the rows of the screen model that changed instead of the output itself, at most once
+/// per `interval`. A slow peer just sees fewer, larger updates; nothing is ever dropped.
+async fn forward_diffs(
+    pane: PaneId,
+    mut rx: OutputRx,
+    tx: mpsc::Sender<Event>,
+    screen: Arc<Mutex<Screen>>,
+    mut end: Epilogue,
+    interval: Duration,
+) {
+    let mut shown: Option<Frame> = None;
+    let mut next = Instant::now();
+    // after output-driven updates, one more look once things settle
+    let mut settle = false;
+    loop {
+        let eof = tokio::select! {
+            chunk = rx.recv() => chunk.is_none(),
+            _ = sleep(SETTLE.max(interval)), if settle => false,
+        };
+        if eof {
+            // the screen model has all the output once the exit line is on it, or it never comes
+            let _ = end.wait_for(Option::is_some).await;
+        } else {
+            sleep_until(next).await;
+        }
+        while rx.try_recv().is_some() {}
+        rx.take_dropped();
+        let Ok(permit) = tx.reserve().await else {
+            break;
+        };
+        let frame = screen.lock().unwrap_or_else(|e| e.into_inner()).frame();
+        let chunk = frame.diff(shown.as_ref());
+        let changed = !chunk.is_empty();
+        if changed {
+            permit.send(Event::PtyOutput { pane, chunk });
+            shown = Some(frame);
+        }
+        if eof {
+            break;
+        }
+        settle = changed;
+        next = Instant::now() + interval;
+    }
+}
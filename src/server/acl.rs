@@ -0,0 +1,105 @@
+use super::peer::{PeerCred, PeerId};
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What a peer may do in a session; each role includes the ones before it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Attach and watch; capture and search the session's panes.
+    View,
+    /// Also type into panes, resize them and signal their programs.
+    Input,
+    /// Also change the session: spawn, kill and respawn panes, set its policies and environment,
+    /// and grant or revoke access.
+    Admin,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::View => "view",
+            Self::Input => "input",
+            Self::Admin => "admin",
+        })
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "view" => Ok(Self::View),
+            "input" => Ok(Self::Input),
+            "admin" => Ok(Self::Admin),
+            _ => Err(Error::UserInput(format!("role must be view, input or admin: {s}"))),
+        }
+    }
+}
+
+/// Who a grant is for: one connection, or every connection from a user.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grantee {
+    Peer(PeerId),
+    Uid(u32),
+}
+
+impl std::fmt::Display for Grantee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Peer(p) => write!(f, "peer:{p}"),
+            Self::Uid(u) => write!(f, "uid:{u}"),
+        }
+    }
+}
+
+/// `uid:N`, `peer:N`, or a bare uid.
+impl std::str::FromStr for Grantee {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let bad = || Error::UserInput(format!("expected uid:N or peer:N: {s}"));
+        match s.split_once(':').unwrap_or(("uid", s)) {
+            ("uid", n) => n.parse().map(Self::Uid).map_err(|_| bad()),
+            ("peer", n) => n.parse().ok().and_then(PeerId::new).map(Self::Peer).ok_or_else(bad),
+            _ => Err(bad()),
+        }
+    }
+}
+
+/// A session's grants to peers other than the server's owner, who may always do everything.
+#[derive(Debug, Clone, Default)]
+pub struct Acl {
+    grants: BTreeMap<Grantee, Role>,
+}
+
+impl Acl {
+    /// Give `who` exactly `role`, replacing an earlier grant.
+    pub fn grant(&mut self, who: Grantee, role: Role) {
+        self.grants.insert(who, role);
+    }
+
+    /// Whether `who` had a grant.
+    pub fn revoke(&mut self, who: Grantee) -> bool {
+        self.grants.remove(&who).is_some()
+    }
+
+    /// The highest role granted to `peer` itself or to its user.
+    pub fn role(&self, peer: PeerId, cred: Option<&PeerCred>) -> Option<Role> {
+        let by_uid = cred.and_then(|c| self.grants.get(&Grantee::Uid(c.uid)));
+        self.grants.get(&Grantee::Peer(peer)).max(by_uid).copied()
+    }
+
+    /// Whether only the server's owner has access.
+    pub fn is_empty(&self) -> bool {
+        self.grants.is_empty()
+    }
+
+    pub fn grants(&self) -> impl Iterator<Item = (Grantee, Role)> + '_ {
+        self.grants.iter().map(|(&who, &role)| (who, role))
+    }
+
+    /// Drop grants to a peer that disconnected.
+    pub fn forget_peer(&mut self, peer: PeerId) {
+        self.grants.remove(&Grantee::Peer(peer));
+    }
+}
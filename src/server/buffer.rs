@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+
+pub const DEFAULT_LIMIT: usize = 50;
+
+#[derive(Debug, Clone)]
+pub struct PasteBuffer {
+    pub name: String,
+    pub data: Vec<u8>,
+    /// Named by the server (`bufferN`) rather than the user; only these are evicted.
+    pub automatic: bool,
+}
+
+/// Server-side stack of paste buffers, newest first.
+#[derive(Debug)]
+pub struct PasteBuffers {
+    items: VecDeque<PasteBuffer>,
+    next: u64,
+    limit: usize,
+}
+
+impl Default for PasteBuffers {
+    fn default() -> Self {
+        Self { items: VecDeque::new(), next: 0, limit: DEFAULT_LIMIT }
+    }
+}
+
+impl PasteBuffers {
+    /// Replace the buffer called `name`, or push a new automatic buffer when `None`.
+    /// Returns the buffer's name.
+    pub fn set(&mut self, name: Option<String>, data: Vec<u8>) -> String {
+        let buf = match name {
+            Some(name) => {
+                self.items.retain(|b| b.name != name);
+                PasteBuffer { name, data, automatic: false }
+            }
+            None => {
+                let name = format!("buffer{}", self.next);
+                self.next += 1;
+                PasteBuffer { name, data, automatic: true }
+            }
+        };
+        let name = buf.name.clone();
+        self.items.push_front(buf);
+        self.evict();
+        name
+    }
+
+    pub fn top(&self) -> Option<&PasteBuffer> {
+        self.items.front()
+    }
+
+    /// Buffers newest first.
+    pub fn iter(&self) -> impl Iterator<Item = &PasteBuffer> {
+        self.items.iter()
+    }
+
+    /// The buffer called `name`, or the newest one when `None`.
+    pub fn get(&self, name: Option<&str>) -> Option<&PasteBuffer> {
+        match name {
+            Some(name) => self.items.iter().find(|b| b.name == name),
+            None => self.top(),
+        }
+    }
+
+    /// Remove the buffer called `name`, or the newest one when `None`.
+    pub fn delete(&mut self, name: Option<&str>) -> Option<PasteBuffer> {
+        let i = match name {
+            Some(name) => self.items.iter().position(|b| b.name == name)?,
+            None => 0,
+        };
+        self.items.remove(i)
+    }
+
+    fn evict(&mut self) {
+        while self.items.iter().filter(|b| b.automatic).count() > self.limit {
+            if let Some(i) = self.items.iter().rposition(|b| b.automatic) {
+                self.items.remove(i);
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+pub mod acl;
+pub mod buffer;
 pub mod pane;
 pub mod peer;
 pub mod session;
@@ -1,9 +1,33 @@
 use crate::server::peer::PeerId;
+use crate::term::{Notice, Screen};
 use crate::{Error, Result};
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::AbortHandle;
+
+/// How long `kill` without force waits before escalating to SIGKILL.
+pub const KILL_GRACE: Duration = Duration::from_secs(5);
+
+/// Where a pane's screen model reports [`Notice`]s; the server core holds the receiver.
+pub type NoticeTx = mpsc::UnboundedSender<(PaneId, Notice)>;
+
+/// The line a kept pane shows under its exited process's output, e.g. `[exited with code 1]`,
+/// once the screen model has it; closed without one when the pane moves on.
+pub type Epilogue = watch::Receiver<Option<Arc<[u8]>>>;
 
 crate::common::idgen::id_newtype!(PaneId);
 
+/// Restart a pane whenever its program exits. The delay starts at `min` and doubles on every
+/// exit up to `max`; a run that lasted longer than `max` starts over from `min`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AutoRespawn {
+    pub min: Duration,
+    pub max: Duration,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TermSize {
     pub cols: u16,
@@ -21,7 +45,41 @@ impl std::fmt::Display for TermSize {
 }
 
 // PTY is optional at the model level: spawn later when needed.
-use crate::pty::{self, ExitStatus as PtyExit, OutputRx, Program, PtyConfig, PtyHandle, Sig as PtySig};
+use crate::pty::procinfo::{self, ProcInfo};
+use crate::pty::{
+    self, Backpressure, ExitStatus as PtyExit, KillStage, OutputRx, Program, PtyConfig, PtyHandle, Sig as PtySig,
+    SigTarget, Spawner,
+};
+
+/// Who may type into a pane.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum InputMode {
+    /// Only the input owner; others ask for it, and the owner hands it over or doesn't.
+    #[default]
+    Exclusive,
+    /// Every attached peer that isn't read-only, merged in arrival order.
+    Shared,
+}
+
+impl std::fmt::Display for InputMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Exclusive => "exclusive",
+            Self::Shared => "shared",
+        })
+    }
+}
+
+impl std::str::FromStr for InputMode {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "exclusive" => Ok(Self::Exclusive),
+            "shared" => Ok(Self::Shared),
+            _ => Err(Error::UserInput(format!("input mode must be exclusive or shared: {s}"))),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum PaneState {
@@ -32,16 +90,70 @@ pub enum PaneState {
 
 pub type SpawnTarget = Program;
 
+/// What happens to a pane when its program exits. Unset at pane level falls back to the
+/// window's, then the session's, then [`RemainOnExit::Close`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RemainOnExit {
+    /// Remove the pane, and its window or session once empty.
+    #[default]
+    Close,
+    /// Keep the pane with its last screen and an exit status line.
+    Keep,
+    /// Keep the pane only when the program failed or was killed by a signal.
+    OnFailure,
+}
+
+impl RemainOnExit {
+    pub fn keeps(self, status: &PtyExit) -> bool {
+        match self {
+            Self::Close => false,
+            Self::Keep => true,
+            Self::OnFailure => !status.success(),
+        }
+    }
+}
+
+impl std::str::FromStr for RemainOnExit {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "close" | "off" => Ok(Self::Close),
+            "keep" | "on" => Ok(Self::Keep),
+            "on-failure" | "failed" => Ok(Self::OnFailure),
+            _ => Err(Error::UserInput(format!("unknown remain-on-exit policy: {s}"))),
+        }
+    }
+}
+
 pub struct Pane {
     pub id: PaneId,
     pub title: String,
     pub size: TermSize,
+    pub remain_on_exit: Option<RemainOnExit>,
 
     pty: Option<PtyHandle>,
     taps: HashMap<PeerId, OutputRx>,
-    attached: BTreeSet<PeerId>,
+    /// Attached peers and how their taps treat a slow reader.
+    attached: BTreeMap<PeerId, Backpressure>,
+    /// Attached peers that only watch: they never hold input and what they type is dropped.
+    read_only: BTreeSet<PeerId>,
     input_owner: Option<PeerId>,
+    input_mode: InputMode,
     state: PaneState,
+    screen: Arc<Mutex<Screen>>,
+    /// Feeds the screen model from the current process.
+    feed: Option<AbortHandle>,
+    /// Hands the feed its [`Epilogue`], to draw after the last output.
+    epilogue_tx: Option<oneshot::Sender<Vec<u8>>>,
+    epilogue: Epilogue,
+    notify: Option<NoticeTx>,
+    spawner: Option<Spawner>,
+    process: Option<ProcInfo>,
+    /// What the pane was last spawned with, for respawns.
+    spawned: Option<(Program, PtyConfig)>,
+    started: Option<Instant>,
+    auto_respawn: Option<AutoRespawn>,
+    backoff: Duration,
 }
 
 impl std::fmt::Display for Pane {
@@ -56,11 +168,25 @@ impl Pane {
             id,
             title: title.into(),
             size,
+            remain_on_exit: None,
             pty: None,
             taps: HashMap::new(),
-            attached: BTreeSet::new(),
+            attached: BTreeMap::new(),
+            read_only: BTreeSet::new(),
             input_owner: None,
+            input_mode: InputMode::Exclusive,
             state: PaneState::Empty,
+            screen: Arc::new(Mutex::new(Screen::new(size.cols, size.rows))),
+            feed: None,
+            epilogue_tx: None,
+            epilogue: watch::channel(None).1,
+            notify: None,
+            spawner: None,
+            process: None,
+            spawned: None,
+            started: None,
+            auto_respawn: None,
+            backoff: Duration::ZERO,
         }
     }
 
@@ -71,77 +197,293 @@ impl Pane {
         matches!(self.state, PaneState::Running)
     }
 
+    /// Foreground process as of the last [`refresh_process`](Self::refresh_process).
+    pub fn process(&self) -> Option<&ProcInfo> {
+        self.process.as_ref()
+    }
+
+    /// Re-read the foreground job (falling back to the spawned child) from `/proc`.
+    /// Returns whether the command or cwd changed.
+    pub fn refresh_process(&mut self) -> bool {
+        let info = self
+            .pty
+            .as_ref()
+            .and_then(|p| p.foreground_pid().and_then(procinfo::read).or_else(|| p.pid().and_then(procinfo::read)));
+        let changed =
+            info.as_ref().map(|i| (&i.command, &i.cwd)) != self.process.as_ref().map(|i| (&i.command, &i.cwd));
+        self.process = info;
+        changed
+    }
+
     pub fn spawn(&mut self, target: SpawnTarget, cfg: PtyConfig) -> Result {
         if self.pty.is_some() {
             return Err(Error::InvalidState("pane already spawned".into()));
         }
 
-        let handle = pty::spawn(target, cfg)?;
+        let handle = match self.spawner {
+            Some(ref spawner) => spawner(target.clone(), cfg.clone())?,
+            None => pty::spawn(target.clone(), cfg.clone())?,
+        };
+        self.spawned = Some((target, cfg));
+        self.started = Some(Instant::now());
+        self.adopt(handle)
+    }
+
+    /// Run the pane on an already spawned process, e.g. one on a [`pty::fake::FakePty`].
+    pub fn adopt(&mut self, handle: PtyHandle) -> Result {
+        if self.pty.is_some() {
+            return Err(Error::InvalidState("pane already spawned".into()));
+        }
+
+        // the screen model keeps its own tap so it tracks output whoever is attached; it must not
+        // lose chunks, since resyncs of slow peers are drawn from it
+        let mut rx = handle.subscribe_with(Backpressure::Block);
+        let screen = self.screen.clone();
+        let (id, notify) = (self.id, self.notify.clone());
+        let (epilogue_tx, epilogue_rx) = oneshot::channel::<Vec<u8>>();
+        let (shown_tx, shown_rx) = watch::channel(None);
+        let feed = tokio::spawn(async move {
+            while let Some(chunk) = rx.recv().await {
+                let notices = {
+                    let mut screen = screen.lock().unwrap_or_else(|e| e.into_inner());
+                    screen.feed(&chunk);
+                    screen.take_notices()
+                };
+                if let Some(ref tx) = notify {
+                    for n in notices {
+                        let _ = tx.send((id, n));
+                    }
+                }
+            }
+            // published under the screen lock, so a redraw either has it or leaves it to follow
+            if let Ok(line) = epilogue_rx.await {
+                let mut screen = screen.lock().unwrap_or_else(|e| e.into_inner());
+                screen.feed(&line);
+                shown_tx.send_replace(Some(Arc::from(line)));
+            }
+        });
+        self.feed = Some(feed.abort_handle());
+        (self.epilogue_tx, self.epilogue) = (Some(epilogue_tx), shown_rx);
         self.pty = Some(handle);
         if let Some(ref p) = self.pty {
-            for &peer in &self.attached {
-                let _ = self.taps.insert(peer, p.subscribe());
+            for (&peer, &backpressure) in &self.attached {
+                let _ = self.taps.insert(peer, p.subscribe_with(backpressure));
             }
             if self.input_owner.is_none() {
-                self.input_owner = self.attached.iter().copied().next();
+                self.input_owner = self.next_owner();
             }
         }
         self.state = PaneState::Running;
         Ok(())
     }
 
-    pub fn attach_peer(&mut self, who: PeerId) -> Result<()> {
-        self.attached.insert(who);
+    /// Restart the program in place: id, attached peers and input owner stay as they are, and
+    /// peers get fresh taps. `program` and `cwd` override what the pane was last spawned with.
+    /// A pane that is still running is refused unless `kill`, which SIGKILLs it first.
+    pub fn respawn(&mut self, program: Option<Program>, cwd: Option<PathBuf>, kill: bool) -> Result {
+        self.poll_exit();
+        if let Some(ref p) = self.pty {
+            if self.is_running() {
+                if !kill {
+                    return Err(Error::InvalidState("pane is still running".into()));
+                }
+                // its exit is observed through the old handle only, which is dropped below
+                let _ = p.signal(PtySig::Kill);
+            }
+        }
+        let (last, mut cfg) =
+            self.spawned.clone().ok_or_else(|| Error::InvalidState("pane was never spawned".into()))?;
+        (cfg.cols, cfg.rows) = (self.size.cols, self.size.rows);
+        if cwd.is_some() {
+            cfg.cwd = cwd;
+        }
+        // output the old process wrote but the screen model hasn't read yet must not land on top
+        // of the new one's; a killed process may not even have reached EOF
+        if let Some(feed) = self.feed.take() {
+            feed.abort();
+        }
+        self.pty = None;
+        self.taps.clear();
+        self.spawn(program.unwrap_or(last), cfg)
+    }
+
+    pub fn auto_respawn(&self) -> Option<AutoRespawn> {
+        self.auto_respawn
+    }
+    pub fn set_auto_respawn(&mut self, policy: Option<AutoRespawn>) {
+        self.auto_respawn = policy;
+        self.backoff = Duration::ZERO;
+    }
+
+    /// Delay before the next automatic respawn, or `None` when the policy is off.
+    pub fn respawn_delay(&mut self) -> Option<Duration> {
+        let policy = self.auto_respawn?;
+        let ran = self.started.map(|t| t.elapsed()).unwrap_or_default();
+        self.backoff =
+            if self.backoff.is_zero() || ran > policy.max { policy.min } else { (self.backoff * 2).min(policy.max) };
+        Some(self.backoff)
+    }
+
+    /// Report escape-sequence notices to `tx`; takes effect at the next spawn.
+    pub fn set_notify(&mut self, tx: NoticeTx) {
+        self.notify = Some(tx);
+    }
+
+    /// Start the program with `spawner` instead of [`pty::spawn`] from the next spawn on.
+    pub fn set_spawner(&mut self, spawner: Spawner) {
+        self.spawner = Some(spawner);
+    }
+
+    /// Attach `who`; a `read_only` peer gets output like any other but never input ownership,
+    /// and never holds the program back: its tap drops whatever `backpressure` asks for.
+    pub fn attach_peer(&mut self, who: PeerId, backpressure: Backpressure, read_only: bool) -> Result<()> {
+        let backpressure = if read_only { Backpressure::Drop } else { backpressure };
+        self.attached.insert(who, backpressure);
+        if read_only {
+            self.set_read_only(who);
+        } else {
+            self.read_only.remove(&who);
+        }
         if let Some(ref p) = self.pty {
-            let rx = p.subscribe();
+            let rx = p.subscribe_with(backpressure);
             self.taps.insert(who, rx);
-            if self.input_owner.is_none() {
+            if self.input_owner.is_none() && !read_only {
                 self.input_owner = Some(who);
             }
         }
         Ok(())
     }
 
+    /// Detach `who`. Input it owned is released, not passed on: the next owner asks for it or,
+    /// with nobody holding it, just starts typing.
     pub fn detach_peer(&mut self, who: PeerId) {
         self.attached.remove(&who);
+        self.read_only.remove(&who);
         self.taps.remove(&who); // dropping rx closes that tap
         if self.input_owner == Some(who) {
-            self.input_owner = self.attached.iter().copied().next();
+            self.input_owner = None;
         }
     }
 
+    /// Keep an attached peer watching only, releasing input ownership if it held it. A blocking
+    /// tap gets a dropping one instead, to be picked up like one from [`attach_peer`](Self::attach_peer).
+    pub fn set_read_only(&mut self, who: PeerId) {
+        if self.attached.contains_key(&who) {
+            self.read_only.insert(who);
+            if self.input_owner == Some(who) {
+                self.input_owner = None;
+            }
+            if self.attached.get(&who) == Some(&Backpressure::Block) {
+                self.set_backpressure(who, Backpressure::Drop);
+            }
+        }
+    }
+
+    /// The first attached peer that may type, for a pane that starts with peers attached.
+    fn next_owner(&self) -> Option<PeerId> {
+        self.attached.keys().copied().find(|p| !self.read_only.contains(p))
+    }
+
+    /// Change how `who`'s tap treats it falling behind. An attached peer gets a fresh tap, to be
+    /// picked up like one from [`attach_peer`](Self::attach_peer); returns whether it was attached.
+    pub fn set_backpressure(&mut self, who: PeerId, backpressure: Backpressure) -> bool {
+        let Some(bp) = self.attached.get_mut(&who) else {
+            return false;
+        };
+        *bp = backpressure;
+        self.retap(who)
+    }
+
+    /// Give an attached peer a fresh tap, e.g. so a new forwarder picks up changed settings.
+    pub fn retap(&mut self, who: PeerId) -> bool {
+        let Some(&backpressure) = self.attached.get(&who) else {
+            return false;
+        };
+        if let Some(ref p) = self.pty {
+            self.taps.insert(who, p.subscribe_with(backpressure));
+        }
+        true
+    }
+
+    /// Whether `who` could own input: attached, and not read-only.
+    pub fn may_own(&self, who: PeerId) -> Result<()> {
+        if !self.attached.contains_key(&who) {
+            return Err(Error::InvalidState("peer not attached".into()));
+        }
+        if self.read_only.contains(&who) {
+            return Err(Error::InvalidState("peer is attached read-only".into()));
+        }
+        Ok(())
+    }
+
     pub fn set_input_owner(&mut self, who: Option<PeerId>) -> Result<()> {
         if let Some(p) = who {
-            if !self.attached.contains(&p) {
-                return Err(Error::InvalidState("peer not attached".into()));
-            }
+            self.may_own(p)?;
         }
         self.input_owner = who;
         Ok(())
     }
 
-    pub async fn write_from(&self, who: PeerId, bytes: &[u8]) -> Result<usize> {
-        if self.input_owner != Some(who) {
+    pub fn input_mode(&self) -> InputMode {
+        self.input_mode
+    }
+    /// Switching modes keeps the owner, so going back to exclusive hands input back to it.
+    pub fn set_input_mode(&mut self, mode: InputMode) {
+        self.input_mode = mode;
+    }
+
+    /// Whether `who` may type now: the owner, or in shared mode any attached peer that isn't
+    /// read-only.
+    pub fn may_type(&self, who: PeerId) -> bool {
+        match self.input_mode {
+            _ if self.read_only.contains(&who) => false,
+            InputMode::Exclusive => self.input_owner == Some(who),
+            InputMode::Shared => self.attached.contains_key(&who),
+        }
+    }
+
+    /// Write `who`'s input to the program. Input from read-only peers is dropped, as if written.
+    /// Never waits: a program that stopped reading makes this fail instead.
+    pub fn write_from(&self, who: PeerId, bytes: &[u8]) -> Result<usize> {
+        if self.read_only.contains(&who) {
+            return Ok(0);
+        }
+        if !self.may_type(who) {
             return Err(Error::InvalidState("peer has no input focus".into()));
         }
         let p = self.pty.as_ref().ok_or_else(|| Error::InvalidState("pane has no PTY".into()))?;
-        p.write(bytes).await.map_err(|e| e.into())
+        p.try_write(bytes).map_err(|e| e.into())
+    }
+
+    /// Write a terminal reply (not user input) to the program, bypassing input ownership.
+    pub fn reply(&self, bytes: &[u8]) -> Result<usize> {
+        let p = self.pty.as_ref().ok_or_else(|| Error::InvalidState("pane has no PTY".into()))?;
+        p.try_write(bytes).map_err(|e| e.into())
     }
 
     pub fn resize(&mut self, size: TermSize) -> Result<()> {
         self.size = size;
+        self.screen().resize(size.cols, size.rows);
         if let Some(ref p) = self.pty {
             p.resize(size.cols, size.rows)?;
         }
         Ok(())
     }
 
-    pub fn kill(&mut self, force: bool) -> Result<()> {
-        if let Some(ref p) = self.pty {
-            let sig = if force { PtySig::Kill } else { PtySig::Term };
-            p.signal(sig)?;
+    /// Staged shutdown (see [`PtyHandle::shutdown`]) that does not borrow the pane while it waits.
+    pub fn shutdown(&self, force: bool, grace: Duration) -> impl Future<Output = KillStage> + Send + 'static {
+        let pty = self.pty.clone();
+        async move {
+            match pty {
+                Some(p) => p.shutdown(force, grace).await,
+                None => KillStage::Exited,
+            }
         }
-        Ok(())
+    }
+
+    pub fn signal(&self, sig: PtySig, target: SigTarget) -> Result<()> {
+        let p = self.pty.as_ref().ok_or_else(|| Error::InvalidState("pane has no PTY".into()))?;
+        p.signal_to(sig, target).map_err(Into::into)
     }
 
     /// Non-blocking state refresh from PTY exit_watch.
@@ -154,6 +496,17 @@ impl Pane {
         }
     }
 
+    /// The current process, for server-side readers and writers that bypass peers and input
+    /// ownership (pipes, recordings).
+    pub fn pty(&self) -> Option<&PtyHandle> {
+        self.pty.as_ref()
+    }
+
+    /// Exit watch of the current process, if one was spawned.
+    pub fn exit_watch(&self) -> Option<watch::Receiver<Option<PtyExit>>> {
+        self.pty.as_ref().map(PtyHandle::exit_watch)
+    }
+
     pub fn tap(&mut self, peer: PeerId) -> Option<&mut OutputRx> {
         self.taps.get_mut(&peer)
     }
@@ -162,7 +515,49 @@ impl Pane {
         self.taps.remove(&peer)
     }
 
+    pub fn exit_status(&self) -> Option<&PtyExit> {
+        match self.state {
+            PaneState::Exited(ref status) => Some(status),
+            _ => None,
+        }
+    }
+
     pub fn state(&self) -> &PaneState {
         &self.state
     }
+
+    pub fn screen(&self) -> MutexGuard<'_, Screen> {
+        self.screen.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Show `line` under the exited process's output: on the screen once it has all of that, and
+    /// then to attached peers, after whatever output their forwarders still hold.
+    pub fn finish(&mut self, line: Vec<u8>) {
+        if let Some(tx) = self.epilogue_tx.take() {
+            let _ = tx.send(line);
+        }
+    }
+
+    /// The current process's [`Epilogue`].
+    pub fn epilogue(&self) -> Epilogue {
+        self.epilogue.clone()
+    }
+
+    /// The screen model itself, for tasks that outlive a borrow of the pane.
+    pub fn shared_screen(&self) -> Arc<Mutex<Screen>> {
+        self.screen.clone()
+    }
+
+    pub fn attached(&self) -> impl Iterator<Item = &PeerId> {
+        self.attached.keys()
+    }
+    pub fn is_read_only(&self, peer: PeerId) -> bool {
+        self.read_only.contains(&peer)
+    }
+    pub fn backpressure(&self, peer: PeerId) -> Option<Backpressure> {
+        self.attached.get(&peer).copied()
+    }
+    pub fn input_owner(&self) -> Option<PeerId> {
+        self.input_owner
+    }
 }
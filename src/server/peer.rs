@@ -1,15 +1,56 @@
+use super::{pane::PaneId, session::SessionId, window::WindowId};
+use crate::pty::Backpressure;
+
 crate::common::idgen::id_newtype!(PeerId);
 
+/// Pane output frames a peer gets per second unless it asks otherwise.
+pub const DEFAULT_FPS: u16 = 60;
+
+/// Where a peer is currently attached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attachment {
+    pub session: SessionId,
+    pub window: WindowId,
+    pub pane: PaneId,
+    /// Attached to watch only (see [`Pane::attach_peer`](super::pane::Pane::attach_peer)).
+    pub read_only: bool,
+}
+
+/// Who is on the other end of a connection, as the kernel reports it (`SO_PEERCRED`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCred {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: Option<i32>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Peer {
     pub id: PeerId,
     pub name: String,
-    // future: perms, caps, palette, etc.
+    pub attachment: Option<Attachment>,
+    /// What happens to pane output this peer can't keep up with.
+    pub backpressure: Backpressure,
+    /// Most output frames per second; 0 sends output as soon as it arrives.
+    pub fps: u16,
+    /// Send redraws of changed screen rows instead of the raw output.
+    pub diffs: bool,
+    /// Credentials of a socket peer; `None` for peers inside the server process.
+    pub cred: Option<PeerCred>,
+    // future: caps, palette, etc.
 }
 
 impl Peer {
     pub fn new(id: PeerId, name: impl Into<String>) -> Self {
-        Self { id, name: name.into() }
+        Self {
+            id,
+            name: name.into(),
+            attachment: None,
+            backpressure: Backpressure::default(),
+            fps: DEFAULT_FPS,
+            diffs: false,
+            cred: None,
+        }
     }
 }
 
@@ -1,16 +1,42 @@
+use super::acl::Acl;
+use super::pane::RemainOnExit;
 use super::peer::PeerId;
 use super::window::{Window, WindowId};
 use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 
 crate::common::idgen::id_newtype!(SessionId);
 
+/// What programs in a session may do with the clipboard through OSC 52.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClipboardPolicy {
+    /// Answer OSC 52 queries with the newest paste buffer. The buffers are the owner's, so
+    /// sessions shared through grants never answer.
+    pub read: bool,
+    /// Store OSC 52 writes in a paste buffer and forward them to attached clients. Shared
+    /// sessions only forward them, for the same reason they never answer reads.
+    pub write: bool,
+}
+
+impl Default for ClipboardPolicy {
+    fn default() -> Self {
+        Self { read: false, write: true }
+    }
+}
+
 pub struct Session {
     pub id: SessionId,
     pub name: String,
     windows: BTreeMap<WindowId, Window>,
     focused: Option<WindowId>,
     peers: BTreeSet<PeerId>,
+    pub clipboard: ClipboardPolicy,
+    pub remain_on_exit: Option<RemainOnExit>,
+    /// Variables every new pane inherits.
+    pub environment: BTreeMap<String, String>,
+    /// Access for peers other than the server's owner.
+    pub acl: Acl,
 }
 
 impl std::fmt::Display for Session {
@@ -29,7 +55,17 @@ impl std::fmt::Display for Session {
 
 impl Session {
     pub fn new(id: SessionId, name: impl Into<String>) -> Self {
-        Self { id, name: name.into(), windows: BTreeMap::new(), focused: None, peers: BTreeSet::new() }
+        Self {
+            id,
+            name: name.into(),
+            windows: BTreeMap::new(),
+            focused: None,
+            peers: BTreeSet::new(),
+            clipboard: ClipboardPolicy::default(),
+            remain_on_exit: None,
+            environment: BTreeMap::new(),
+            acl: Acl::default(),
+        }
     }
 
     pub fn add_window(&mut self, win: Window) -> Result<()> {
@@ -44,6 +80,10 @@ impl Session {
         Ok(())
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
     pub fn remove_window(&mut self, id: WindowId) -> Option<Window> {
         let removed = self.windows.remove(&id);
         if self.focused == Some(id) {
@@ -79,4 +119,10 @@ impl Session {
     pub fn windows(&self) -> impl Iterator<Item = (&WindowId, &Window)> {
         self.windows.iter()
     }
+    pub fn windows_mut(&mut self) -> impl Iterator<Item = (&WindowId, &mut Window)> {
+        self.windows.iter_mut()
+    }
+    pub fn peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.peers.iter()
+    }
 }
@@ -1,9 +1,11 @@
 use super::{
+    buffer::PasteBuffers,
     pane::{Pane, PaneId, TermSize},
     peer::{Peer, PeerId},
     session::{Session, SessionId},
     window::{Window, WindowId},
 };
+use crate::client::keys::KeyTables;
 use crate::server::IdAllocator;
 use crate::{Error, Result};
 use std::collections::BTreeMap;
@@ -21,6 +23,8 @@ pub struct ServerState {
     allocs: Allocators,
     sessions: BTreeMap<SessionId, Session>,
     peers: BTreeMap<PeerId, Peer>,
+    keys: KeyTables,
+    buffers: PasteBuffers,
 }
 
 impl std::fmt::Display for ServerState {
@@ -86,11 +90,55 @@ impl ServerState {
     pub fn sessions(&self) -> impl Iterator<Item = (&SessionId, &Session)> {
         self.sessions.iter()
     }
+    pub fn sessions_mut(&mut self) -> impl Iterator<Item = (&SessionId, &mut Session)> {
+        self.sessions.iter_mut()
+    }
+    pub fn remove_session(&mut self, id: SessionId) -> Option<Session> {
+        self.sessions.remove(&id)
+    }
+
+    /// Find the session and window owning a pane.
+    pub fn locate_pane(&self, id: PaneId) -> Option<(SessionId, WindowId)> {
+        self.sessions
+            .iter()
+            .find_map(|(&sid, s)| s.windows().find(|(_, w)| w.pane(id).is_some()).map(|(&wid, _)| (sid, wid)))
+    }
+    pub fn locate_window(&self, id: WindowId) -> Option<SessionId> {
+        self.sessions.iter().find(|(_, s)| s.window(id).is_some()).map(|(&sid, _)| sid)
+    }
+    pub fn pane(&self, id: PaneId) -> Option<&Pane> {
+        let (sid, wid) = self.locate_pane(id)?;
+        self.sessions.get(&sid)?.window(wid)?.pane(id)
+    }
+    pub fn pane_mut(&mut self, id: PaneId) -> Option<&mut Pane> {
+        let (sid, wid) = self.locate_pane(id)?;
+        self.sessions.get_mut(&sid)?.window_mut(wid)?.pane_mut(id)
+    }
 
     pub fn peer(&self, id: PeerId) -> Option<&Peer> {
         self.peers.get(&id)
     }
+    pub fn peer_mut(&mut self, id: PeerId) -> Option<&mut Peer> {
+        self.peers.get_mut(&id)
+    }
     pub fn peers(&self) -> impl Iterator<Item = (&PeerId, &Peer)> {
         self.peers.iter()
     }
+    pub fn remove_peer(&mut self, id: PeerId) -> Option<Peer> {
+        self.peers.remove(&id)
+    }
+
+    pub fn keys(&self) -> &KeyTables {
+        &self.keys
+    }
+    pub fn keys_mut(&mut self) -> &mut KeyTables {
+        &mut self.keys
+    }
+
+    pub fn buffers(&self) -> &PasteBuffers {
+        &self.buffers
+    }
+    pub fn buffers_mut(&mut self) -> &mut PasteBuffers {
+        &mut self.buffers
+    }
 }
@@ -1,4 +1,4 @@
-use super::pane::{Pane, PaneId};
+use super::pane::{Pane, PaneId, RemainOnExit};
 use crate::{Error, Result};
 use std::collections::BTreeMap;
 
@@ -7,8 +7,11 @@ crate::common::idgen::id_newtype!(WindowId);
 pub struct Window {
     pub id: WindowId,
     pub name: String,
+    pub remain_on_exit: Option<RemainOnExit>,
     panes: BTreeMap<PaneId, Pane>,
     focused: Option<PaneId>,
+    /// Whether `name` follows the focused pane; cleared by an explicit rename.
+    automatic_name: bool,
 }
 
 impl std::fmt::Display for Window {
@@ -19,7 +22,14 @@ impl std::fmt::Display for Window {
 
 impl Window {
     pub fn new(id: WindowId, name: impl Into<String>) -> Self {
-        Self { id, name: name.into(), panes: BTreeMap::new(), focused: None }
+        Self {
+            id,
+            name: name.into(),
+            remain_on_exit: None,
+            panes: BTreeMap::new(),
+            focused: None,
+            automatic_name: true,
+        }
     }
 
     pub fn add_pane(&mut self, pane: Pane) -> Result<()> {
@@ -34,6 +44,10 @@ impl Window {
         Ok(())
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.panes.is_empty()
+    }
+
     pub fn remove_pane(&mut self, id: PaneId) -> Option<Pane> {
         let removed = self.panes.remove(&id);
         if self.focused == Some(id) {
@@ -50,6 +64,34 @@ impl Window {
         Ok(())
     }
 
+    pub fn automatic_name(&self) -> bool {
+        self.automatic_name
+    }
+    pub fn set_automatic_name(&mut self, on: bool) {
+        self.automatic_name = on;
+    }
+
+    /// Take the focused pane's title, or its foreground command when it has none.
+    /// Does nothing once the window was renamed explicitly; returns whether the name changed.
+    pub fn update_name(&mut self) -> bool {
+        if !self.automatic_name {
+            return false;
+        }
+        let Some(p) = self.focused.and_then(|id| self.panes.get(&id)) else {
+            return false;
+        };
+        let name = match p.process() {
+            _ if !p.title.is_empty() => &p.title,
+            Some(info) => &info.command,
+            None => return false,
+        };
+        if *name == self.name {
+            return false;
+        }
+        self.name = name.clone();
+        true
+    }
+
     pub fn focused(&self) -> Option<PaneId> {
         self.focused
     }
@@ -62,4 +104,7 @@ impl Window {
     pub fn panes(&self) -> impl Iterator<Item = (&PaneId, &Pane)> {
         self.panes.iter()
     }
+    pub fn panes_mut(&mut self) -> impl Iterator<Item = (&PaneId, &mut Pane)> {
+        self.panes.iter_mut()
+    }
 }
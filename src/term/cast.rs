@@ -0,0 +1,110 @@
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// First line of an asciicast v2 recording; every following line is a `[time, code, data]` event.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub width: u16,
+    pub height: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl Header {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self { version: 2, width, height, timestamp: None, title: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Output(String),
+    Resize { cols: u16, rows: u16 },
+}
+
+/// Turns output chunks and resizes into event lines. Chunks may split UTF-8 sequences; the
+/// tail is held back until the rest arrives.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    partial: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn header(&self, header: &Header) -> String {
+        format!("{}\n", serde_json::to_string(header).unwrap_or_default())
+    }
+
+    /// `at` is the time since the recording started; `None` while only a partial character
+    /// is pending.
+    pub fn output(&mut self, at: Duration, chunk: &[u8]) -> Option<String> {
+        self.partial.extend_from_slice(chunk);
+        let keep = incomplete_tail(&self.partial);
+        let tail = self.partial.split_off(self.partial.len() - keep);
+        let data = std::mem::replace(&mut self.partial, tail);
+        if data.is_empty() {
+            return None;
+        }
+        Some(line(&serde_json::json!([at.as_secs_f64(), "o", String::from_utf8_lossy(&data)])))
+    }
+
+    pub fn resize(&self, at: Duration, cols: u16, rows: u16) -> String {
+        line(&serde_json::json!([at.as_secs_f64(), "r", format!("{cols}x{rows}")]))
+    }
+}
+
+fn line(v: &serde_json::Value) -> String {
+    format!("{v}\n")
+}
+
+/// Length of a trailing UTF-8 sequence that is cut short.
+fn incomplete_tail(b: &[u8]) -> usize {
+    for back in 1..=b.len().min(3) {
+        let byte = b[b.len() - back];
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+        let need = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return 0,
+        };
+        return if need > back { back } else { 0 };
+    }
+    0
+}
+
+/// Parse a whole recording. Input and marker events are skipped.
+pub fn parse(text: &str) -> Result<(Header, Vec<(Duration, Frame)>)> {
+    let bad = |what: &str| Error::UserInput(format!("not an asciicast v2 file: {what}"));
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    let header: Header =
+        serde_json::from_str(lines.next().ok_or_else(|| bad("empty"))?).map_err(|e| bad(&e.to_string()))?;
+    if header.version != 2 {
+        return Err(bad(&format!("version {}", header.version)));
+    }
+    let mut frames = Vec::new();
+    for l in lines {
+        let (at, code, data): (f64, String, String) = serde_json::from_str(l).map_err(|e| bad(&e.to_string()))?;
+        let at = Duration::try_from_secs_f64(at).map_err(|e| bad(&e.to_string()))?;
+        let frame = match code.as_str() {
+            "o" => Frame::Output(data),
+            "r" => {
+                let size = data.split_once('x').and_then(|(c, r)| Some((c.parse().ok()?, r.parse().ok()?)));
+                let (cols, rows) = size.ok_or_else(|| bad(&format!("resize {data}")))?;
+                Frame::Resize { cols, rows }
+            }
+            _ => continue,
+        };
+        frames.push((at, frame));
+    }
+    Ok((header, frames))
+}
@@ -0,0 +1,96 @@
+/// Streaming filter that drops OSC 52 sequences from pane output.
+///
+/// Clipboard writes are handled by the server and re-sent to clients as events, so the raw
+/// sequence must never reach an outer terminal. State carries across chunk boundaries.
+#[derive(Debug, Default)]
+pub struct Osc52Filter {
+    state: State,
+    /// Bytes of an OSC introducer held back until we know whether it is OSC 52.
+    held: Vec<u8>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum State {
+    #[default]
+    Ground,
+    Esc,
+    OscHead,
+    Drop,
+    DropEsc,
+}
+
+const HEAD: &[u8] = b"\x1b]52;";
+
+impl Osc52Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filter(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(chunk.len());
+        for &b in chunk {
+            match self.state {
+                State::Ground if b == 0x1b => {
+                    self.held.push(b);
+                    self.state = State::Esc;
+                }
+                State::Ground => out.push(b),
+                State::Esc if b == b']' => {
+                    self.held.push(b);
+                    self.state = State::OscHead;
+                }
+                State::Esc | State::OscHead => {
+                    self.held.push(b);
+                    if self.held == HEAD {
+                        self.held.clear();
+                        self.state = State::Drop;
+                    } else if !HEAD.starts_with(&self.held) {
+                        out.append(&mut self.held);
+                        self.state = State::Ground;
+                    }
+                }
+                State::Drop if b == 0x07 => self.state = State::Ground,
+                State::Drop if b == 0x1b => self.state = State::DropEsc,
+                State::Drop => {}
+                State::DropEsc if b == b'\\' => self.state = State::Ground,
+                State::DropEsc => self.state = State::Drop,
+            }
+        }
+        out
+    }
+}
+
+/// Streaming filter that reduces pane output to plain text for logs: escape sequences and
+/// control characters are dropped, keeping printable text, newlines and tabs.
+#[derive(Default)]
+pub struct EscapeStripper {
+    parser: vte::Parser,
+    out: Plain,
+}
+
+#[derive(Default)]
+struct Plain(Vec<u8>);
+
+impl vte::Perform for Plain {
+    fn print(&mut self, c: char) {
+        let mut buf = [0; 4];
+        self.0.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+
+    fn execute(&mut self, byte: u8) {
+        if matches!(byte, b'\n' | b'\t') {
+            self.0.push(byte);
+        }
+    }
+}
+
+impl EscapeStripper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn strip(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.parser.advance(&mut self.out, chunk);
+        std::mem::take(&mut self.out.0)
+    }
+}
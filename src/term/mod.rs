@@ -0,0 +1,476 @@
+use base64::Engine as _;
+use std::collections::VecDeque;
+use vte::{Params, Perform};
+
+pub mod cast;
+mod filter;
+pub use filter::{EscapeStripper, Osc52Filter};
+
+pub const DEFAULT_HISTORY: usize = 2000;
+
+/// Requests a program made through escape sequences that the server, not the screen, must act on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notice {
+    /// OSC 52 set: `selection` is the raw selection parameter (`c`, `p`, ... or empty).
+    ClipboardSet { selection: String, data: Vec<u8> },
+    /// OSC 52 query (`?`); the reply is written back to the program.
+    ClipboardQuery { selection: String },
+    /// OSC 0/1/2 window or icon title.
+    Title(String),
+}
+
+/// Minimal VT screen model: visible grid, scrollback and the handful of modes the server cares
+/// about. Attributes are not tracked; this is for capture, search and copy, not for rendering.
+pub struct Screen {
+    parser: vte::Parser,
+    grid: Grid,
+}
+
+struct Grid {
+    cols: usize,
+    rows: usize,
+    lines: Vec<Vec<char>>,
+    history: VecDeque<String>,
+    history_limit: usize,
+    row: usize,
+    col: usize,
+    wrap_pending: bool,
+    saved: (usize, usize),
+    top: usize,
+    bottom: usize,
+    alt: Option<(Vec<Vec<char>>, usize, usize)>,
+    bracketed_paste: bool,
+    notices: Vec<Notice>,
+}
+
+impl Screen {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        Self::with_history(cols, rows, DEFAULT_HISTORY)
+    }
+
+    pub fn with_history(cols: u16, rows: u16, history_limit: usize) -> Self {
+        let (cols, rows) = (usize::from(cols.max(1)), usize::from(rows.max(1)));
+        Self {
+            parser: vte::Parser::new(),
+            grid: Grid {
+                cols,
+                rows,
+                lines: vec![vec![' '; cols]; rows],
+                history: VecDeque::new(),
+                history_limit,
+                row: 0,
+                col: 0,
+                wrap_pending: false,
+                saved: (0, 0),
+                top: 0,
+                bottom: rows - 1,
+                alt: None,
+                bracketed_paste: false,
+                notices: Vec::new(),
+            },
+        }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.parser.advance(&mut self.grid, bytes);
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.grid.resize(usize::from(cols.max(1)), usize::from(rows.max(1)));
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.grid.cols as u16, self.grid.rows as u16)
+    }
+
+    /// Cursor as (row, col) within the visible screen.
+    pub fn cursor(&self) -> (u16, u16) {
+        (self.grid.row as u16, self.grid.col as u16)
+    }
+
+    /// Visible rows with trailing blanks trimmed.
+    pub fn lines(&self) -> Vec<String> {
+        self.grid.lines.iter().map(|l| trim(l)).collect()
+    }
+
+    /// Lines that scrolled off the top, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &str> {
+        self.grid.history.iter().map(String::as_str)
+    }
+    pub fn history_len(&self) -> usize {
+        self.grid.history.len()
+    }
+
+    /// Scrollback followed by the visible rows.
+    pub fn all_lines(&self) -> Vec<String> {
+        self.history().map(str::to_string).chain(self.lines()).collect()
+    }
+
+    pub fn alternate_screen(&self) -> bool {
+        self.grid.alt.is_some()
+    }
+    /// Whether the program enabled bracketed paste (DECSET 2004).
+    pub fn bracketed_paste(&self) -> bool {
+        self.grid.bracketed_paste
+    }
+
+    /// Bytes that redraw the visible screen on a terminal that lost track of it.
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.frame().diff(None)
+    }
+
+    /// What a terminal attached to this screen should be showing.
+    pub fn frame(&self) -> Frame {
+        Frame {
+            size: self.size(),
+            lines: self.lines(),
+            cursor: self.cursor(),
+            alternate: self.alternate_screen(),
+            bracketed_paste: self.bracketed_paste(),
+        }
+    }
+
+    /// Drain notices raised by output fed so far.
+    pub fn take_notices(&mut self) -> Vec<Notice> {
+        std::mem::take(&mut self.grid.notices)
+    }
+}
+
+/// Visible state of a [`Screen`] at one moment: text, cursor and the screen/paste modes.
+/// Attributes are not tracked, so frames draw plain text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    size: (u16, u16),
+    lines: Vec<String>,
+    cursor: (u16, u16),
+    alternate: bool,
+    bracketed_paste: bool,
+}
+
+impl Frame {
+    /// Bytes that turn a terminal showing `prev` into this frame: only the rows that changed,
+    /// or everything when there is no `prev` or the size or screen changed. Empty if nothing did.
+    pub fn diff(&self, prev: Option<&Frame>) -> Vec<u8> {
+        let mut out = Vec::new();
+        if prev.is_none_or(|p| p.alternate != self.alternate) {
+            out.extend_from_slice(if self.alternate { b"\x1b[?1049h" } else { b"\x1b[?1049l" });
+        }
+        if prev.is_none_or(|p| p.bracketed_paste != self.bracketed_paste) {
+            out.extend_from_slice(if self.bracketed_paste { b"\x1b[?2004h" } else { b"\x1b[?2004l" });
+        }
+        match prev.filter(|p| p.size == self.size && p.alternate == self.alternate) {
+            Some(p) => {
+                for (i, line) in self.lines.iter().enumerate().filter(|&(i, l)| p.lines.get(i) != Some(l)) {
+                    out.extend_from_slice(format!("\x1b[{};1H\x1b[2K{line}", i + 1).as_bytes());
+                }
+            }
+            None => {
+                out.extend_from_slice(b"\x1b[0m\x1b[H\x1b[2J");
+                for (i, line) in self.lines.iter().enumerate().filter(|(_, l)| !l.is_empty()) {
+                    out.extend_from_slice(format!("\x1b[{};1H{line}", i + 1).as_bytes());
+                }
+            }
+        }
+        if !out.is_empty() || prev.is_some_and(|p| p.cursor != self.cursor) {
+            let (row, col) = self.cursor;
+            out.extend_from_slice(format!("\x1b[{};{}H", row + 1, col + 1).as_bytes());
+        }
+        out
+    }
+}
+
+fn trim(l: &[char]) -> String {
+    let s: String = l.iter().collect();
+    s.trim_end().to_string()
+}
+
+impl Grid {
+    fn blank(&self) -> Vec<char> {
+        vec![' '; self.cols]
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        for _ in 0..n {
+            let line = self.lines.remove(self.top);
+            if self.top == 0 && self.alt.is_none() && self.history_limit > 0 {
+                if self.history.len() == self.history_limit {
+                    self.history.pop_front();
+                }
+                self.history.push_back(trim(&line));
+            }
+            self.lines.insert(self.bottom, self.blank());
+        }
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        for _ in 0..n {
+            self.lines.remove(self.bottom);
+            self.lines.insert(self.top, self.blank());
+        }
+    }
+
+    fn linefeed(&mut self) {
+        self.wrap_pending = false;
+        if self.row == self.bottom {
+            self.scroll_up(1);
+        } else if self.row + 1 < self.rows {
+            self.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        if self.row == self.top {
+            self.scroll_down(1);
+        } else {
+            self.row = self.row.saturating_sub(1);
+        }
+    }
+
+    fn goto(&mut self, row: usize, col: usize) {
+        self.row = row.min(self.rows - 1);
+        self.col = col.min(self.cols - 1);
+        self.wrap_pending = false;
+    }
+
+    fn erase(&mut self, row: usize, from: usize, to: usize) {
+        let to = to.min(self.cols);
+        if let Some(l) = self.lines.get_mut(row) {
+            l[from.min(to)..to].fill(' ');
+        }
+    }
+
+    fn resize(&mut self, cols: usize, rows: usize) {
+        for l in &mut self.lines {
+            l.resize(cols, ' ');
+        }
+        self.cols = cols;
+        if rows < self.rows {
+            // keep the cursor on screen by pushing the top into history
+            let excess = (self.row + 1).saturating_sub(rows);
+            self.top = 0;
+            self.bottom = self.rows - 1;
+            self.scroll_up(excess);
+            self.row -= excess;
+            self.lines.truncate(rows);
+        } else {
+            let blank = vec![' '; cols];
+            self.lines.resize(rows, blank);
+        }
+        self.rows = rows;
+        self.top = 0;
+        self.bottom = rows - 1;
+        self.goto(self.row, self.col);
+        if let Some((ref mut lines, ..)) = self.alt {
+            for l in lines.iter_mut() {
+                l.resize(cols, ' ');
+            }
+            lines.resize(rows, vec![' '; cols]);
+        }
+    }
+
+    fn set_private_mode(&mut self, mode: u16, on: bool) {
+        match mode {
+            47 | 1047 | 1049 => {
+                if on && self.alt.is_none() {
+                    let primary = std::mem::replace(&mut self.lines, vec![vec![' '; self.cols]; self.rows]);
+                    self.alt = Some((primary, self.row, self.col));
+                } else if !on {
+                    if let Some((primary, row, col)) = self.alt.take() {
+                        self.lines = primary;
+                        self.goto(row, col);
+                    }
+                }
+            }
+            2004 => self.bracketed_paste = on,
+            _ => {}
+        }
+    }
+}
+
+fn arg(params: &Params, i: usize, default: usize) -> usize {
+    match params.iter().nth(i).and_then(|p| p.first()) {
+        Some(&0) | None => default,
+        Some(&v) => usize::from(v),
+    }
+}
+
+impl Perform for Grid {
+    fn print(&mut self, c: char) {
+        if self.wrap_pending {
+            self.col = 0;
+            self.linefeed();
+        }
+        self.lines[self.row][self.col] = c;
+        if self.col + 1 < self.cols {
+            self.col += 1;
+        } else {
+            self.wrap_pending = true;
+        }
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            b'\n' | 0x0b | 0x0c => self.linefeed(),
+            b'\r' => {
+                self.col = 0;
+                self.wrap_pending = false;
+            }
+            0x08 => {
+                self.col = self.col.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            b'\t' => self.col = ((self.col / 8 + 1) * 8).min(self.cols - 1),
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, action: char) {
+        let private = intermediates.first() == Some(&b'?');
+        let n = arg(params, 0, 1);
+        match (private, action) {
+            (true, 'h' | 'l') => {
+                for p in params.iter().filter_map(|p| p.first()) {
+                    self.set_private_mode(*p, action == 'h');
+                }
+            }
+            (true, _) => {}
+            (false, 'A') => {
+                let floor = if self.row >= self.top { self.top } else { 0 };
+                self.goto(self.row.saturating_sub(n).max(floor), self.col);
+            }
+            (false, 'B') => {
+                let ceil = if self.row <= self.bottom { self.bottom } else { self.rows - 1 };
+                self.goto((self.row + n).min(ceil), self.col);
+            }
+            (false, 'C') => self.goto(self.row, self.col + n),
+            (false, 'D') => self.goto(self.row, self.col.saturating_sub(n)),
+            (false, 'E') => self.goto(self.row + n, 0),
+            (false, 'F') => self.goto(self.row.saturating_sub(n), 0),
+            (false, 'G' | '`') => self.goto(self.row, n - 1),
+            (false, 'd') => self.goto(n - 1, self.col),
+            (false, 'H' | 'f') => self.goto(arg(params, 0, 1) - 1, arg(params, 1, 1) - 1),
+            (false, 'J') => {
+                let (row, col) = (self.row, self.col);
+                match arg(params, 0, 0) {
+                    0 => {
+                        self.erase(row, col, self.cols);
+                        for r in row + 1..self.rows {
+                            self.erase(r, 0, self.cols);
+                        }
+                    }
+                    1 => {
+                        for r in 0..row {
+                            self.erase(r, 0, self.cols);
+                        }
+                        self.erase(row, 0, col + 1);
+                    }
+                    2 => {
+                        for r in 0..self.rows {
+                            self.erase(r, 0, self.cols);
+                        }
+                    }
+                    3 => self.history.clear(),
+                    _ => {}
+                }
+            }
+            (false, 'K') => {
+                let (row, col) = (self.row, self.col);
+                match arg(params, 0, 0) {
+                    0 => self.erase(row, col, self.cols),
+                    1 => self.erase(row, 0, col + 1),
+                    2 => self.erase(row, 0, self.cols),
+                    _ => {}
+                }
+            }
+            (false, 'X') => {
+                let (row, col) = (self.row, self.col);
+                self.erase(row, col, col + n);
+            }
+            (false, 'P') => {
+                let cols = self.cols;
+                let l = &mut self.lines[self.row];
+                let n = n.min(cols - self.col);
+                l.drain(self.col..self.col + n);
+                l.resize(cols, ' ');
+            }
+            (false, '@') => {
+                let cols = self.cols;
+                let l = &mut self.lines[self.row];
+                for _ in 0..n.min(cols - self.col) {
+                    l.insert(self.col, ' ');
+                }
+                l.truncate(cols);
+            }
+            (false, 'L') if (self.top..=self.bottom).contains(&self.row) => {
+                for _ in 0..n.min(self.bottom - self.row + 1) {
+                    self.lines.remove(self.bottom);
+                    self.lines.insert(self.row, self.blank());
+                }
+            }
+            (false, 'M') if (self.top..=self.bottom).contains(&self.row) => {
+                for _ in 0..n.min(self.bottom - self.row + 1) {
+                    self.lines.remove(self.row);
+                    self.lines.insert(self.bottom, self.blank());
+                }
+            }
+            (false, 'S') => self.scroll_up(n),
+            (false, 'T') => self.scroll_down(n),
+            (false, 'r') => {
+                let top = arg(params, 0, 1) - 1;
+                let bottom = arg(params, 1, self.rows).min(self.rows) - 1;
+                if top < bottom {
+                    self.top = top;
+                    self.bottom = bottom;
+                    self.goto(0, 0);
+                }
+            }
+            (false, 's') => self.saved = (self.row, self.col),
+            (false, 'u') => self.goto(self.saved.0, self.saved.1),
+            _ => {}
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        match params {
+            // vte splits on `;`, so a title containing one arrives in pieces
+            [b"0" | b"1" | b"2", title @ ..] => {
+                let title: Vec<_> = title.iter().map(|p| String::from_utf8_lossy(p)).collect();
+                self.notices.push(Notice::Title(title.join(";")));
+            }
+            [b"52", selection, payload, ..] => {
+                let selection = String::from_utf8_lossy(selection).into_owned();
+                if *payload == b"?" {
+                    self.notices.push(Notice::ClipboardQuery { selection });
+                } else if let Ok(data) = base64::engine::general_purpose::STANDARD.decode(payload) {
+                    self.notices.push(Notice::ClipboardSet { selection, data });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        if !intermediates.is_empty() {
+            return;
+        }
+        match byte {
+            b'7' => self.saved = (self.row, self.col),
+            b'8' => self.goto(self.saved.0, self.saved.1),
+            b'D' => self.linefeed(),
+            b'E' => {
+                self.col = 0;
+                self.linefeed();
+            }
+            b'M' => self.reverse_index(),
+            b'c' => {
+                self.lines = vec![self.blank(); self.rows];
+                self.alt = None;
+                self.top = 0;
+                self.bottom = self.rows - 1;
+                self.bracketed_paste = false;
+                self.goto(0, 0);
+            }
+            _ => {}
+        }
+    }
+}
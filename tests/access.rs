@@ -0,0 +1,83 @@
+mod common;
+
+use common::{create_session, register, request};
+use splicer::ipc::proto::{ErrorCode, Event, KillTarget, Request, Response, StateScope};
+use splicer::server::acl::{Grantee, Role};
+use splicer::server::peer::PeerCred;
+use tokio::time::{Duration, timeout};
+
+fn denied(resp: &Response) -> bool {
+    matches!(resp, Response::Err { code: ErrorCode::Denied, .. })
+}
+
+#[tokio::test(start_paused = true)]
+async fn roles_gate_requests() {
+    let (core, mut spawned) = common::fake_core();
+    // peers inside the server process count as its owner
+    let (owner, _) = register(&core, None).await;
+    let (guest, mut guest_events) = register(&core, Some(PeerCred { uid: 4242, gid: 4242, pid: None })).await;
+
+    let session = create_session(&core, owner).await;
+    let spawn = common::spawn(session, &["cat"]);
+    let Response::PaneSpawned { pane, .. } = request(&core, owner, spawn.clone()).await else {
+        panic!("no pane");
+    };
+    let program = spawned.recv().await.unwrap().ctl;
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    let input = Request::Input { pane, data: b"x".to_vec() };
+
+    // strangers see nothing and may do nothing
+    assert!(
+        matches!(request(&core, guest, Request::ListSessions).await, Response::Sessions { items } if items.is_empty())
+    );
+    assert!(denied(&request(&core, guest, attach.clone()).await));
+    assert!(denied(&request(&core, guest, Request::CreateSession { name: None }).await));
+
+    let grant = |role| Request::Grant { session, grantee: Grantee::Uid(4242), role };
+    assert!(matches!(request(&core, owner, grant(Role::View)).await, Response::Ok));
+    assert!(
+        matches!(request(&core, guest, Request::ListSessions).await, Response::Sessions { items } if items.len() == 1)
+    );
+    assert!(matches!(request(&core, guest, attach.clone()).await, Response::Attached { .. }));
+    assert!(denied(&request(&core, guest, input.clone()).await));
+    // viewers attach read-only, whatever they asked for
+    let Response::State { json } = request(&core, owner, Request::GetState { scope: StateScope::Peers }).await else {
+        panic!("no state");
+    };
+    assert!(json.as_array().unwrap().iter().any(|p| p["id"] == guest.to_string() && p["read_only"] == true));
+
+    assert!(matches!(request(&core, owner, grant(Role::Input)).await, Response::Ok));
+    // the read-only attachment stays read-only until the guest attaches again
+    assert!(matches!(request(&core, guest, input.clone()).await, Response::Ok));
+    assert!(program.take_input().is_empty());
+    assert!(matches!(request(&core, guest, attach.clone()).await, Response::Attached { .. }));
+    assert!(matches!(request(&core, guest, input).await, Response::Ok));
+    assert_eq!(program.take_input(), b"x");
+    assert!(denied(&request(&core, guest, spawn).await));
+    assert!(denied(
+        &request(&core, guest, Request::Kill { target: KillTarget::Pane(pane), force: true, grace: None }).await
+    ));
+    assert!(denied(&request(&core, guest, grant(Role::Admin)).await));
+
+    let Response::Access { grants } = request(&core, owner, Request::ShowAccess { session }).await else {
+        panic!("no grants");
+    };
+    assert_eq!(grants, [(Grantee::Uid(4242), Role::Input)]);
+
+    // revoking detaches and tells the guest
+    let revoke = Request::Revoke { session, grantee: Grantee::Uid(4242) };
+    assert!(matches!(request(&core, owner, revoke).await, Response::Ok));
+    let bye = timeout(Duration::from_secs(1), async {
+        loop {
+            if let Some(Event::Bye { reason }) = guest_events.recv().await {
+                return reason;
+            }
+        }
+    })
+    .await
+    .expect("no Bye after revoke");
+    assert!(bye.contains("revoked"), "{bye}");
+    assert!(denied(&request(&core, guest, attach).await));
+    let kill = Request::Kill { target: KillTarget::Session(session), force: true, grace: None };
+    assert!(matches!(request(&core, owner, kill).await, Response::Killed { .. }));
+}
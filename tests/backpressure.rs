@@ -0,0 +1,135 @@
+mod common;
+
+use common::{create_session, register, register_with, request};
+use splicer::ipc::proto::{ErrorCode, Event, Request, Response};
+use splicer::pty::Backpressure;
+use splicer::pty::fake::{FakeControl, FakePty};
+use splicer::server::acl::{Grantee, Role};
+use splicer::server::peer::PeerCred;
+use splicer::term::Screen;
+use tokio::time::{Duration, sleep, timeout};
+
+#[tokio::test]
+async fn full_taps_drop_or_block() {
+    let (h, ctl) = FakePty::new();
+    let mut lossy = h.subscribe_with(Backpressure::Drop);
+    for i in 0..600u32 {
+        ctl.output(&i.to_be_bytes()).await;
+    }
+    // the tap survives being full: it lost the overflow and says so
+    assert_eq!(lossy.take_dropped(), 600 - 512);
+    assert_eq!(lossy.take_dropped(), 0);
+    let mut n = 0;
+    while lossy.try_recv().is_some() {
+        n += 1;
+    }
+    assert_eq!(n, 512);
+    ctl.output(b"more").await;
+    assert_eq!(&lossy.recv().await.unwrap()[..], b"more");
+
+    let mut paced = h.subscribe_with(Backpressure::Block);
+    let writer = tokio::spawn(async move {
+        for i in 0..600u32 {
+            ctl.output(&i.to_be_bytes()).await;
+        }
+    });
+    sleep(Duration::from_millis(50)).await;
+    assert!(!writer.is_finished(), "a full flow-controlled tap holds the writer back");
+    for i in 0..600u32 {
+        assert_eq!(&paced.recv().await.unwrap()[..], i.to_be_bytes());
+    }
+    writer.await.unwrap();
+    assert_eq!(paced.take_dropped(), 0);
+}
+
+#[test]
+fn snapshot_redraws_screen() {
+    let mut s = Screen::new(10, 3);
+    s.feed(b"\x1b[?2004hone\r\n\r\nthree\x1b[1;2H");
+    let mut copy = Screen::new(10, 3);
+    copy.feed(b"garbage\r\nthat was here");
+    copy.feed(&s.snapshot());
+    assert_eq!(copy.lines(), s.lines());
+    assert_eq!(copy.cursor(), (0, 1));
+    assert!(copy.bracketed_paste());
+}
+
+#[tokio::test(start_paused = true)]
+async fn slow_peer_gets_notice_and_redraw() {
+    let (core, mut spawned) = common::fake_core();
+    // a peer that can hold only a few events and doesn't read them for a while
+    let (peer, mut ev_rx) = register_with(&core, None, 2).await;
+
+    let session = create_session(&core, peer).await;
+    let Response::PaneSpawned { pane, .. } = request(&core, peer, common::spawn(session, &["seq"])).await else {
+        panic!("no pane");
+    };
+    let program = spawned.recv().await.unwrap().ctl;
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    assert!(matches!(request(&core, peer, attach).await, Response::Attached { .. }));
+
+    // many more frames than that
+    for n in (1..=1000).step_by(50) {
+        let lines: String = (n..n + 50).map(|i| format!("{i}\r\n")).collect();
+        program.output(lines.as_bytes()).await;
+        sleep(Duration::from_millis(50)).await;
+    }
+    // the redraw is drawn from the screen model, which has kept up
+    let Response::Captured { lines, .. } = request(&core, peer, Request::Capture { pane, history: false }).await else {
+        panic!("no capture");
+    };
+    assert!(lines.iter().any(|l| l == "1000"), "{lines:?}");
+
+    let redraw = timeout(Duration::from_secs(2), async {
+        while let Some(ev) = ev_rx.recv().await {
+            if matches!(ev, Event::StreamDropNotice { .. }) {
+                return ev_rx.recv().await;
+            }
+        }
+        None
+    })
+    .await
+    .expect("no StreamDropNotice");
+    let Some(Event::PtyOutput { chunk, .. }) = redraw else {
+        panic!("no redraw after the notice: {redraw:?}");
+    };
+    assert!(String::from_utf8_lossy(&chunk).contains("1000"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn viewers_never_hold_the_program_back() {
+    let (core, mut spawned) = common::fake_core();
+    let (owner, _) = register(&core, None).await;
+    // a guest that never reads its events
+    let (guest, _events) = register_with(&core, Some(PeerCred { uid: 4242, gid: 4242, pid: None }), 2).await;
+    let session = create_session(&core, owner).await;
+    assert!(matches!(request(&core, owner, common::spawn(session, &["seq"])).await, Response::PaneSpawned { .. }));
+    let program = spawned.recv().await.unwrap().ctl;
+    let grant = |role| Request::Grant { session, grantee: Grantee::Uid(4242), role };
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    let block = Request::SetBackpressure { policy: Backpressure::Block };
+
+    // asked for before attaching, then attached read-only: the tap drops anyway
+    assert!(matches!(request(&core, owner, grant(Role::View)).await, Response::Ok));
+    assert!(matches!(request(&core, guest, block.clone()).await, Response::Ok));
+    assert!(matches!(request(&core, guest, attach.clone()).await, Response::Attached { .. }));
+    assert!(matches!(request(&core, guest, block.clone()).await, Response::Err { code: ErrorCode::Denied, .. }));
+    let flood = |program: FakeControl| {
+        tokio::spawn(async move {
+            for i in 0..2000u32 {
+                program.output(format!("{i}\r\n").as_bytes()).await;
+            }
+        })
+    };
+    timeout(Duration::from_secs(1), flood(program.clone())).await.expect("a viewer held the program back").unwrap();
+
+    // a guest that may type may block...
+    assert!(matches!(request(&core, owner, grant(Role::Input)).await, Response::Ok));
+    assert!(matches!(request(&core, guest, attach).await, Response::Attached { .. }));
+    let writer = flood(program);
+    sleep(Duration::from_millis(100)).await;
+    assert!(!writer.is_finished(), "the blocking tap did not hold the program back");
+    // ...until it is back to watching
+    assert!(matches!(request(&core, owner, grant(Role::View)).await, Response::Ok));
+    timeout(Duration::from_secs(1), writer).await.expect("the demoted guest still holds the program back").unwrap();
+}
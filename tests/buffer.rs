@@ -0,0 +1,33 @@
+use splicer::server::buffer::PasteBuffers;
+
+#[test]
+fn named_and_automatic_buffers() {
+    let mut bufs = PasteBuffers::default();
+    assert_eq!(bufs.set(None, b"one".to_vec()), "buffer0");
+    assert_eq!(bufs.set(Some("x".into()), b"two".to_vec()), "x");
+    assert_eq!(bufs.set(None, b"three".to_vec()), "buffer1");
+
+    assert_eq!(bufs.get(None).map(|b| b.data.as_slice()), Some(b"three".as_slice()));
+    assert_eq!(bufs.get(Some("x")).map(|b| b.data.as_slice()), Some(b"two".as_slice()));
+
+    // setting a named buffer replaces it and moves it to the top
+    bufs.set(Some("x".into()), b"again".to_vec());
+    let names: Vec<_> = bufs.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, ["x", "buffer1", "buffer0"]);
+
+    assert!(bufs.delete(Some("buffer0")).is_some());
+    assert!(bufs.delete(Some("buffer0")).is_none());
+    assert_eq!(bufs.delete(None).map(|b| b.name), Some("x".into()));
+}
+
+#[test]
+fn only_automatic_buffers_are_evicted() {
+    let mut bufs = PasteBuffers::default();
+    bufs.set(Some("keep".into()), Vec::new());
+    for _ in 0..splicer::server::buffer::DEFAULT_LIMIT + 5 {
+        bufs.set(None, Vec::new());
+    }
+    assert_eq!(bufs.iter().count(), splicer::server::buffer::DEFAULT_LIMIT + 1);
+    assert!(bufs.get(Some("keep")).is_some());
+    assert!(bufs.get(Some("buffer0")).is_none());
+}
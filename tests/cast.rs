@@ -0,0 +1,109 @@
+mod common;
+
+use common::{create_session, register, request};
+use splicer::ipc::proto::{KillTarget, RecordTarget, Request, Response};
+use splicer::term::cast::{self, Encoder, Frame, Header};
+use std::path::Path;
+use std::time::Duration;
+
+#[test]
+fn encode_and_parse_roundtrip() {
+    let mut enc = Encoder::new();
+    let mut text = enc.header(&Header::new(80, 24));
+    assert!(text.starts_with(r#"{"version":2,"width":80,"height":24}"#));
+
+    // "é" split across two chunks is written once it is whole
+    let ms = Duration::from_millis;
+    let first = enc.output(ms(500), b"caf\xc3").unwrap();
+    assert_eq!(first, "[0.5,\"o\",\"caf\"]\n");
+    assert_eq!(enc.output(ms(600), b""), None);
+    text += &first;
+    text += &enc.output(ms(750), b"\xa9\r\n").unwrap();
+    text += &enc.resize(ms(1000), 100, 30);
+    text += "[1.5,\"i\",\"q\"]\n";
+
+    let (header, frames) = cast::parse(&text).unwrap();
+    assert_eq!((header.width, header.height), (80, 24));
+    assert_eq!(
+        frames,
+        vec![
+            (ms(500), Frame::Output("caf".into())),
+            (ms(750), Frame::Output("é\r\n".into())),
+            (ms(1000), Frame::Resize { cols: 100, rows: 30 }),
+        ]
+    );
+    assert!(cast::parse("{\"version\":1,\"width\":1,\"height\":1}").is_err());
+}
+
+/// The output and resizes recorded to `path`, once it holds `want`; panics after a few seconds.
+async fn recorded(path: &Path, want: &str) -> (String, Vec<(u16, u16)>) {
+    for _ in 0..250 {
+        let text = std::fs::read_to_string(path).unwrap_or_default();
+        if let Ok((_, frames)) = cast::parse(&text) {
+            let mut out = String::new();
+            let mut sizes = Vec::new();
+            for (_, f) in frames {
+                match f {
+                    Frame::Output(o) => out += &o,
+                    Frame::Resize { cols, rows } => sizes.push((cols, rows)),
+                }
+            }
+            if out.contains(want) {
+                return (out, sizes);
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{want:?} never recorded: {:?}", std::fs::read_to_string(path));
+}
+
+#[tokio::test]
+async fn recordings_follow_respawns_and_end_with_the_pane() {
+    let dir = std::env::temp_dir().join(format!("splicer-rec-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let (core, mut spawned) = common::fake_core();
+    let (owner, _) = register(&core, None).await;
+    let session = create_session(&core, owner).await;
+    let spawn = common::spawn(session, &["sh"]);
+    let Response::PaneSpawned { pane, .. } = request(&core, owner, spawn.clone()).await else {
+        panic!("no pane");
+    };
+    let program = spawned.recv().await.unwrap().ctl;
+    let path = dir.join("pane.cast");
+    let record = Request::Record { target: RecordTarget::Pane(pane), path: Some(path.to_string_lossy().into()) };
+    assert!(matches!(request(&core, owner, record).await, Response::Ok));
+    program.output(b"before\r\n").await;
+    recorded(&path, "before").await;
+
+    let respawn = Request::RespawnPane { pane, argv: Vec::new(), cwd: None, kill: true };
+    assert!(matches!(request(&core, owner, respawn).await, Response::Ok));
+    let program = spawned.recv().await.unwrap().ctl;
+    program.output(b"after\r\n").await;
+    recorded(&path, "after").await;
+    assert!(matches!(request(&core, owner, Request::Resize { pane, cols: 100, rows: 30 }).await, Response::Ok));
+
+    // what the program writes just before it is killed still makes it in
+    program.output(b"last\r\n").await;
+    let kill = Request::Kill { target: KillTarget::Pane(pane), force: true, grace: None };
+    assert!(matches!(request(&core, owner, kill).await, Response::Killed { .. }));
+    let (out, sizes) = recorded(&path, "last").await;
+    assert_eq!(out, "before\r\nafter\r\nlast\r\n");
+    assert_eq!(sizes, [(100, 30)]);
+
+    // stopping ends it at once
+    let Response::PaneSpawned { pane, .. } = request(&core, owner, spawn).await else {
+        panic!("no pane");
+    };
+    let program = spawned.recv().await.unwrap().ctl;
+    let path = dir.join("stopped.cast");
+    let record = |path: Option<String>| Request::Record { target: RecordTarget::Pane(pane), path };
+    assert!(matches!(request(&core, owner, record(Some(path.to_string_lossy().into()))).await, Response::Ok));
+    program.output(b"kept\r\n").await;
+    recorded(&path, "kept").await;
+    assert!(matches!(request(&core, owner, record(None)).await, Response::Ok));
+    program.output(b"dropped\r\n").await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(recorded(&path, "kept").await.0, "kept\r\n");
+    let _ = std::fs::remove_dir_all(&dir);
+}
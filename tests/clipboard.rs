@@ -0,0 +1,79 @@
+mod common;
+
+use common::{create_session, register, request};
+use splicer::ipc::proto::{Event, Request, Response};
+use splicer::server::acl::{Grantee, Role};
+use splicer::server::session::ClipboardPolicy;
+use splicer::term::{Notice, Osc52Filter, Screen};
+use tokio::time::{Duration, sleep, timeout};
+
+#[test]
+fn screen_reports_osc52() {
+    let mut s = Screen::new(20, 2);
+    s.feed(b"a\x1b]52;c;aGVsbG8=\x07b\x1b]52;p;?\x1b\\");
+    assert_eq!(
+        s.take_notices(),
+        vec![
+            Notice::ClipboardSet { selection: "c".into(), data: b"hello".to_vec() },
+            Notice::ClipboardQuery { selection: "p".into() },
+        ]
+    );
+    assert!(s.take_notices().is_empty());
+    assert_eq!(s.lines()[0], "ab");
+}
+
+#[test]
+fn filter_strips_osc52_across_chunks() {
+    let mut f = Osc52Filter::new();
+    let mut out = f.filter(b"x\x1b]5");
+    out.extend(f.filter(b"2;c;aGVs"));
+    out.extend(f.filter(b"bG8=\x1b"));
+    out.extend(f.filter(b"\\y"));
+    assert_eq!(out, b"xy");
+
+    // other OSCs and escapes pass through untouched
+    let title = b"\x1b]0;title\x07\x1b[1m\x1b]5x";
+    assert_eq!(f.filter(title), title);
+}
+
+#[tokio::test(start_paused = true)]
+async fn shared_sessions_stay_out_of_the_owners_buffers() {
+    let (core, mut spawned) = common::fake_core();
+    let (owner, mut events) = register(&core, None).await;
+    let set = Request::SetBuffer { name: None, data: b"secret".to_vec() };
+    assert!(matches!(request(&core, owner, set).await, Response::BufferSet { .. }));
+    let session = create_session(&core, owner).await;
+    let policy = ClipboardPolicy { read: true, write: true };
+    assert!(matches!(request(&core, owner, Request::SetClipboardPolicy { session, policy }).await, Response::Ok));
+    assert!(matches!(request(&core, owner, common::spawn(session, &["sh"])).await, Response::PaneSpawned { .. }));
+    let program = spawned.recv().await.unwrap().ctl;
+
+    program.output(b"\x1b]52;c;?\x07").await;
+    sleep(Duration::from_millis(50)).await;
+    assert_eq!(program.take_input(), b"\x1b]52;c;c2VjcmV0\x07");
+
+    let grant = Request::Grant { session, grantee: Grantee::Uid(4242), role: Role::View };
+    assert!(matches!(request(&core, owner, grant).await, Response::Ok));
+    program.output(b"\x1b]52;c;?\x07").await;
+    sleep(Duration::from_millis(50)).await;
+    assert!(program.take_input().is_empty());
+
+    // writes still reach attached clients, but not the buffers
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    assert!(matches!(request(&core, owner, attach).await, Response::Attached { .. }));
+    program.output(b"\x1b]52;c;aGk=\x07").await;
+    let copied = timeout(Duration::from_secs(1), async {
+        loop {
+            if let Some(Event::Clipboard { data, .. }) = events.recv().await {
+                return data;
+            }
+        }
+    })
+    .await
+    .expect("no Clipboard event");
+    assert_eq!(copied, b"hi");
+    let Response::Buffers { items } = request(&core, owner, Request::ListBuffers).await else {
+        panic!("no buffers");
+    };
+    assert_eq!(items.iter().map(|b| b.size).collect::<Vec<_>>(), [6]);
+}
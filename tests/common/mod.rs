@@ -0,0 +1,98 @@
+//! Helpers for the tests that drive a [`Core`] in-process; each test binary uses some of them.
+#![allow(dead_code)]
+
+use splicer::ipc::proto::{Event, Request, Response};
+use splicer::ipc::server::CoreMsg;
+use splicer::pty::fake::{FakeControl, FakePty};
+use splicer::pty::{Program, PtyConfig, Spawner};
+use splicer::runtime::Core;
+use splicer::server::{peer::PeerCred, peer::PeerId, session::SessionId, state::ServerState};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+/// A program the core started on a [`FakePty`], with what it was asked to run.
+pub struct Spawned {
+    pub program: Program,
+    pub cfg: PtyConfig,
+    pub ctl: FakeControl,
+}
+
+/// A core on a fresh server state, running on its own task.
+pub fn core() -> mpsc::Sender<CoreMsg> {
+    let (core, core_rx) = mpsc::channel(16);
+    tokio::spawn(Core::new(ServerState::new()).run(core_rx));
+    core
+}
+
+/// A core whose panes run on [`FakePty`]s; every spawn shows up on the receiver as it happens.
+pub fn fake_core() -> (mpsc::Sender<CoreMsg>, mpsc::UnboundedReceiver<Spawned>) {
+    run_fake(Core::new(ServerState::new()))
+}
+
+/// Run `core` with its panes on [`FakePty`]s, as [`fake_core`] does.
+pub fn run_fake(core: Core) -> (mpsc::Sender<CoreMsg>, mpsc::UnboundedReceiver<Spawned>) {
+    let (spawned_tx, spawned_rx) = mpsc::unbounded_channel();
+    let spawner: Spawner = Arc::new(move |program, cfg| {
+        let (handle, ctl) = FakePty::new();
+        let _ = spawned_tx.send(Spawned { program, cfg, ctl });
+        Ok(handle)
+    });
+    let (tx, core_rx) = mpsc::channel(16);
+    tokio::spawn(core.with_spawner(spawner).run(core_rx));
+    (tx, spawned_rx)
+}
+
+/// A peer with room for `queue` undelivered events; `cred: None` is the server's owner.
+pub async fn register_with(
+    core: &mpsc::Sender<CoreMsg>,
+    cred: Option<PeerCred>,
+    queue: usize,
+) -> (PeerId, mpsc::Receiver<Event>) {
+    let (ev_tx, ev_rx) = mpsc::channel(queue);
+    let (reply, rx) = oneshot::channel();
+    core.send(CoreMsg::RegisterPeer { ev_tx, cred, reply }).await.unwrap();
+    (rx.await.unwrap(), ev_rx)
+}
+
+pub async fn register(core: &mpsc::Sender<CoreMsg>, cred: Option<PeerCred>) -> (PeerId, mpsc::Receiver<Event>) {
+    register_with(core, cred, 256).await
+}
+
+pub async fn request(core: &mpsc::Sender<CoreMsg>, peer: PeerId, req: Request) -> Response {
+    let (reply, rx) = oneshot::channel();
+    core.send(CoreMsg::FromPeer { peer, req, reply }).await.unwrap();
+    rx.await.unwrap()
+}
+
+pub async fn create_session(core: &mpsc::Sender<CoreMsg>, peer: PeerId) -> SessionId {
+    match request(core, peer, Request::CreateSession { name: None }).await {
+        Response::SessionCreated { session } => session,
+        other => panic!("no session: {other:?}"),
+    }
+}
+
+/// `SpawnPane` of `argv` in a new window of `session`, with nothing else overridden.
+pub fn spawn(session: SessionId, argv: &[&str]) -> Request {
+    spawn_with(session, argv, &[], None, None)
+}
+
+/// [`spawn`] with the pane's own variables, `TERM` and shell.
+pub fn spawn_with(
+    session: SessionId,
+    argv: &[&str],
+    env: &[(&str, &str)],
+    term: Option<&str>,
+    shell: Option<&str>,
+) -> Request {
+    Request::SpawnPane {
+        session,
+        window: None,
+        title: None,
+        cwd: None,
+        cwd_from: None,
+        argv: argv.iter().map(|s| s.to_string()).collect(),
+        env: env.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect(),
+        term: term.map(str::to_string),
+        shell: shell.map(str::to_string),
+    }
+}
@@ -0,0 +1,173 @@
+mod common;
+
+use splicer::ipc::client::IpcClient;
+use splicer::ipc::proto::{ErrorCode, Event, FEATURE_LZ4, GrepMatch, Hello, HelloAck, Request, Response};
+use splicer::ipc::wire::{API_MAJOR, COMPRESS_MIN, FrameError, Kind, MAX_FRAME, frame, read_payload, write_payload};
+use splicer::pty::Backpressure;
+use splicer::server::{pane::PaneId, session::SessionId, window::WindowId};
+use tokio::net::UnixListener;
+use tokio::time::{Duration, timeout};
+
+async fn round_trip(kind: Kind, bytes: Vec<u8>, compress: bool) -> (bool, Vec<u8>) {
+    let (hdr, packed) = frame(kind, 2, bytes, compress);
+    let sent = hdr.compressed;
+    let mut wire = Vec::new();
+    write_payload(&mut wire, hdr, &packed).await.unwrap();
+    let (hdr, bytes) = read_payload(&wire[..], MAX_FRAME).await.unwrap();
+    assert!(!hdr.compressed && matches!(hdr.kind, Kind::Event));
+    assert_eq!(hdr.len as usize, bytes.len());
+    (sent, bytes)
+}
+
+#[tokio::test]
+async fn only_large_frames_are_compressed() {
+    let big: Vec<u8> = (0..20_000u32).flat_map(|i| format!("{i}\r\n").into_bytes()).collect();
+    assert_eq!(round_trip(Kind::Event, big.clone(), true).await, (true, big.clone()));
+    assert_eq!(round_trip(Kind::Event, big.clone(), false).await, (false, big));
+
+    let small = vec![b'x'; COMPRESS_MIN - 1];
+    assert_eq!(round_trip(Kind::Event, small.clone(), true).await, (false, small));
+    // incompressible data goes out as it is
+    let mut x = 0x2545_f491_4f6c_dd1du64;
+    let noise: Vec<u8> = (0..8192)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as u8
+        })
+        .collect();
+    assert_eq!(round_trip(Kind::Event, noise.clone(), true).await, (false, noise));
+}
+
+#[tokio::test]
+async fn oversized_claims_are_rejected() {
+    let mut packed = lz4_flex::compress_prepend_size(b"hello");
+    packed[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+    let (mut hdr, _) = frame(Kind::Event, 2, Vec::new(), false);
+    hdr.compressed = true;
+    hdr.len = packed.len() as u32;
+    let mut wire = Vec::new();
+    write_payload(&mut wire, hdr, &packed).await.unwrap();
+    let err = read_payload(&wire[..], MAX_FRAME).await.err().expect("accepted a 4 GiB frame");
+    assert!(matches!(err, FrameError::TooLarge { len: 0xffff_ffff, .. }), "{err}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn compressed_session_over_the_socket() {
+    let socket = std::env::temp_dir().join(format!("splicer-lz4-{}", std::process::id())).join("splicer.sock");
+    let server = tokio::spawn({
+        let socket = socket.clone();
+        async move { splicer::runtime::serve(&socket, Default::default()).await.is_ok() }
+    });
+    let mut client = None;
+    for _ in 0..50 {
+        if let Ok(c) = IpcClient::connect_with(&socket.to_string_lossy(), FEATURE_LZ4).await {
+            client = Some(c);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let mut client = client.expect("server did not come up");
+    let mut events = client.take_events();
+
+    // the whole stream, not a redraw, even if this client falls behind
+    let block = Request::SetBackpressure { policy: Backpressure::Block };
+    assert!(matches!(client.request(block).await.unwrap(), Response::Ok));
+    let Response::SessionCreated { session } = client.request(Request::CreateSession { name: None }).await.unwrap()
+    else {
+        panic!("no session");
+    };
+    let spawn = common::spawn(session, &["sh", "-c", "sleep 0.2; seq 1 20000; sleep 0.5"]);
+    assert!(matches!(client.request(spawn).await.unwrap(), Response::PaneSpawned { .. }));
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    assert!(matches!(client.request(attach).await.unwrap(), Response::Attached { .. }));
+
+    let text = timeout(Duration::from_secs(5), async {
+        let mut text = Vec::new();
+        while let Some(ev) = events.recv().await {
+            match ev {
+                Event::PtyOutput { chunk, .. } => text.extend_from_slice(&chunk),
+                Event::PaneExited { .. } => break,
+                _ => {}
+            }
+        }
+        String::from_utf8_lossy(&text).into_owned()
+    })
+    .await
+    .expect("pane did not exit");
+    assert!(text.contains("\r\n19999\r\n20000\r\n"), "{} bytes", text.len());
+    server.abort();
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap());
+}
+
+/// Stand in for the server: check the Hello the CLI sends and, if it offers lz4, answer a grep
+/// with one large compressed match, and anything else with an error.
+async fn stand_in(listener: &UnixListener) -> bool {
+    let (sock, _) = listener.accept().await.unwrap();
+    let (mut r, mut w) = sock.into_split();
+    let (_, hello) = read_payload(&mut r, MAX_FRAME).await.unwrap();
+    let hello: Hello = rmp_serde::from_slice(&hello).unwrap();
+    let offered = hello.features & FEATURE_LZ4 != 0;
+    let ack = HelloAck { server_api_major: API_MAJOR, features: hello.features & FEATURE_LZ4 };
+    let (hdr, bytes) = frame(Kind::Response, 0, rmp_serde::to_vec(&ack).unwrap(), false);
+    write_payload(&mut w, hdr, &bytes).await.unwrap();
+    let Ok((_, req)) = read_payload(&mut r, MAX_FRAME).await else { return offered };
+    let resp = if let Ok(Request::Grep { pattern, .. }) = rmp_serde::from_slice(&req) {
+        let item = GrepMatch {
+            session: SessionId::new(1).unwrap(),
+            window: WindowId::new(1).unwrap(),
+            pane: PaneId::new(1).unwrap(),
+            line: 0,
+            text: format!("{}{pattern}", "x".repeat(4 * COMPRESS_MIN)),
+            before: Vec::new(),
+            after: Vec::new(),
+        };
+        Response::Matches { items: vec![item] }
+    } else {
+        Response::Err { code: ErrorCode::NotFound, msg: "stand-in".into() }
+    };
+    let (hdr, bytes) = frame(Kind::Response, 1, rmp_serde::to_vec(&resp).unwrap(), offered);
+    assert_eq!(hdr.compressed, offered && matches!(resp, Response::Matches { .. }));
+    write_payload(&mut w, hdr, &bytes).await.unwrap();
+    offered
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn the_cli_negotiates_compression_when_asked() {
+    let dir = std::env::temp_dir().join(format!("splicer-cli-lz4-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("splicer.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    let run = |args: &'static [&'static str], env: Option<&'static str>| {
+        let socket = socket.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut cmd = std::process::Command::new(env!("CARGO_BIN_EXE_splicer"));
+            cmd.args(args).env("SPLICER_SOCKET", &socket).env_remove("SPLICER_COMPRESS");
+            if let Some(v) = env {
+                cmd.env("SPLICER_COMPRESS", v);
+            }
+            cmd.output().unwrap()
+        })
+    };
+
+    // any command, through the environment
+    let cli = run(&["grep", "needle"], Some("1"));
+    assert!(stand_in(&listener).await, "SPLICER_COMPRESS=1 did not offer lz4");
+    let out = cli.await.unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success() && stdout.contains("xxxxneedle"), "{stdout} {}", String::from_utf8_lossy(&out.stderr));
+
+    for (args, env) in [(&["grep", "needle"][..], None), (&["grep", "needle"][..], Some("0"))] {
+        let cli = run(args, env);
+        assert!(!stand_in(&listener).await, "{env:?} offered lz4");
+        assert!(cli.await.unwrap().status.success());
+    }
+
+    // attach takes it as a flag; the stand-in turns down the session lookup
+    let cli = run(&["attach", "--compress", "s"], None);
+    assert!(stand_in(&listener).await, "attach --compress did not offer lz4");
+    let _ = cli.await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
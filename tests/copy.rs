@@ -0,0 +1,82 @@
+use splicer::client::copy::{CopyCmd, CopyMode, CopyOutcome};
+use splicer::client::keys::{Action, KeyEngine, Outcome, ROOT};
+use splicer::term::Screen;
+use std::time::Instant;
+
+#[test]
+fn screen_keeps_scrollback() {
+    let mut s = Screen::with_history(10, 3, 100);
+    s.feed(b"one\r\ntwo\r\nthree\r\nfour");
+    assert_eq!(s.lines(), vec!["two", "three", "four"]);
+    assert_eq!(s.history().collect::<Vec<_>>(), vec!["one"]);
+    assert_eq!(s.cursor(), (2, 4));
+
+    // the alternate screen neither shows the primary nor feeds history
+    s.feed(b"\x1b[?1049h\x1b[2J\x1b[Hvim\r\n\n\n\n");
+    assert_eq!(s.history_len(), 1);
+    s.feed(b"\x1b[?1049l");
+    assert_eq!(s.lines(), vec!["two", "three", "four"]);
+
+    s.feed(b"\x1b[?2004h");
+    assert!(s.bracketed_paste());
+}
+
+#[test]
+fn screen_wraps_and_erases() {
+    let mut s = Screen::new(4, 2);
+    s.feed(b"abcdef");
+    assert_eq!(s.lines(), vec!["abcd", "ef"]);
+    s.feed(b"\x1b[1;2H\x1b[K");
+    assert_eq!(s.lines(), vec!["a", "ef"]);
+}
+
+fn lines(v: &[&str]) -> Vec<String> {
+    v.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn select_and_copy() {
+    let mut cm = CopyMode::new(lines(&["hello world", "second line"]), (1, 0), 20, 5);
+    cm.apply(CopyCmd::HistoryTop);
+    cm.apply(CopyCmd::NextWord);
+    assert_eq!(cm.cursor(), (0, 6));
+    cm.apply(CopyCmd::BeginSelection);
+    cm.apply(CopyCmd::CursorDown);
+    cm.apply(CopyCmd::StartOfLine);
+    cm.apply(CopyCmd::NextWordEnd);
+    assert_eq!(cm.selection_text().as_deref(), Some("world\nsecond"));
+    assert!(matches!(cm.apply(CopyCmd::CopySelection), CopyOutcome::Copied(t) if t == "world\nsecond"));
+
+    cm.apply(CopyCmd::SelectLine);
+    assert_eq!(cm.selection_text().as_deref(), Some("second line\n"));
+}
+
+#[test]
+fn regex_search_wraps() {
+    let mut cm = CopyMode::new(lines(&["err: a", "ok", "err: b"]), (1, 0), 20, 5);
+    cm.apply(CopyCmd::SearchForward);
+    for c in "err".chars() {
+        cm.prompt_key(c.to_string().parse().unwrap());
+    }
+    assert_eq!(cm.cursor(), (2, 0));
+    cm.prompt_key("Enter".parse().unwrap());
+    assert!(!cm.prompting());
+    cm.apply(CopyCmd::SearchAgain);
+    assert_eq!(cm.cursor(), (0, 0));
+    cm.apply(CopyCmd::SearchReverse);
+    assert_eq!(cm.cursor(), (2, 0));
+}
+
+#[test]
+fn copy_table_is_modal() {
+    let mut eng = KeyEngine::default();
+    let now = Instant::now();
+    eng.feed("C-b".parse().unwrap(), now);
+    assert!(matches!(eng.feed("[".parse().unwrap(), now), Outcome::Run(Action::CopyMode)));
+    eng.enter_mode(eng.copy_table());
+    assert!(matches!(eng.feed("C-n".parse().unwrap(), now), Outcome::Run(Action::Copy(CopyCmd::CursorDown))));
+    // unbound keys never reach the pane while in copy mode
+    assert!(matches!(eng.feed("z".parse().unwrap(), now), Outcome::Consumed));
+    eng.leave_mode();
+    assert_eq!(eng.current_table(), ROOT);
+}
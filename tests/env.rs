@@ -0,0 +1,68 @@
+mod common;
+
+use common::{Spawned, create_session, register, request};
+use splicer::ipc::proto::{ErrorCode, Request, Response};
+use splicer::pty::{Program, PtyConfig};
+use splicer::server::session::SessionId;
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+/// The environment `cfg` leaves the program with: later entries win, as they do at exec.
+fn effective(cfg: &PtyConfig) -> HashMap<String, String> {
+    cfg.env.iter().map(|(k, v)| (k.to_string_lossy().into_owned(), v.to_string_lossy().into_owned())).collect()
+}
+
+fn set(session: SessionId, name: &str, value: &str) -> Request {
+    Request::SetEnvironment { session, name: name.into(), value: Some(value.into()) }
+}
+
+#[tokio::test(start_paused = true)]
+async fn panes_inherit_and_override_the_session_environment() {
+    let (core, mut spawned) = common::fake_core();
+    let (peer, _) = register(&core, None).await;
+    let session = create_session(&core, peer).await;
+    for (name, value) in [("EDITOR", "vi"), ("LANG", "C")] {
+        assert!(matches!(request(&core, peer, set(session, name, value)).await, Response::Ok));
+    }
+
+    assert!(matches!(request(&core, peer, common::spawn(session, &["env"])).await, Response::PaneSpawned { .. }));
+    let Spawned { cfg, .. } = spawned.recv().await.unwrap();
+    let env = effective(&cfg);
+    assert_eq!((env["EDITOR"].as_str(), env["LANG"].as_str()), ("vi", "C"));
+    assert_eq!(cfg.term, None);
+
+    // the pane's own variables win over the session's; its shell runs and is exported
+    let env = [("LANG", "C.UTF-8"), ("PAGER", "less")];
+    let spawn = common::spawn_with(session, &[], &env, Some("screen-256color"), Some("/bin/dash"));
+    assert!(matches!(request(&core, peer, spawn).await, Response::PaneSpawned { .. }));
+    let Spawned { program, cfg, .. } = spawned.recv().await.unwrap();
+    let env = effective(&cfg);
+    assert_eq!((env["EDITOR"].as_str(), env["LANG"].as_str(), env["PAGER"].as_str()), ("vi", "C.UTF-8", "less"));
+    assert_eq!(env["SHELL"], "/bin/dash");
+    assert_eq!(cfg.term.as_deref(), Some("screen-256color"));
+    assert!(matches!(program, Program::Argv { ref argv } if argv == &[OsString::from("/bin/dash")]));
+
+    // a later change reaches the panes spawned after it
+    assert!(matches!(request(&core, peer, set(session, "EDITOR", "ed")).await, Response::Ok));
+    assert!(matches!(request(&core, peer, common::spawn(session, &["env"])).await, Response::PaneSpawned { .. }));
+    assert_eq!(effective(&spawned.recv().await.unwrap().cfg)["EDITOR"], "ed");
+}
+
+#[tokio::test(start_paused = true)]
+async fn invalid_variable_names_are_rejected() {
+    let (core, mut spawned) = common::fake_core();
+    let (peer, _) = register(&core, None).await;
+    let session = create_session(&core, peer).await;
+    let invalid = |resp: Response| matches!(resp, Response::Err { code: ErrorCode::InvalidArgs, .. });
+
+    for name in ["", "A=B", "NUL\0"] {
+        assert!(invalid(request(&core, peer, set(session, name, "x")).await), "{name:?}");
+        let spawn = common::spawn_with(session, &["env"], &[(name, "x")], None, None);
+        assert!(invalid(request(&core, peer, spawn).await), "{name:?}");
+    }
+    let Response::Environment { vars } = request(&core, peer, Request::ShowEnvironment { session }).await else {
+        panic!("no environment");
+    };
+    assert!(vars.is_empty());
+    assert!(spawned.try_recv().is_err(), "nothing was spawned");
+}
@@ -0,0 +1,74 @@
+use splicer::pty::fake::FakePty;
+use splicer::pty::{Backpressure, ExitStatus, KillStage, Sig, SigTarget};
+use splicer::server::{pane::Pane, pane::PaneId, pane::PaneState, pane::TermSize, peer::PeerId};
+use tokio::time::{Duration, sleep, timeout};
+
+#[tokio::test]
+async fn pane_runs_on_scripted_backend() {
+    let (handle, ctl) = FakePty::new();
+    let mut p = Pane::new(PaneId::new(1).unwrap(), "", TermSize::new(20, 4));
+    let peer = PeerId::new(1).unwrap();
+    p.attach_peer(peer, Backpressure::Drop, false).unwrap();
+    p.adopt(handle).unwrap();
+    assert!(p.is_running());
+    assert_eq!(p.input_owner(), Some(peer));
+
+    ctl.output(b"hello\r\nworld").await;
+    let chunk = timeout(Duration::from_secs(1), p.tap(peer).unwrap().recv()).await.unwrap().unwrap();
+    assert_eq!(&chunk[..], b"hello\r\nworld");
+    // the screen's own tap runs on a task
+    for _ in 0..50 {
+        if p.screen().lines().first().map(String::as_str) == Some("hello") {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(p.screen().lines()[..2], ["hello", "world"]);
+
+    p.write_from(peer, b"ls\r").unwrap();
+    p.reply(b"\x1b[1;1R").unwrap();
+    assert_eq!(ctl.take_input(), b"ls\r\x1b[1;1R");
+    p.resize(TermSize::new(40, 10)).unwrap();
+    assert_eq!(ctl.resizes(), vec![(40, 10)]);
+
+    ctl.exit(ExitStatus { code: 3, signal: None });
+    assert!(timeout(Duration::from_secs(1), p.tap(peer).unwrap().recv()).await.unwrap().is_none());
+    p.poll_exit();
+    assert!(matches!(p.state(), PaneState::Exited(s) if s.code == 3));
+}
+
+#[tokio::test]
+async fn shutdown_escalates_on_scripted_backend() {
+    let (handle, ctl) = FakePty::new();
+    assert_eq!(handle.shutdown(false, Duration::from_millis(20)).await, KillStage::Kill);
+    assert_eq!(
+        ctl.signals(),
+        vec![(Sig::Hup, SigTarget::Group), (Sig::Term, SigTarget::Group), (Sig::Kill, SigTarget::Group)]
+    );
+    assert!(handle.signal(Sig::Term).is_err());
+}
+
+#[tokio::test]
+async fn read_only_peers_watch_but_never_type() {
+    let (handle, ctl) = FakePty::new();
+    let mut p = Pane::new(PaneId::new(1).unwrap(), "", TermSize::new(20, 4));
+    let (watcher, typist) = (PeerId::new(1).unwrap(), PeerId::new(2).unwrap());
+    p.attach_peer(watcher, Backpressure::Drop, true).unwrap();
+    p.adopt(handle).unwrap();
+    assert_eq!(p.input_owner(), None);
+    p.attach_peer(typist, Backpressure::Drop, false).unwrap();
+    assert_eq!(p.input_owner(), Some(typist));
+    assert!(p.set_input_owner(Some(watcher)).is_err());
+
+    ctl.output(b"hi").await;
+    let chunk = timeout(Duration::from_secs(1), p.tap(watcher).unwrap().recv()).await.unwrap().unwrap();
+    assert_eq!(&chunk[..], b"hi");
+    // dropped, not refused
+    p.write_from(watcher, b"rm -rf ~\r").unwrap();
+    p.write_from(typist, b"ls\r").unwrap();
+    assert_eq!(ctl.take_input(), b"ls\r");
+
+    // ownership never falls to a watcher
+    p.detach_peer(typist);
+    assert_eq!(p.input_owner(), None);
+}
@@ -0,0 +1,86 @@
+use rmp_serde::{from_slice, to_vec};
+use splicer::ipc::proto::{Event, Hello, HelloAck};
+use splicer::ipc::wire::{FrameError, FrameHeader, Kind, MAX_FRAME, frame, read_payload, write_payload};
+use splicer::runtime::ServerConfig;
+use std::path::PathBuf;
+use tokio::net::UnixStream;
+use tokio::time::{Duration, sleep, timeout};
+
+#[test]
+fn headers_are_checked() {
+    let (hdr, _) = frame(Kind::Response, 1, vec![0; 3], false);
+    let mut b = hdr.to_bytes();
+    assert!(matches!(FrameHeader::from_bytes(b), Ok(FrameHeader { kind: Kind::Response, len: 3, .. })));
+    b[1] = 7;
+    assert!(matches!(FrameHeader::from_bytes(b), Err(FrameError::UnknownKind(7))));
+    b[1] = 0;
+    b[0] = 2;
+    assert!(matches!(FrameHeader::from_bytes(b), Err(FrameError::ApiMajor(2))));
+}
+
+#[tokio::test]
+async fn oversized_frames_are_refused_unread() {
+    let (mut hdr, _) = frame(Kind::Request, 1, Vec::new(), false);
+    hdr.len = u32::MAX;
+    // no payload follows: the length alone must be enough to refuse it
+    let wire = hdr.to_bytes();
+    let err = read_payload(&wire[..], MAX_FRAME).await.err().expect("accepted a 4 GiB frame");
+    assert!(matches!(err, FrameError::TooLarge { len: 0xffff_ffff, max: MAX_FRAME }), "{err}");
+}
+
+/// A server with a 1 KiB frame limit, and a raw connection to it.
+async fn server(name: &str) -> (PathBuf, UnixStream) {
+    let socket = std::env::temp_dir().join(format!("splicer-{name}-{}", std::process::id())).join("splicer.sock");
+    tokio::spawn({
+        let socket = socket.clone();
+        async move { splicer::runtime::serve(&socket, ServerConfig { max_frame: 1024, ..Default::default() }).await.is_ok() }
+    });
+    for _ in 0..50 {
+        if let Ok(sock) = UnixStream::connect(&socket).await {
+            return (socket, sock);
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    panic!("server did not come up");
+}
+
+async fn send(sock: &mut UnixStream, schema_id: u32, bytes: Vec<u8>) {
+    let (hdr, bytes) = frame(Kind::Request, schema_id, bytes, false);
+    write_payload(sock, hdr, &bytes).await.unwrap();
+}
+
+/// The `Bye` reason the server closes with.
+async fn bye(sock: &mut UnixStream) -> String {
+    let (hdr, bytes) = timeout(Duration::from_secs(2), read_payload(&mut *sock, MAX_FRAME)).await.unwrap().unwrap();
+    assert!(matches!(hdr.kind, Kind::Event));
+    let Ok(Event::Bye { reason }) = from_slice(&bytes) else {
+        panic!("not a Bye");
+    };
+    let eof = timeout(Duration::from_secs(2), read_payload(&mut *sock, MAX_FRAME)).await.unwrap();
+    assert!(eof.is_err_and(|e| e.is_disconnect()), "still open after Bye");
+    reason
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn misbehaving_peers_get_bye() {
+    let (socket, mut sock) = server("bye").await;
+    send(&mut sock, 0, to_vec(&Hello { client_api_major: 1, features: 0 }).unwrap()).await;
+    let (_, ack) = read_payload(&mut sock, MAX_FRAME).await.unwrap();
+    assert_eq!(from_slice::<HelloAck>(&ack).unwrap().server_api_major, 1);
+    send(&mut sock, 1, vec![0xc1; 4096]).await;
+    let reason = bye(&mut sock).await;
+    assert!(reason.contains("4096 bytes exceeds the 1024 byte limit"), "{reason}");
+
+    let mut sock = UnixStream::connect(&socket).await.unwrap();
+    send(&mut sock, 0, to_vec(&Hello { client_api_major: 9, features: 0 }).unwrap()).await;
+    let reason = bye(&mut sock).await;
+    assert!(reason.contains("version 9"), "{reason}");
+
+    let mut sock = UnixStream::connect(&socket).await.unwrap();
+    send(&mut sock, 0, to_vec(&Hello { client_api_major: 1, features: 0 }).unwrap()).await;
+    read_payload(&mut sock, MAX_FRAME).await.unwrap();
+    send(&mut sock, 1, b"\xc1garbage".to_vec()).await;
+    let reason = bye(&mut sock).await;
+    assert!(reason.starts_with("malformed request"), "{reason}");
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap());
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{create_session, register, request};
+use splicer::ipc::proto::{GrepScope, Request, Response};
+use tokio::time::{Duration, sleep};
+
+#[tokio::test(start_paused = true)]
+async fn context_is_clamped_to_the_pane() {
+    let (core, mut spawned) = common::fake_core();
+    let (peer, _) = register(&core, None).await;
+    let session = create_session(&core, peer).await;
+    assert!(matches!(request(&core, peer, common::spawn(session, &["sh"])).await, Response::PaneSpawned { .. }));
+    let program = spawned.recv().await.unwrap().ctl;
+    program.output(b"one\r\ntwo\r\nthree").await;
+    sleep(Duration::from_millis(50)).await;
+
+    // any context a peer sends is answered, however large
+    let grep = Request::Grep { pattern: "two".into(), scope: GrepScope::All, context: usize::MAX, ignore_case: false };
+    let Response::Matches { items } = request(&core, peer, grep).await else {
+        panic!("no matches");
+    };
+    assert_eq!(items.len(), 1);
+    assert_eq!((items[0].line, items[0].text.as_str()), (2, "two"));
+    assert_eq!(items[0].before, ["one"]);
+    assert_eq!(items[0].after.first().map(String::as_str), Some("three"));
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::{create_session, register, request};
+use splicer::ipc::proto::{ErrorCode, Event, KillTarget, Request, Response};
+use splicer::server::acl::{Grantee, Role};
+use splicer::server::pane::InputMode;
+use splicer::server::{peer::PeerCred, peer::PeerId};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, timeout};
+
+/// The next ownership change `events` reports.
+async fn owner_change(events: &mut mpsc::Receiver<Event>) -> (InputMode, Option<PeerId>) {
+    timeout(Duration::from_secs(1), async {
+        loop {
+            if let Some(Event::InputOwnerChanged { mode, owner, .. }) = events.recv().await {
+                return (mode, owner);
+            }
+        }
+    })
+    .await
+    .expect("no InputOwnerChanged")
+}
+
+fn ok(resp: Response) {
+    assert!(matches!(resp, Response::Ok), "{resp:?}");
+}
+
+#[tokio::test(start_paused = true)]
+async fn input_is_requested_given_stolen_and_shared() {
+    let (core, mut spawned) = common::fake_core();
+    let (alice, mut alice_events) = register(&core, None).await;
+    let (bob, mut bob_events) = register(&core, Some(PeerCred { uid: 4242, gid: 4242, pid: None })).await;
+
+    let session = create_session(&core, alice).await;
+    ok(request(&core, alice, Request::Grant { session, grantee: Grantee::Uid(4242), role: Role::Input }).await);
+    let Response::PaneSpawned { pane, .. } = request(&core, alice, common::spawn(session, &["cat"])).await else {
+        panic!("no pane");
+    };
+    let program = spawned.recv().await.unwrap().ctl;
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    assert!(matches!(request(&core, alice, attach.clone()).await, Response::Attached { .. }));
+    assert_eq!(owner_change(&mut alice_events).await, (InputMode::Exclusive, Some(alice)));
+    assert!(matches!(request(&core, bob, attach).await, Response::Attached { .. }));
+    let input = |data: &[u8]| Request::Input { pane, data: data.to_vec() };
+    assert!(matches!(request(&core, bob, input(b"x")).await, Response::Err { .. }));
+
+    // asking only tells the owner
+    ok(request(&core, bob, Request::RequestInput { pane, force: false }).await);
+    let asked = timeout(Duration::from_secs(1), async {
+        loop {
+            if let Some(Event::InputRequested { pane: p, peer }) = alice_events.recv().await {
+                return (p, peer);
+            }
+        }
+    })
+    .await
+    .expect("owner was not asked");
+    assert_eq!(asked, (pane, bob));
+    ok(request(&core, alice, Request::GiveInput { pane, to: Some(bob) }).await);
+    assert_eq!(owner_change(&mut bob_events).await, (InputMode::Exclusive, Some(bob)));
+    ok(request(&core, bob, input(b"x")).await);
+
+    // only admins steal
+    let steal = Request::RequestInput { pane, force: true };
+    ok(request(&core, alice, steal.clone()).await);
+    assert_eq!(owner_change(&mut bob_events).await.1, Some(alice));
+    assert!(matches!(request(&core, bob, steal).await, Response::Err { code: ErrorCode::Denied, .. }));
+    assert!(matches!(
+        request(&core, bob, Request::GiveInput { pane, to: Some(bob) }).await,
+        Response::Err { code: ErrorCode::Denied, .. }
+    ));
+
+    ok(request(&core, alice, Request::SetInputMode { pane, mode: InputMode::Shared }).await);
+    assert_eq!(owner_change(&mut bob_events).await, (InputMode::Shared, Some(alice)));
+    ok(request(&core, bob, input(b"y")).await);
+    ok(request(&core, alice, input(b"z")).await);
+    assert_eq!(program.take_input(), b"xyz");
+    ok(request(&core, alice, Request::SetInputMode { pane, mode: InputMode::Exclusive }).await);
+    assert_eq!(owner_change(&mut bob_events).await, (InputMode::Exclusive, Some(alice)));
+
+    // the owner leaving frees the pane instead of handing it on; whoever types next gets it
+    assert!(matches!(request(&core, alice, Request::Detach { target: None }).await, Response::Detached));
+    assert_eq!(owner_change(&mut bob_events).await.1, None);
+    ok(request(&core, bob, input(b"w")).await);
+    assert_eq!(owner_change(&mut bob_events).await.1, Some(bob));
+    assert_eq!(program.take_input(), b"w");
+
+    let kill = Request::Kill { target: KillTarget::Session(session), force: true, grace: None };
+    assert!(matches!(request(&core, alice, kill).await, Response::Killed { .. }));
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_program_that_stops_reading_does_not_hold_up_the_server() {
+    let (core, mut spawned) = common::fake_core();
+    let (peer, _) = register(&core, None).await;
+    let session = create_session(&core, peer).await;
+    let Response::PaneSpawned { pane, .. } = request(&core, peer, common::spawn(session, &["cat"])).await else {
+        panic!("no pane");
+    };
+    let program = spawned.recv().await.unwrap().ctl;
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    assert!(matches!(request(&core, peer, attach).await, Response::Attached { .. }));
+
+    // e.g. after SIGSTOP: typing is refused at once instead of waiting for room
+    program.stall_input(true);
+    let typed = timeout(Duration::from_secs(1), request(&core, peer, Request::Input { pane, data: b"x".to_vec() }));
+    assert!(matches!(typed.await, Ok(Response::Err { code: ErrorCode::Timeout, .. })));
+    let paste = Request::PasteBuffer { pane, name: None, delete: false };
+    let set = Request::SetBuffer { name: None, data: b"y".to_vec() };
+    assert!(matches!(request(&core, peer, set).await, Response::BufferSet { .. }));
+    assert!(matches!(request(&core, peer, paste).await, Response::Err { code: ErrorCode::Timeout, .. }));
+    assert!(matches!(request(&core, peer, Request::ListSessions).await, Response::Sessions { .. }));
+
+    program.stall_input(false);
+    ok(request(&core, peer, Request::Input { pane, data: b"z".to_vec() }).await);
+    assert_eq!(program.take_input(), b"z");
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::{create_session, register, request};
+use splicer::ipc::client::IpcClient;
+use splicer::ipc::proto::{KillTarget, Request, Response, StateScope};
+use splicer::runtime::{Core, ServerConfig};
+use splicer::server::state::ServerState;
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::time::{Duration, sleep};
+
+#[tokio::test(start_paused = true)]
+async fn panes_are_told_where_they_run() {
+    let socket = Path::new("/run/splicer,test/splicer.sock");
+    let (core, mut spawned) = common::run_fake(Core::new(ServerState::new()).with_socket(socket));
+    let (peer, _) = register(&core, None).await;
+    let session = create_session(&core, peer).await;
+    let Response::PaneSpawned { pane, .. } = request(&core, peer, common::spawn(session, &["sh"])).await else {
+        panic!("no pane");
+    };
+    let cfg = spawned.recv().await.unwrap().cfg;
+    let env: HashMap<_, _> = cfg.env.iter().map(|(k, v)| (k.to_str().unwrap(), v.to_str().unwrap())).collect();
+    assert_eq!(env["SPLICER"], format!("/run/splicer,test/splicer.sock,{},{session}", std::process::id()));
+    assert_eq!(env["SPLICER_PANE"], pane.to_string());
+}
+
+/// `splicer ARGS` as a program inside `pane` of the server on `socket` would run it.
+fn run_inside(socket: &Path, pane: &str, args: &[&str]) -> std::process::Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_splicer"))
+        .args(args)
+        .env_remove("SPLICER_SOCKET")
+        .env("SPLICER", format!("{},4242,1", socket.display()))
+        .env("SPLICER_PANE", pane)
+        .output()
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn commands_default_to_the_server_and_pane_they_run_in() {
+    // commas in the socket path must survive the split from the pid and session
+    let dir = std::env::temp_dir().join(format!("splicer-in,side-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let socket = dir.join("splicer,1.sock");
+    tokio::spawn({
+        let socket = socket.clone();
+        async move { splicer::runtime::serve(&socket, ServerConfig::default()).await.is_ok() }
+    });
+    while !socket.exists() {
+        sleep(Duration::from_millis(20)).await;
+    }
+    let mut client = IpcClient::connect(&socket.to_string_lossy()).await.unwrap();
+    let Response::SessionCreated { session } = client.request(Request::CreateSession { name: None }).await.unwrap()
+    else {
+        panic!("no session");
+    };
+    let Response::PaneSpawned { pane, .. } = client.request(common::spawn(session, &["cat"])).await.unwrap() else {
+        panic!("no pane");
+    };
+
+    let out = run_inside(&socket, &pane.to_string(), &["input-mode", "shared"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let Response::State { json } =
+        client.request(Request::GetState { scope: StateScope::Panes { window: None } }).await.unwrap()
+    else {
+        panic!("no state");
+    };
+    assert_eq!(json[0]["input_mode"], "shared");
+
+    // the pane given on the command line wins over the one the command runs in
+    let Response::PaneSpawned { pane: other, .. } = client.request(common::spawn(session, &["cat"])).await.unwrap()
+    else {
+        panic!("no pane");
+    };
+    let modes = async |client: &mut IpcClient| {
+        let Response::State { json } =
+            client.request(Request::GetState { scope: StateScope::Panes { window: None } }).await.unwrap()
+        else {
+            panic!("no state");
+        };
+        json.as_array()
+            .unwrap()
+            .iter()
+            .map(|p| (p["id"].as_str().unwrap().to_owned(), p["input_mode"].as_str().unwrap().to_owned()))
+            .collect::<HashMap<_, _>>()
+    };
+    let before = modes(&mut client).await;
+    let out = run_inside(&socket, &other.to_string(), &["input-mode", &pane.to_string(), "exclusive"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let after = modes(&mut client).await;
+    assert_eq!(after[&pane.to_string()], "exclusive");
+    assert_eq!(after[&other.to_string()], before[&other.to_string()]);
+
+    let kill = Request::Kill { target: KillTarget::Session(session), force: true, grace: None };
+    assert!(matches!(client.request(kill).await.unwrap(), Response::Killed { .. }));
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,104 @@
+use splicer::client::keys::{Action, Binding, Code, Key, KeyEngine, MOD_ALT, MOD_CTRL, Outcome, PREFIX, ROOT};
+use std::time::{Duration, Instant};
+
+fn key(s: &str) -> Key {
+    s.parse().expect("parse key")
+}
+
+#[test]
+fn parse_and_display_roundtrip() {
+    for s in ["C-b", "C-a", "M-x", "C-M-Left", "F5", "Space", "Enter", "BSpace", "-", "C--", "%"] {
+        assert_eq!(key(s).to_string(), s);
+    }
+    assert_eq!(key("C-b"), Key::ctrl('b'));
+    assert_eq!(key("M-Up"), Key::new(Code::Up, MOD_ALT));
+    assert!("C-Nope".parse::<Key>().is_err());
+}
+
+#[test]
+fn key_bytes() {
+    assert_eq!(key("C-b").to_bytes(), vec![0x02]);
+    assert_eq!(key("M-x").to_bytes(), b"\x1bx".to_vec());
+    assert_eq!(key("Up").to_bytes(), b"\x1b[A".to_vec());
+    assert_eq!(Key::new(Code::Left, MOD_CTRL).to_bytes(), b"\x1b[1;5D".to_vec());
+    assert_eq!(key("F1").to_bytes(), b"\x1bOP".to_vec());
+}
+
+#[test]
+fn prefix_then_binding() {
+    let mut eng = KeyEngine::default();
+    let now = Instant::now();
+    assert!(matches!(eng.feed(key("a"), now), Outcome::Forward(b) if b == b"a"));
+    assert!(matches!(eng.feed(key("C-b"), now), Outcome::Consumed));
+    assert_eq!(eng.current_table(), PREFIX);
+    assert!(matches!(eng.feed(key("d"), now), Outcome::Run(Action::Detach)));
+    assert_eq!(eng.current_table(), ROOT);
+
+    // unbound key after prefix is swallowed
+    eng.feed(key("C-b"), now);
+    assert!(matches!(eng.feed(key("z"), now), Outcome::Consumed));
+    assert!(matches!(eng.feed(key("z"), now), Outcome::Forward(_)));
+}
+
+#[test]
+fn set_prefix_moves_send_prefix() {
+    let mut eng = KeyEngine::default();
+    eng.set_prefix(key("C-a"));
+    let now = Instant::now();
+    assert!(matches!(eng.feed(key("C-b"), now), Outcome::Forward(b) if b == vec![0x02]));
+    eng.feed(key("C-a"), now);
+    assert!(matches!(eng.feed(key("C-a"), now), Outcome::Run(Action::SendPrefix)));
+}
+
+#[test]
+fn repeat_window() {
+    let mut eng = KeyEngine::default();
+    eng.set_repeat_time(Duration::from_millis(100));
+    eng.tables_mut().bind(PREFIX, key("n"), Binding::new(Action::Lua("next()".into())).repeat());
+    let t0 = Instant::now();
+
+    eng.feed(key("C-b"), t0);
+    assert!(matches!(eng.feed(key("n"), t0), Outcome::Run(Action::Lua(_))));
+    // still in the repeat window: no prefix needed
+    assert!(matches!(eng.feed(key("n"), t0 + Duration::from_millis(50)), Outcome::Run(Action::Lua(_))));
+    // a non-repeatable key leaves the window and goes to the pane
+    assert!(matches!(eng.feed(key("x"), t0 + Duration::from_millis(60)), Outcome::Forward(_)));
+
+    eng.feed(key("C-b"), t0);
+    eng.feed(key("n"), t0);
+    // window expired
+    assert!(matches!(eng.feed(key("n"), t0 + Duration::from_secs(1)), Outcome::Forward(_)));
+}
+
+#[test]
+fn custom_table() {
+    let mut eng = KeyEngine::default();
+    eng.tables_mut().bind(PREFIX, key("r"), Binding::new(Action::SwitchTable("resize".into())));
+    eng.tables_mut().bind("resize", key("h"), Binding::new(Action::Lua("left()".into())));
+    let now = Instant::now();
+    eng.feed(key("C-b"), now);
+    assert!(matches!(eng.feed(key("r"), now), Outcome::Consumed));
+    assert_eq!(eng.current_table(), "resize");
+    assert!(matches!(eng.feed(key("h"), now), Outcome::Run(Action::Lua(_))));
+    assert_eq!(eng.current_table(), ROOT);
+}
+
+#[test]
+fn lua_binds_keys() {
+    let lua = splicer::lua::LuaRuntime::new().unwrap();
+    lua.exec(
+        r#"
+        splicer.prefix("C-a")
+        splicer.bind("prefix", "|", "switch-table split")
+        splicer.bind("root", "F2", function() end)
+        "#,
+    )
+    .unwrap();
+    let mut eng = KeyEngine::default();
+    lua.apply_keys(&mut eng);
+    assert_eq!(eng.prefix(), key("C-a"));
+    assert!(
+        matches!(eng.tables().lookup(PREFIX, key("|")), Some(Binding { action: Action::SwitchTable(t), .. }) if t == "split")
+    );
+    assert!(matches!(eng.feed(key("F2"), Instant::now()), Outcome::Run(Action::Lua(_))));
+}
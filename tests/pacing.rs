@@ -0,0 +1,183 @@
+mod common;
+
+use common::{create_session, register, request};
+use splicer::ipc::proto::{Event, PolicyTarget, Request, Response};
+use splicer::ipc::server::CoreMsg;
+use splicer::pty::ExitStatus;
+use splicer::pty::fake::FakeControl;
+use splicer::server::pane::RemainOnExit;
+use splicer::term::Screen;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, sleep, timeout};
+
+#[test]
+fn diff_redraws_changed_rows() {
+    let mut s = Screen::new(10, 3);
+    s.feed(b"one\r\ntwo\r\nthree");
+    let before = s.frame();
+    assert!(before.diff(Some(&before)).is_empty());
+
+    s.feed(b"\x1b[2;1Hxyz");
+    let diff = s.frame().diff(Some(&before));
+    assert_eq!(diff, b"\x1b[2;1H\x1b[2Kxyz\x1b[2;4H");
+
+    // a size change redraws everything
+    let mut shown = Screen::new(10, 3);
+    shown.feed(&before.diff(None));
+    s.resize(12, 3);
+    shown.resize(12, 3);
+    shown.feed(&s.frame().diff(Some(&before)));
+    assert_eq!(shown.lines(), s.lines());
+}
+
+/// A peer attached to a new session, after `setup`; returns its events and the pane's program.
+async fn attach(setup: Request) -> (mpsc::Receiver<Event>, FakeControl) {
+    let (core, mut spawned) = common::fake_core();
+    let (peer, ev_rx) = register(&core, None).await;
+
+    assert!(matches!(request(&core, peer, setup).await, Response::Ok));
+    let session = create_session(&core, peer).await;
+    assert!(matches!(request(&core, peer, common::spawn(session, &["sh"])).await, Response::PaneSpawned { .. }));
+    let program = spawned.recv().await.unwrap().ctl;
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    assert!(matches!(request(&core, peer, attach).await, Response::Attached { .. }));
+    (ev_rx, program)
+}
+
+/// Output chunks until the pane exits.
+async fn output(mut events: mpsc::Receiver<Event>) -> Vec<Vec<u8>> {
+    timeout(Duration::from_secs(5), async {
+        let mut chunks = Vec::new();
+        while let Some(ev) = events.recv().await {
+            match ev {
+                Event::PtyOutput { chunk, .. } => chunks.push(chunk),
+                Event::PaneExited { .. } => break,
+                _ => {}
+            }
+        }
+        chunks
+    })
+    .await
+    .expect("pane did not exit")
+}
+
+/// Let the last frame go out, then end the program.
+async fn exit(program: FakeControl) {
+    sleep(Duration::from_millis(200)).await;
+    program.exit(ExitStatus { code: 0, signal: None });
+}
+
+#[tokio::test(start_paused = true)]
+async fn output_is_coalesced_into_paced_frames() {
+    let (events, program) = attach(Request::SetOutputRate { fps: Some(5), diffs: None }).await;
+    // many small writes over half a second
+    for i in 1..=50 {
+        program.output(format!("{i}\r\n").as_bytes()).await;
+        sleep(Duration::from_millis(10)).await;
+    }
+    exit(program).await;
+    let chunks = output(events).await;
+    let text = String::from_utf8_lossy(&chunks.concat()).into_owned();
+    assert!(text.contains("1\r\n2\r\n") && text.contains("50\r\n"), "{text:?}");
+    assert!(chunks.len() <= 6, "{} frames", chunks.len());
+}
+
+#[tokio::test(start_paused = true)]
+async fn diff_peers_see_the_screen() {
+    let (events, program) = attach(Request::SetOutputRate { fps: None, diffs: Some(true) }).await;
+    program.output(b"\x1b[31mred\x1b[0m\r\n").await;
+    for i in 1..=100 {
+        program.output(format!("{i}\r\n").as_bytes()).await;
+    }
+    exit(program).await;
+    let mut shown = Screen::new(80, 24);
+    for chunk in output(events).await {
+        assert!(!chunk.windows(5).any(|w| w == b"\x1b[31m"), "raw output leaked");
+        shown.feed(&chunk);
+    }
+    let lines = shown.lines();
+    assert_eq!(lines.iter().rev().find(|l| !l.is_empty()).map(String::as_str), Some("100"));
+    assert_eq!(lines[0], "78");
+}
+
+/// Like [`attach`], on a pane kept after it exits; the core runs while the sender is kept.
+async fn attach_kept(setup: Request) -> (mpsc::Sender<CoreMsg>, mpsc::Receiver<Event>, FakeControl) {
+    let (core, mut spawned) = common::fake_core();
+    let (peer, ev_rx) = register(&core, None).await;
+    assert!(matches!(request(&core, peer, setup).await, Response::Ok));
+    let session = create_session(&core, peer).await;
+    let keep = Request::SetRemainOnExit { target: PolicyTarget::Session(session), policy: Some(RemainOnExit::Keep) };
+    assert!(matches!(request(&core, peer, keep).await, Response::Ok));
+    assert!(matches!(request(&core, peer, common::spawn(session, &["sh"])).await, Response::PaneSpawned { .. }));
+    let program = spawned.recv().await.unwrap().ctl;
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    assert!(matches!(request(&core, peer, attach).await, Response::Attached { .. }));
+    (core, ev_rx, program)
+}
+
+/// Every output chunk sent within a few seconds.
+async fn all_output(mut events: mpsc::Receiver<Event>) -> Vec<Vec<u8>> {
+    let mut chunks = Vec::new();
+    let _ = timeout(Duration::from_secs(3), async {
+        while let Some(ev) = events.recv().await {
+            if let Event::PtyOutput { chunk, .. } = ev {
+                chunks.push(chunk);
+            }
+        }
+    })
+    .await;
+    chunks
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_exit_line_comes_after_paced_output() {
+    let (_core, events, program) = attach_kept(Request::SetOutputRate { fps: Some(5), diffs: None }).await;
+    // still held back by pacing when the program ends
+    for i in 1..=50 {
+        program.output(format!("{i}\r\n").as_bytes()).await;
+    }
+    program.exit(ExitStatus { code: 3, signal: None });
+    let text = String::from_utf8_lossy(&all_output(events).await.concat()).into_owned();
+    assert!(text.ends_with("49\r\n50\r\n\r\n[exited with code 3]\r\n"), "{text:?}");
+    assert_eq!(text.matches("[exited").count(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn diff_peers_get_the_exit_line_drawn() {
+    let (_core, events, program) = attach_kept(Request::SetOutputRate { fps: None, diffs: Some(true) }).await;
+    program.output(b"last\r\n").await;
+    program.exit(ExitStatus { code: 3, signal: None });
+    let mut shown = Screen::new(80, 24);
+    for chunk in all_output(events).await {
+        assert!(!chunk.starts_with(b"\r\n[exited"), "raw exit line sent");
+        shown.feed(&chunk);
+    }
+    assert_eq!(&shown.lines()[..3], ["last", "", "[exited with code 3]"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn closing_a_windows_last_pane_closes_the_window() {
+    let (core, mut spawned) = common::fake_core();
+    let (peer, mut events) = register(&core, None).await;
+    let session = create_session(&core, peer).await;
+    assert!(matches!(request(&core, peer, common::spawn(session, &["sh"])).await, Response::PaneSpawned { .. }));
+    let Response::PaneSpawned { window, .. } = request(&core, peer, common::spawn(session, &["sh"])).await else {
+        panic!("no pane");
+    };
+    let (first, second) = (spawned.recv().await.unwrap().ctl, spawned.recv().await.unwrap().ctl);
+    let attach = Request::Attach { session, window: None, pane: None, read_only: false };
+    assert!(matches!(request(&core, peer, attach).await, Response::Attached { .. }));
+
+    second.exit(ExitStatus { code: 0, signal: None });
+    let closed = timeout(Duration::from_secs(1), async {
+        loop {
+            if let Some(Event::WindowClosed { window }) = events.recv().await {
+                return window;
+            }
+        }
+    })
+    .await
+    .expect("no WindowClosed");
+    assert_eq!(closed, window);
+    drop(first);
+}
@@ -0,0 +1,147 @@
+use splicer::ipc::client::IpcClient;
+use splicer::ipc::proto::{Event, Request, Response, StateScope};
+use splicer::ipc::wire::{Kind, MAX_FRAME, frame, read_payload, write_payload};
+use splicer::runtime::ServerConfig;
+use splicer::server::acl::{Grantee, Role};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use tokio::time::{Duration, sleep};
+
+/// A server on a socket in a directory that does not exist yet.
+async fn server(name: &str, config: ServerConfig) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("splicer-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let socket = dir.join("private").join("splicer.sock");
+    tokio::spawn({
+        let socket = socket.clone();
+        async move { splicer::runtime::serve(&socket, config).await.is_ok() }
+    });
+    for _ in 0..50 {
+        if socket.exists() {
+            return socket;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    panic!("server did not come up");
+}
+
+fn mode(path: &Path) -> u32 {
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn socket_is_private_and_peers_are_known() {
+    let socket = server("cred", ServerConfig::default()).await;
+    assert_eq!(mode(socket.parent().unwrap()), 0o700);
+    assert_eq!(mode(&socket), 0o600);
+
+    let mut client = IpcClient::connect(&socket.to_string_lossy()).await.unwrap();
+    let Response::State { json } = client.request(Request::GetState { scope: StateScope::Peers }).await.unwrap() else {
+        panic!("no state");
+    };
+    let me = &json[0];
+    assert_eq!(me["uid"], rustix::process::getuid().as_raw());
+    assert_eq!(me["gid"], rustix::process::getgid().as_raw());
+    assert_eq!(me["pid"], std::process::id());
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap().parent().unwrap());
+}
+
+/// Run `splicer grep ARGS anything` against `socket` as uid/gid 65534, from a copy of the binary next to the
+/// socket directory.
+fn grep_as_nobody(socket: &Path, args: &[&str]) -> std::process::Output {
+    let outer = socket.parent().unwrap().parent().unwrap();
+    std::fs::set_permissions(outer, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let bin = outer.join("splicer");
+    if !bin.exists() {
+        std::fs::copy(env!("CARGO_BIN_EXE_splicer"), &bin).unwrap();
+    }
+    std::process::Command::new(bin)
+        .arg("grep")
+        .args(args)
+        .arg("anything")
+        .env("SPLICER_SOCKET", socket)
+        .uid(65534)
+        .gid(65534)
+        .output()
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn foreign_uids_need_the_allow_list() {
+    if !rustix::process::getuid().is_root() {
+        return; // needs to run a client as another user
+    }
+    let socket = server("foreign", ServerConfig::default()).await;
+    let private = grep_as_nobody(&socket, &[]);
+    assert!(String::from_utf8_lossy(&private.stderr).contains("Permission denied"), "{private:?}");
+
+    // an allow list opens the socket up on purpose; SO_PEERCRED turns away whoever isn't on it
+    let socket = server("other", ServerConfig { allow_uids: vec![65533], ..Default::default() }).await;
+    assert_eq!((mode(socket.parent().unwrap()), mode(&socket)), (0o711, 0o666));
+    let denied = grep_as_nobody(&socket, &[]);
+    assert!(String::from_utf8_lossy(&denied.stderr).contains("Denied: uid 65534 may not connect"), "{denied:?}");
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap().parent().unwrap());
+
+    let socket = server("allowed", ServerConfig { allow_uids: vec![65534], ..Default::default() }).await;
+    // admitted, but only into sessions it was granted
+    let ungranted = grep_as_nobody(&socket, &[]);
+    assert!(String::from_utf8_lossy(&ungranted.stderr).contains("Denied: only the server's owner"), "{ungranted:?}");
+    let mut owner = IpcClient::connect(&socket.to_string_lossy()).await.unwrap();
+    let Response::SessionCreated { session } =
+        owner.request(Request::CreateSession { name: Some("shared".into()) }).await.unwrap()
+    else {
+        panic!("no session");
+    };
+    let grant = Request::Grant { session, grantee: Grantee::Uid(65534), role: Role::View };
+    assert!(matches!(owner.request(grant).await.unwrap(), Response::Ok));
+    let allowed = grep_as_nobody(&socket, &["-s", "shared"]);
+    assert!(allowed.status.success(), "{allowed:?}");
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap().parent().unwrap());
+}
+
+#[tokio::test]
+async fn socket_dir_must_be_private() {
+    let base = std::env::temp_dir().join(format!("splicer-dirs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    let (loose, private) = (base.join("loose"), base.join("private"));
+    for dir in [&loose, &private] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    std::fs::set_permissions(&loose, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o700)).unwrap();
+    std::os::unix::fs::symlink(&private, base.join("link")).unwrap();
+
+    for dir in ["loose", "link"] {
+        let socket = base.join(dir).join("splicer.sock");
+        let refused = splicer::runtime::serve(&socket, ServerConfig::default()).await;
+        assert!(refused.is_err_and(|e| e.to_string().contains(dir)), "{dir}");
+        assert!(!socket.exists(), "{dir}");
+    }
+    assert_eq!(mode(&loose), 0o755);
+    let _ = std::fs::remove_dir_all(&base);
+}
+
+/// The reason the server gives for dropping a peer that sent `hello`, raw.
+async fn bye_after(socket: &Path, hello: Option<Vec<u8>>) -> String {
+    let mut sock = tokio::net::UnixStream::connect(socket).await.unwrap();
+    if let Some(bytes) = hello {
+        let (hdr, bytes) = frame(Kind::Request, 0, bytes, false);
+        write_payload(&mut sock, hdr, &bytes).await.unwrap();
+    }
+    let (hdr, bytes) = read_payload(&mut sock, MAX_FRAME).await.unwrap();
+    assert!(matches!(hdr.kind, Kind::Event));
+    let Ok(Event::Bye { reason }) = rmp_serde::from_slice(&bytes) else {
+        panic!("no Bye");
+    };
+    reason
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_hello_must_be_prompt_and_small() {
+    let socket = server("hello", ServerConfig::default()).await;
+    assert!(bye_after(&socket, None).await.contains("no Hello in time"));
+    // the Hello limit is far below the frame limit
+    assert!(bye_after(&socket, Some(vec![0; 4096])).await.contains("exceeds the 1024 byte limit"));
+    let _ = std::fs::remove_dir_all(socket.parent().unwrap().parent().unwrap());
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use common::{create_session, register, request};
+use splicer::ipc::proto::{KillTarget, PipeTarget, Request, Response, StateScope};
+use splicer::ipc::server::CoreMsg;
+use splicer::pty::fake::FakeControl;
+use splicer::server::{pane::PaneId, peer::PeerId};
+use splicer::term::EscapeStripper;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, sleep};
+
+#[test]
+fn stripper_keeps_plain_text_across_chunks() {
+    let mut s = EscapeStripper::new();
+    let mut out = s.strip(b"\x1b[1mbold\x1b[");
+    out.extend(s.strip(b"0m\r\n\ttab\x1b]0;title\x07 \xc3"));
+    out.extend(s.strip(b"\xa9\x07"));
+    assert_eq!(String::from_utf8(out).unwrap(), "bold\n\ttab é");
+}
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("splicer-pipe-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// What `path` holds once it contains `want`, or panics after a few seconds.
+async fn wait_for(path: &Path, want: &str) -> String {
+    for _ in 0..250 {
+        let text = std::fs::read_to_string(path).unwrap_or_default();
+        if text.contains(want) {
+            return text;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{want:?} never reached {}: {:?}", path.display(), std::fs::read_to_string(path));
+}
+
+struct Piped {
+    core: mpsc::Sender<CoreMsg>,
+    spawned: mpsc::UnboundedReceiver<common::Spawned>,
+    owner: PeerId,
+    pane: PaneId,
+    program: FakeControl,
+}
+
+async fn piped_pane() -> Piped {
+    let (core, mut spawned) = common::fake_core();
+    let (owner, _) = register(&core, None).await;
+    let session = create_session(&core, owner).await;
+    let Response::PaneSpawned { pane, .. } = request(&core, owner, common::spawn(session, &["sh"])).await else {
+        panic!("no pane");
+    };
+    let program = spawned.recv().await.unwrap().ctl;
+    Piped { core, spawned, owner, pane, program }
+}
+
+impl Piped {
+    async fn pipe(&self, target: Option<PipeTarget>, plain: bool, input: bool) -> Response {
+        request(&self.core, self.owner, Request::PipePane { pane: self.pane, target, plain, input }).await
+    }
+
+    async fn is_piped(&self) -> bool {
+        let Response::State { json } =
+            request(&self.core, self.owner, Request::GetState { scope: StateScope::Panes { window: None } }).await
+        else {
+            panic!("no state");
+        };
+        json.as_array().unwrap().iter().any(|p| p["id"] == self.pane.to_string() && p["piped"] == true)
+    }
+}
+
+#[tokio::test]
+async fn output_reaches_files_and_commands() {
+    let dir = scratch("targets");
+    let p = piped_pane().await;
+    let (log, cmd_log) = (dir.join("file.log"), dir.join("cmd.log"));
+
+    let file = PipeTarget::File(log.to_string_lossy().into_owned());
+    assert!(matches!(p.pipe(Some(file), false, false).await, Response::Ok));
+    p.program.output(b"\x1b[1mone\x1b[0m\r\n").await;
+    assert_eq!(wait_for(&log, "one").await, "\x1b[1mone\x1b[0m\r\n");
+
+    // a new pipe replaces the old one
+    let cmd = PipeTarget::Command(format!("cat > '{}'", cmd_log.display()));
+    assert!(matches!(p.pipe(Some(cmd), true, false).await, Response::Ok));
+    p.program.output(b"\x1b[1mtwo\x1b[0m\r\n").await;
+    assert_eq!(wait_for(&cmd_log, "two").await, "two\n");
+    assert!(!std::fs::read_to_string(&log).unwrap().contains("two"));
+    assert!(p.is_piped().await);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn a_command_can_type_into_the_pane() {
+    let p = piped_pane().await;
+    let dir = scratch("input");
+    let file = PipeTarget::File(dir.join("x").to_string_lossy().into_owned());
+    assert!(matches!(p.pipe(Some(file), false, true).await, Response::Err { .. }));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let cmd = PipeTarget::Command("read line; echo \"got $line\"".into());
+    assert!(matches!(p.pipe(Some(cmd), true, true).await, Response::Ok));
+    p.program.output(b"ping\r\n").await;
+    let mut typed = Vec::new();
+    for _ in 0..250 {
+        typed.extend(p.program.take_input());
+        if typed.ends_with(b"\n") {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(typed, b"got ping\n");
+}
+
+#[tokio::test]
+async fn pipes_stop_on_unpipe_and_follow_respawns() {
+    let dir = scratch("respawn");
+    let mut p = piped_pane().await;
+    let log = dir.join("out.log");
+    let file = PipeTarget::File(log.to_string_lossy().into_owned());
+    assert!(matches!(p.pipe(Some(file.clone()), false, false).await, Response::Ok));
+    p.program.output(b"before\r\n").await;
+    wait_for(&log, "before").await;
+
+    let respawn = Request::RespawnPane { pane: p.pane, argv: Vec::new(), cwd: None, kill: true };
+    assert!(matches!(request(&p.core, p.owner, respawn).await, Response::Ok));
+    p.program = p.spawned.recv().await.unwrap().ctl;
+    p.program.output(b"after\r\n").await;
+    assert_eq!(wait_for(&log, "after").await, "before\r\nafter\r\n");
+    assert!(p.is_piped().await);
+
+    assert!(matches!(p.pipe(None, false, false).await, Response::Ok));
+    assert!(!p.is_piped().await);
+    p.program.output(b"unpiped\r\n").await;
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), "before\r\nafter\r\n");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn killing_the_pane_lets_the_command_finish() {
+    let dir = scratch("kill");
+    let p = piped_pane().await;
+    let log = dir.join("out.log");
+    // only reaches "done" if it sees the end of its input rather than being killed
+    let cmd = PipeTarget::Command(format!("cat > '{0}'; echo done >> '{0}'", log.display()));
+    assert!(matches!(p.pipe(Some(cmd), false, false).await, Response::Ok));
+    p.program.output(b"last words\r\n").await;
+    let kill = Request::Kill { target: KillTarget::Pane(p.pane), force: true, grace: None };
+    assert!(matches!(request(&p.core, p.owner, kill).await, Response::Killed { .. }));
+    assert_eq!(wait_for(&log, "done").await, "last words\r\ndone\n");
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -1,4 +1,4 @@
-use splicer::pty::{self, Program, PtyConfig};
+use splicer::pty::{self, Backend, KillStage, Program, PtyConfig, Sig, procinfo};
 use tokio::time::{Duration, timeout};
 
 #[tokio::test]
@@ -88,3 +88,135 @@ async fn run_noninteractive() {
         w.changed().await.unwrap();
     }
 }
+
+#[tokio::test]
+async fn term_is_a_real_sigterm() {
+    // a shell that traps TERM proves it was not SIGKILLed
+    let h = pty::spawn(
+        Program::Argv {
+            argv: vec!["sh".into(), "-c".into(), "trap 'exit 7' TERM; echo ready; while :; do sleep 0.05; done".into()],
+        },
+        PtyConfig { cols: 80, rows: 24, cwd: None, env: vec![], term: None },
+    )
+    .expect("spawn sh");
+    let mut rx = h.subscribe();
+    let mut buf = Vec::new();
+    while !String::from_utf8_lossy(&buf).contains("ready") {
+        let ch = timeout(Duration::from_secs(2), rx.recv()).await.expect("no output").expect("closed");
+        buf.extend_from_slice(&ch);
+    }
+
+    h.signal(Sig::Term).expect("signal");
+    let mut w = h.exit_watch();
+    let status = timeout(Duration::from_secs(2), w.wait_for(Option::is_some)).await.expect("no exit").unwrap().clone();
+    assert_eq!(status.map(|s| s.code), Some(7));
+    assert!(h.signal(Sig::Term).is_err());
+}
+
+#[test]
+fn signal_names() {
+    assert_eq!("sigusr1".parse::<Sig>().unwrap(), Sig::Usr1);
+    assert_eq!("WINCH".parse::<Sig>().unwrap().to_string(), "WINCH");
+    assert!("NOPE".parse::<Sig>().is_err());
+}
+
+#[tokio::test]
+async fn staged_shutdown_reports_stage() {
+    let spawn = |script: &str| {
+        pty::spawn(
+            Program::Argv {
+                argv: vec![
+                    "sh".into(),
+                    "-c".into(),
+                    format!("{script}; echo ready; while :; do sleep 0.05; done").into(),
+                ],
+            },
+            PtyConfig { cols: 80, rows: 24, cwd: None, env: vec![], term: None },
+        )
+        .expect("spawn sh")
+    };
+    for (script, want) in
+        [("true", KillStage::Hup), ("trap '' HUP", KillStage::Term), ("trap '' HUP TERM", KillStage::Kill)]
+    {
+        let h = spawn(script);
+        let mut rx = h.subscribe();
+        let mut buf = Vec::new();
+        while !String::from_utf8_lossy(&buf).contains("ready") {
+            buf.extend_from_slice(
+                &timeout(Duration::from_secs(2), rx.recv()).await.expect("no output").expect("closed"),
+            );
+        }
+        assert_eq!(h.shutdown(false, Duration::from_millis(300)).await, want, "{script}");
+        assert_eq!(h.shutdown(false, Duration::from_millis(300)).await, KillStage::Exited);
+    }
+}
+
+#[tokio::test]
+async fn foreground_process_info() {
+    let h = pty::spawn(
+        Program::Argv { argv: vec!["sh".into(), "-c".into(), "cd /tmp && exec sleep 5".into()] },
+        PtyConfig { cols: 80, rows: 24, cwd: None, env: vec![], term: None },
+    )
+    .expect("spawn sh");
+    let mut info = None;
+    for _ in 0..40 {
+        info = h.foreground_pid().and_then(procinfo::read).filter(|i| i.command == "sleep");
+        if info.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let info = info.expect("sleep in the foreground");
+    assert_eq!(Some(info.pid), h.pid());
+    assert_eq!(info.cmdline, ["sleep", "5"]);
+    assert_eq!(info.cwd.as_deref(), Some(std::path::Path::new("/tmp")));
+    h.shutdown(true, Duration::ZERO).await;
+}
+
+#[tokio::test]
+async fn backends_agree() {
+    for backend in [Backend::Native, Backend::Portable] {
+        let h = pty::spawn_with(
+            backend,
+            Program::Argv { argv: vec!["sh".into(), "-c".into(), "read x; stty size; kill -9 $$".into()] },
+            PtyConfig { cols: 80, rows: 24, cwd: None, env: vec![], term: None },
+        )
+        .expect("spawn sh");
+        let mut rx = h.subscribe();
+        h.resize_pixels(100, 30, 1000, 600).unwrap();
+        h.write(b"go\n").await.unwrap();
+        let mut out = Vec::new();
+        while let Ok(Some(ch)) = timeout(Duration::from_secs(2), rx.recv()).await {
+            out.extend_from_slice(&ch);
+        }
+        assert!(String::from_utf8_lossy(&out).contains("30 100"), "{backend:?}: {out:?}");
+        let mut w = h.exit_watch();
+        let status = timeout(Duration::from_secs(2), w.wait_for(Option::is_some)).await.unwrap().unwrap().clone();
+        assert_eq!(status.map(|s| (s.code, s.signal)), Some((1, Some("Killed".into()))), "{backend:?}");
+    }
+}
+
+#[tokio::test]
+async fn term_flag_beats_the_environment() {
+    let cases =
+        [(Some("vt100"), Some("screen"), "vt100"), (None, Some("screen"), "screen"), (None, None, "xterm-256color")];
+    for backend in [Backend::Native, Backend::Portable] {
+        for (term, env, want) in cases {
+            let env = env.map(|v| ("TERM".into(), v.into())).into_iter().collect();
+            let h = pty::spawn_with(
+                backend,
+                Program::Argv { argv: vec!["sh".into(), "-c".into(), "read x; echo \"<$TERM>\"".into()] },
+                PtyConfig { cols: 80, rows: 24, cwd: None, env, term: term.map(Into::into) },
+            )
+            .expect("spawn sh");
+            let mut rx = h.subscribe();
+            h.write(b"go\n").await.unwrap();
+            let mut out = Vec::new();
+            while let Ok(Some(ch)) = timeout(Duration::from_secs(2), rx.recv()).await {
+                out.extend_from_slice(&ch);
+            }
+            let out = String::from_utf8_lossy(&out);
+            assert!(out.contains(&format!("<{want}>")), "{backend:?} {term:?}: {out:?}");
+        }
+    }
+}
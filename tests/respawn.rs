@@ -0,0 +1,76 @@
+use splicer::pty::fake::FakePty;
+use splicer::pty::{ExitStatus, Program, PtyConfig};
+use splicer::server::pane::{AutoRespawn, Pane, PaneId, RemainOnExit, TermSize};
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, sleep, timeout};
+
+fn argv(cmd: &str) -> Program {
+    Program::Argv { argv: vec!["sh".into(), "-c".into(), cmd.into()] }
+}
+
+async fn wait_exit(p: &Pane) {
+    let mut exit = p.exit_watch().expect("spawned");
+    timeout(Duration::from_secs(2), exit.wait_for(Option::is_some)).await.expect("pane did not exit").unwrap();
+}
+
+#[tokio::test]
+async fn respawn_in_place() {
+    let mut p = Pane::new(PaneId::new(1).unwrap(), "", TermSize::new(80, 24));
+    p.spawn(argv("exit 3"), PtyConfig { cols: 80, rows: 24, cwd: None, env: vec![], term: None }).unwrap();
+    wait_exit(&p).await;
+
+    p.respawn(None, None, false).unwrap();
+    assert!(p.is_running());
+    wait_exit(&p).await;
+
+    // a running pane is only replaced when asked to kill it
+    p.respawn(Some(argv("sleep 30")), None, false).unwrap();
+    assert!(p.respawn(None, None, false).is_err());
+    p.respawn(Some(argv("exit 0")), None, true).unwrap();
+    wait_exit(&p).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn killed_output_stays_off_the_new_screen() {
+    let programs = Arc::new(Mutex::new(Vec::new()));
+    let mut p = Pane::new(PaneId::new(1).unwrap(), "", TermSize::new(80, 24));
+    let spawned = programs.clone();
+    p.set_spawner(Arc::new(move |_, _| {
+        let (handle, ctl) = FakePty::new();
+        spawned.lock().unwrap().push(ctl);
+        Ok(handle)
+    }));
+    p.spawn(argv("old"), PtyConfig { cols: 80, rows: 24, cwd: None, env: vec![], term: None }).unwrap();
+    let old = programs.lock().unwrap()[0].clone();
+
+    // written, but not yet read into the screen model, when the respawn kills it
+    old.output(b"stale").await;
+    p.respawn(Some(argv("new")), None, true).unwrap();
+    let new = programs.lock().unwrap()[1].clone();
+    new.output(b"fresh").await;
+    sleep(Duration::from_millis(50)).await;
+    assert_eq!(p.screen().lines()[0], "fresh");
+}
+
+#[test]
+fn respawn_backoff_doubles_up_to_max() {
+    let mut p = Pane::new(PaneId::new(1).unwrap(), "", TermSize::new(80, 24));
+    assert_eq!(p.respawn_delay(), None);
+    p.set_auto_respawn(Some(AutoRespawn { min: Duration::from_secs(1), max: Duration::from_secs(3) }));
+    let delays: Vec<_> = (0..4).map(|_| p.respawn_delay().unwrap().as_secs()).collect();
+    assert_eq!(delays, [1, 2, 3, 3]);
+}
+
+#[test]
+fn remain_on_exit_policy() {
+    let ok = ExitStatus { code: 0, signal: None };
+    let failed = ExitStatus { code: 3, signal: None };
+    let killed = ExitStatus { code: 1, signal: Some("Killed".into()) };
+    assert_eq!((failed.to_string(), killed.to_string()), ("code 3".into(), "signal Killed".into()));
+
+    let on_failure: RemainOnExit = "on-failure".parse().unwrap();
+    assert!(!on_failure.keeps(&ok) && on_failure.keeps(&failed) && on_failure.keeps(&killed));
+    assert!(RemainOnExit::Keep.keeps(&ok));
+    assert!(!RemainOnExit::default().keeps(&failed));
+    assert!("sometimes".parse::<RemainOnExit>().is_err());
+}
@@ -0,0 +1,29 @@
+use splicer::server::{pane::Pane, pane::PaneId, pane::TermSize, window::Window, window::WindowId};
+use splicer::term::{Notice, Screen};
+
+#[test]
+fn screen_reports_titles() {
+    let mut s = Screen::new(20, 2);
+    s.feed(b"\x1b]0;vim\x07\x1b]2;user@host: ~; ls\x1b\\");
+    assert_eq!(s.take_notices(), vec![Notice::Title("vim".into()), Notice::Title("user@host: ~; ls".into())]);
+}
+
+#[test]
+fn window_name_follows_focused_pane() {
+    let mut w = Window::new(WindowId::new(1).unwrap(), "1");
+    let mut pane = Pane::new(PaneId::new(1).unwrap(), "", TermSize::new(80, 24));
+    pane.title = "htop".into();
+    w.add_pane(pane).unwrap();
+    assert!(w.update_name());
+    assert_eq!(w.name, "htop");
+    assert!(!w.update_name());
+
+    // an explicit rename sticks until automatic naming is switched back on
+    w.name = "logs".into();
+    w.set_automatic_name(false);
+    assert!(!w.update_name());
+    assert_eq!(w.name, "logs");
+    w.set_automatic_name(true);
+    assert!(w.update_name());
+    assert_eq!(w.name, "htop");
+}